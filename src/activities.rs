@@ -1,14 +1,31 @@
 mod app;
+mod build_log;
+mod commit_status;
+mod components;
+mod forge;
 mod forgejo;
 mod git;
 mod git_auth;
+mod github;
+mod gitlab;
+mod image_backend;
 mod process;
+mod pull_request;
+mod registry_auth;
+mod scan;
+mod signing;
 mod workspace;
 
 pub use app::*;
+pub use build_log::build_log_path;
+pub use commit_status::*;
+pub use components::*;
 pub use forgejo::*;
 pub use git::*;
 pub use git_auth::git_command_for_url;
+pub use pull_request::*;
+pub use registry_auth::prepare_registry_auth;
+pub use scan::*;
 
 use crate::core::app::image::Image;
 use temporalio_macros::activities;
@@ -27,6 +44,22 @@ impl PlatformActivities {
         publish_image_from_source(ctx, input).await
     }
 
+    #[activity]
+    pub async fn plan_source_components(
+        ctx: ActivityContext,
+        input: PlanSourceComponentsInput,
+    ) -> Result<Vec<SourceComponent>, ActivityError> {
+        plan_source_components(ctx, input).await
+    }
+
+    #[activity]
+    pub async fn scan_image(
+        ctx: ActivityContext,
+        input: ScanImageInput,
+    ) -> Result<ImageScanReport, ActivityError> {
+        scan_image(ctx, input).await
+    }
+
     #[activity]
     pub async fn update_gitops_image(
         ctx: ActivityContext,
@@ -59,6 +92,38 @@ impl PlatformActivities {
         delete_gitops_app(ctx, input).await
     }
 
+    #[activity]
+    pub async fn rollback_gitops_app(
+        ctx: ActivityContext,
+        input: RollbackGitopsAppInput,
+    ) -> Result<RollbackGitopsAppResult, ActivityError> {
+        rollback_gitops_app(ctx, input).await
+    }
+
+    #[activity]
+    pub async fn remove_gitops_components(
+        ctx: ActivityContext,
+        input: RemoveGitopsComponentsInput,
+    ) -> Result<RemoveGitopsComponentsResult, ActivityError> {
+        remove_gitops_components(ctx, input).await
+    }
+
+    #[activity]
+    pub async fn update_gitops_secrets(
+        ctx: ActivityContext,
+        input: UpdateGitopsSecretsInput,
+    ) -> Result<UpdateGitopsSecretsResult, ActivityError> {
+        update_gitops_secrets(ctx, input).await
+    }
+
+    #[activity]
+    pub async fn create_preview_environment(
+        ctx: ActivityContext,
+        input: CreatePreviewEnvironmentInput,
+    ) -> Result<CreatePreviewEnvironmentResult, ActivityError> {
+        create_preview_environment(ctx, input).await
+    }
+
     #[activity]
     pub async fn enqueue_gitops_publish(
         ctx: ActivityContext,
@@ -75,6 +140,14 @@ impl PlatformActivities {
         find_gitops_app_targets(ctx, input).await
     }
 
+    #[activity]
+    pub async fn find_promotion_images(
+        ctx: ActivityContext,
+        input: FindPromotionImagesInput,
+    ) -> Result<Vec<PromotionImage>, ActivityError> {
+        find_promotion_images(ctx, input).await
+    }
+
     #[activity]
     pub async fn find_gitops_source_repos(
         ctx: ActivityContext,
@@ -147,6 +220,22 @@ impl PlatformActivities {
         forgejo_create_commit_status(ctx, input).await
     }
 
+    #[activity]
+    pub async fn create_commit_status(
+        ctx: ActivityContext,
+        input: CreateCommitStatusInput,
+    ) -> Result<(), ActivityError> {
+        create_commit_status(ctx, input).await
+    }
+
+    #[activity]
+    pub async fn comment_pull_request(
+        ctx: ActivityContext,
+        input: CommentPullRequestInput,
+    ) -> Result<(), ActivityError> {
+        comment_pull_request(ctx, input).await
+    }
+
     #[activity]
     pub async fn forgejo_ensure_gitops_repo_seeded(
         ctx: ActivityContext,
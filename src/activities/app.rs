@@ -1,9 +1,18 @@
 use super::{
+    build_log::BuildLog,
+    git::non_retryable_error,
     git_auth::git_command_for_url,
-    process::{run_checked_command, run_command},
+    image_backend::ImageBackend,
+    process::{run_checked_command, run_command, run_logged_command},
+    signing::sign_image,
     workspace::TempWorkspace,
 };
-use crate::core::app::{builder::Builder, image::Image, source::Source};
+use crate::core::app::{
+    build_config::{BUILD_CONFIG_FILE, BuildConfig, BuilderKind, RepoConfig},
+    builder::Builder,
+    image::Image,
+    source::Source,
+};
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use std::env;
@@ -11,25 +20,32 @@ use temporalio_sdk::activities::{ActivityContext, ActivityError};
 use tokio::{fs::remove_dir_all, process::Command};
 use tracing::{info, warn};
 
-const BUILD_CACHE_TAG: &str = "buildcache-image";
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublishImageFromSourceInput {
     pub source: Source,
     pub registry: String,
     pub image_owner: String,
     pub image_repository: String,
+    #[serde(default)]
+    pub component: Option<String>,
 }
 
 pub async fn publish_image_from_source(
     ctx: ActivityContext,
     input: PublishImageFromSourceInput,
 ) -> Result<Image, ActivityError> {
+    let backend = ImageBackend::from_env()?;
     let (_workspace, source) = source_with_activity_workspace(input.source);
     let source = pull_source(&ctx, source).await?;
+    let config = read_repo_config(&source)
+        .await?
+        .component_build(input.component.as_deref())
+        .map_err(|error| non_retryable_error(anyhow!(error)))?;
+    info!(component = ?input.component, config = ?config, "resolved build configuration");
     let builder = detect_builder(
         &ctx,
         &source,
+        &config,
         &input.registry,
         &input.image_owner,
         &input.image_repository,
@@ -37,17 +53,29 @@ pub async fn publish_image_from_source(
     .await?;
     let image = builder_image(&builder);
 
-    if image_exists_in_registry(&ctx, &image).await? {
+    let image = if backend
+        .image_exists(&ctx, &image, &config.platforms)
+        .await?
+    {
         info!(image = %image, "image already exists in registry");
-        return Ok(image);
-    }
-
-    let image = build_image(&ctx, builder).await?;
-    push_image(&ctx, &image).await?;
+        image
+    } else {
+        let log = BuildLog::for_activity(&ctx).await;
+        build_image(&ctx, backend, builder, &config, log.as_ref()).await?
+    };
+
+    let digest = backend.image_digest(&ctx, &image).await?;
+    info!(image = %image, digest = %digest, "resolved pushed image digest");
+    let image = Image {
+        digest: Some(digest),
+        ..image
+    };
+
+    sign_image(&ctx, &image, &source).await?;
     Ok(image)
 }
 
-fn source_with_activity_workspace(source: Source) -> (Option<TempWorkspace>, Source) {
+pub(super) fn source_with_activity_workspace(source: Source) -> (Option<TempWorkspace>, Source) {
     match source {
         Source::Git {
             name,
@@ -73,7 +101,10 @@ fn source_with_activity_workspace(source: Source) -> (Option<TempWorkspace>, Sou
     }
 }
 
-async fn pull_source(ctx: &ActivityContext, source: Source) -> Result<Source, ActivityError> {
+pub(super) async fn pull_source(
+    ctx: &ActivityContext,
+    source: Source,
+) -> Result<Source, ActivityError> {
     match source {
         Source::Git {
             name,
@@ -125,9 +156,25 @@ async fn pull_source(ctx: &ActivityContext, source: Source) -> Result<Source, Ac
     }
 }
 
+pub(super) async fn read_repo_config(source: &Source) -> Result<RepoConfig, ActivityError> {
+    let Source::Git { path, .. } = source else {
+        return Ok(RepoConfig::default());
+    };
+
+    let contents = match tokio::fs::read_to_string(path.join(BUILD_CONFIG_FILE)).await {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(RepoConfig::default());
+        }
+        Err(error) => return Err(anyhow!("failed to read {BUILD_CONFIG_FILE}: {error}").into()),
+    };
+    RepoConfig::parse(&contents).map_err(|error| non_retryable_error(anyhow!(error)))
+}
+
 async fn detect_builder(
     ctx: &ActivityContext,
     source: &Source,
+    config: &BuildConfig,
     registry: &str,
     image_owner: &str,
     image_repository: &str,
@@ -143,20 +190,59 @@ async fn detect_builder(
                 owner: image_owner.to_owned(),
                 repository: image_repository.to_owned(),
                 tag: revision.to_string(),
+                digest: None,
             };
-
-            if path.join("Dockerfile").exists() {
-                return Ok(Builder::Dockerfile(path.to_path_buf(), image));
+            let context = match &config.context {
+                Some(context) => path.join(context),
+                None => path.to_path_buf(),
+            };
+            if !context.is_dir() {
+                return Err(non_retryable_error(anyhow!(
+                    "build context {} does not exist",
+                    config.context.as_deref().unwrap_or(".")
+                )));
             }
 
-            let mut command = Command::new("nixpacks");
-            command.args(["detect", "."]).current_dir(path);
-            let output = run_command(ctx, &mut command, "nixpacks detect").await?;
-            if output.status.success() && output.stdout.len() > 1 {
-                Ok(Builder::Nixpacks(path.to_path_buf(), image))
-            } else {
-                Err(anyhow!("no buildable code detected").into())
+            let dockerfile = config.dockerfile.as_deref().unwrap_or("Dockerfile");
+            let kind = match config.builder {
+                Some(kind) => kind,
+                None if config.dockerfile.is_some() || context.join(dockerfile).exists() => {
+                    BuilderKind::Dockerfile
+                }
+                None if context.join("project.toml").exists() => BuilderKind::Buildpacks,
+                None => {
+                    let mut command = Command::new("nixpacks");
+                    command.args(["detect", "."]).current_dir(&context);
+                    let output = run_command(ctx, &mut command, "nixpacks detect").await?;
+                    if output.status.success() && output.stdout.len() > 1 {
+                        BuilderKind::Nixpacks
+                    } else {
+                        return Err(anyhow!("no buildable code detected").into());
+                    }
+                }
+            };
+
+            if kind == BuilderKind::Dockerfile && !context.join(dockerfile).is_file() {
+                return Err(non_retryable_error(anyhow!(
+                    "{dockerfile} does not exist in the build context"
+                )));
+            }
+            if kind != BuilderKind::Dockerfile && config.target.is_some() {
+                return Err(non_retryable_error(anyhow!(
+                    "build.target requires the dockerfile builder"
+                )));
             }
+            if kind == BuilderKind::Buildpacks && !config.platforms.is_empty() {
+                return Err(non_retryable_error(anyhow!(
+                    "build.platforms is not supported with the buildpacks builder"
+                )));
+            }
+
+            Ok(match kind {
+                BuilderKind::Dockerfile => Builder::Dockerfile(context, image),
+                BuilderKind::Nixpacks => Builder::Nixpacks(context, image),
+                BuilderKind::Buildpacks => Builder::Buildpacks(context, image),
+            })
         }
         Source::Docker(image) => Ok(Builder::Vendor(
             image.clone(),
@@ -165,6 +251,7 @@ async fn detect_builder(
                 owner: image_owner.to_owned(),
                 repository: image_repository.to_owned(),
                 tag: image.tag.clone(),
+                digest: None,
             },
         )),
     }
@@ -172,184 +259,66 @@ async fn detect_builder(
 
 fn builder_image(builder: &Builder) -> Image {
     match builder {
-        Builder::Dockerfile(_, image) | Builder::Nixpacks(_, image) => image.clone(),
+        Builder::Dockerfile(_, image)
+        | Builder::Nixpacks(_, image)
+        | Builder::Buildpacks(_, image) => image.clone(),
         Builder::Vendor(_, image) => image.clone(),
     }
 }
 
-async fn image_exists_in_registry(
+async fn build_image(
     ctx: &ActivityContext,
-    image: &Image,
-) -> Result<bool, ActivityError> {
-    let image_ref = format!("{image}");
-    let mut command = Command::new("docker");
-    command.args(["manifest", "inspect", &image_ref]);
-    let output = run_command(ctx, &mut command, "docker manifest inspect").await?;
-    Ok(output.status.success())
-}
-
-async fn build_image(ctx: &ActivityContext, builder: Builder) -> Result<Image, ActivityError> {
+    backend: ImageBackend,
+    builder: Builder,
+    config: &BuildConfig,
+    log: Option<&BuildLog>,
+) -> Result<Image, ActivityError> {
     match builder {
         Builder::Dockerfile(path, image) => {
-            info!("building container image with Dockerfile");
-            build_docker_context(ctx, &path, None, image).await
+            info!(backend = ?backend, "building container image with Dockerfile");
+            backend
+                .build_and_push(
+                    ctx,
+                    &path,
+                    config.dockerfile.as_deref(),
+                    &image,
+                    config,
+                    log,
+                )
+                .await?;
+            Ok(image)
         }
         Builder::Nixpacks(path, image) => {
-            info!("building container image with Nixpacks");
+            info!(backend = ?backend, "building container image with Nixpacks");
             let mut command = Command::new("nixpacks");
             command
                 .args(["build", ".", "--out", ".", "--current-dir"])
                 .arg("--no-error-without-start")
                 .current_dir(&path);
-            run_checked_command(ctx, &mut command, "nixpacks generate").await?;
-
-            build_docker_context(ctx, &path, Some(".nixpacks/Dockerfile"), image).await
-        }
-        Builder::Vendor(source_image, image) => {
-            let source_ref = format!("{source_image}");
-            let image_ref = format!("{image}");
-
-            let mut command = Command::new("docker");
-            command.args(["pull", &source_ref]);
-            run_checked_command(ctx, &mut command, "docker pull source image").await?;
-
-            let mut command = Command::new("docker");
-            command.args(["tag", &source_ref, &image_ref]);
-            run_checked_command(ctx, &mut command, "docker tag source image").await?;
-
+            run_logged_command(ctx, &mut command, "nixpacks generate", log).await?;
+
+            backend
+                .build_and_push(
+                    ctx,
+                    &path,
+                    Some(".nixpacks/Dockerfile"),
+                    &image,
+                    config,
+                    log,
+                )
+                .await?;
             Ok(image)
         }
-    }
-}
-
-async fn build_docker_context(
-    ctx: &ActivityContext,
-    context: &std::path::Path,
-    dockerfile: Option<&str>,
-    image: Image,
-) -> Result<Image, ActivityError> {
-    let image_ref = format!("{image}");
-    let cache_ref = pull_docker_build_cache(ctx, &image).await?;
-    let mut command = Command::new("docker");
-    command.env("DOCKER_BUILDKIT", "1");
-    command.arg("build");
-    configure_docker_build_network(&mut command);
-    if let Some(cache_ref) = &cache_ref {
-        command.args(["--cache-from", cache_ref]);
-    }
-    if let Some(dockerfile) = dockerfile {
-        command.args(["--file", dockerfile]);
-    }
-    command.args(["--tag", &image_ref]);
-    if docker_build_registry_cache_enabled() {
-        command.args(["--tag", &docker_build_cache_ref(&image)]);
-    }
-    command.arg(".").current_dir(context);
-    run_checked_command(ctx, &mut command, "docker build").await?;
-    push_docker_build_cache(ctx, &image).await?;
-    Ok(image)
-}
-
-async fn pull_docker_build_cache(
-    ctx: &ActivityContext,
-    image: &Image,
-) -> Result<Option<String>, ActivityError> {
-    if !docker_build_registry_cache_enabled() {
-        return Ok(None);
-    }
-
-    let cache_ref = docker_build_cache_ref(image);
-    let mut command = Command::new("docker");
-    command.args(["pull", &cache_ref]);
-    let output = run_command(ctx, &mut command, "docker pull build cache").await?;
-    if output.status.success() {
-        info!(cache = %cache_ref, "pulled Docker build cache");
-        Ok(Some(cache_ref))
-    } else {
-        warn!(cache = %cache_ref, "Docker build cache unavailable");
-        Ok(None)
-    }
-}
-
-async fn push_docker_build_cache(
-    ctx: &ActivityContext,
-    image: &Image,
-) -> Result<(), ActivityError> {
-    if !docker_build_registry_cache_enabled() {
-        return Ok(());
-    }
-
-    let cache_ref = docker_build_cache_ref(image);
-    let mut command = Command::new("docker");
-    command.args(["push", &cache_ref]);
-    let output = run_command(ctx, &mut command, "docker push build cache").await?;
-    if output.status.success() {
-        info!(cache = %cache_ref, "pushed Docker build cache");
-    } else {
-        warn!(cache = %cache_ref, "failed to push Docker build cache");
-    }
-    Ok(())
-}
-
-async fn push_image(ctx: &ActivityContext, image: &Image) -> Result<(), ActivityError> {
-    let image_ref = format!("{image}");
-    let mut command = Command::new("docker");
-    command.args(["push", &image_ref]);
-    run_checked_command(ctx, &mut command, "docker push").await?;
-    Ok(())
-}
-
-fn configure_docker_build_network(command: &mut Command) {
-    if let Ok(network) = env::var("DOCKER_BUILD_NETWORK") {
-        let network = network.trim();
-        if !network.is_empty() {
-            command.args(["--network", network]);
+        Builder::Buildpacks(path, image) => {
+            info!(backend = ?backend, "building container image with Buildpacks");
+            backend
+                .buildpacks_build_and_push(ctx, &path, &image, config, log)
+                .await?;
+            Ok(image)
         }
-    }
-
-    if let Ok(add_hosts) = env::var("DOCKER_BUILD_ADD_HOSTS") {
-        for add_host in add_hosts
-            .split(',')
-            .map(str::trim)
-            .filter(|add_host| !add_host.is_empty())
-        {
-            command.args(["--add-host", add_host]);
+        Builder::Vendor(source_image, image) => {
+            backend.copy_image(ctx, &source_image, &image, log).await?;
+            Ok(image)
         }
     }
 }
-
-fn docker_build_registry_cache_enabled() -> bool {
-    env::var("DOCKER_BUILD_REGISTRY_CACHE").map_or(true, |value| {
-        !matches!(
-            value.trim().to_ascii_lowercase().as_str(),
-            "0" | "false" | "no" | "off"
-        )
-    })
-}
-
-fn docker_build_cache_ref(image: &Image) -> String {
-    format!(
-        "{}/{}/{}:{BUILD_CACHE_TAG}",
-        image.registry, image.owner, image.repository
-    )
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn docker_build_cache_ref_uses_stable_app_tag() {
-        let image = Image {
-            registry: "registry.registry.svc.cluster.local".to_string(),
-            owner: "apps/khuedoan".to_string(),
-            repository: "blog".to_string(),
-            tag: "revision".to_string(),
-        };
-
-        assert_eq!(
-            docker_build_cache_ref(&image),
-            "registry.registry.svc.cluster.local/apps/khuedoan/blog:buildcache-image"
-        );
-    }
-}
@@ -0,0 +1,76 @@
+use super::workspace::sanitize_path_component;
+use std::{env, path::PathBuf, sync::Arc};
+use temporalio_sdk::activities::ActivityContext;
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+use tracing::warn;
+
+pub fn build_log_dir() -> PathBuf {
+    env::var("NETAMOS_BUILD_LOG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("/tmp/netamos/build-logs"))
+}
+
+pub fn build_log_path(workflow_id: &str) -> PathBuf {
+    build_log_dir().join(format!("{}.log", sanitize_path_component(workflow_id)))
+}
+
+#[derive(Clone)]
+pub(crate) struct BuildLog {
+    file: Arc<Mutex<fs::File>>,
+}
+
+impl BuildLog {
+    pub(crate) async fn for_activity(ctx: &ActivityContext) -> Option<Self> {
+        let workflow_id = &ctx.info().workflow_execution.as_ref()?.workflow_id;
+        let path = build_log_path(workflow_id);
+        let result = async {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(file) => Some(Self {
+                file: Arc::new(Mutex::new(file)),
+            }),
+            Err(error) => {
+                warn!(path = %path.display(), error = %error, "failed to open build log");
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn write_line(&self, line: &str) {
+        let mut file = self.file.lock().await;
+        let result = async {
+            file.write_all(line.as_bytes()).await?;
+            file.write_all(b"\n").await?;
+            file.flush().await
+        }
+        .await;
+        if let Err(error) = result {
+            warn!(error = %error, "failed to write build log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_log_path_sanitizes_workflow_id() {
+        let path = build_log_path("push-to-deploy/blog:6c1c137");
+
+        assert_eq!(
+            path.file_name().and_then(|name| name.to_str()),
+            Some("push-to-deploy-blog-6c1c137.log")
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use super::{
+    forge::{COMMIT_STATUS_CONTEXT, CommitStatus, ForgeNotifier},
+    forgejo::{
+        ForgejoCommitStatusTarget, ForgejoCreateCommitStatusInput, forgejo_create_commit_status,
+    },
+    github::GithubForge,
+    gitlab::GitlabForge,
+};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use serde_json::Value as JsonValue;
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum CommitStatusTarget {
+    Forgejo(ForgejoCommitStatusTarget),
+    Github(GithubCommitStatusTarget),
+    Gitlab(GitlabCommitStatusTarget),
+}
+
+impl<'de> Deserialize<'de> for CommitStatusTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = JsonValue::deserialize(deserializer)?;
+        let provider = match value
+            .as_object_mut()
+            .and_then(|fields| fields.remove("provider"))
+        {
+            None => "forgejo".to_string(),
+            Some(JsonValue::String(provider)) => provider,
+            Some(_) => return Err(D::Error::custom("provider must be a string")),
+        };
+        match provider.as_str() {
+            "forgejo" => serde_json::from_value(value).map(Self::Forgejo),
+            "github" => serde_json::from_value(value).map(Self::Github),
+            "gitlab" => serde_json::from_value(value).map(Self::Gitlab),
+            other => {
+                return Err(D::Error::unknown_variant(
+                    other,
+                    &["forgejo", "github", "gitlab"],
+                ));
+            }
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommitStatusTarget {
+    pub api_url: String,
+    pub repo: String,
+    pub sha: String,
+    pub target_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitlabCommitStatusTarget {
+    pub api_url: String,
+    pub project_id: u64,
+    pub sha: String,
+    pub target_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCommitStatusInput {
+    pub target: CommitStatusTarget,
+    pub state: String,
+    pub description: String,
+}
+
+pub async fn create_commit_status(
+    ctx: ActivityContext,
+    input: CreateCommitStatusInput,
+) -> Result<(), ActivityError> {
+    match input.target {
+        CommitStatusTarget::Forgejo(target) => {
+            forgejo_create_commit_status(
+                ctx,
+                ForgejoCreateCommitStatusInput {
+                    target,
+                    state: input.state,
+                    description: input.description,
+                },
+            )
+            .await
+        }
+        CommitStatusTarget::Github(target) => {
+            GithubForge::new(&target.api_url)
+                .create_commit_status(
+                    &target.repo,
+                    &target.sha,
+                    &commit_status(input.state, input.description, target.target_url),
+                )
+                .await?;
+            Ok(())
+        }
+        CommitStatusTarget::Gitlab(target) => {
+            GitlabForge::new(&target.api_url)
+                .create_commit_status(
+                    &target.project_id.to_string(),
+                    &target.sha,
+                    &commit_status(input.state, input.description, target.target_url),
+                )
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+fn commit_status(state: String, description: String, target_url: String) -> CommitStatus {
+    CommitStatus {
+        context: COMMIT_STATUS_CONTEXT.to_string(),
+        state,
+        description,
+        target_url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn commit_status_target_is_tagged_by_provider() {
+        let target = CommitStatusTarget::Gitlab(GitlabCommitStatusTarget {
+            api_url: "https://gitlab.com".to_string(),
+            project_id: 42,
+            sha: "abc".to_string(),
+            target_url: "https://temporal/wf".to_string(),
+        });
+
+        let value = serde_json::to_value(&target).unwrap();
+        assert_eq!(value["provider"], "gitlab");
+        assert_eq!(value["project_id"], 42);
+    }
+
+    #[test]
+    fn commit_status_target_without_provider_is_forgejo() {
+        let target: CommitStatusTarget = serde_json::from_value(json!({
+            "forgejo_url": "http://forgejo",
+            "repo": "khuedoan/blog",
+            "sha": "abc",
+            "target_url": "https://temporal/wf",
+        }))
+        .unwrap();
+
+        let CommitStatusTarget::Forgejo(target) = target else {
+            panic!("expected a Forgejo target");
+        };
+        assert_eq!(target.repo, "khuedoan/blog");
+        assert_eq!(target.sha, "abc");
+    }
+
+    #[test]
+    fn commit_status_target_round_trips() {
+        let target = CommitStatusTarget::Github(GithubCommitStatusTarget {
+            api_url: "https://api.github.com".to_string(),
+            repo: "khuedoan/blog".to_string(),
+            sha: "abc".to_string(),
+            target_url: "https://temporal/wf".to_string(),
+        });
+
+        let decoded: CommitStatusTarget =
+            serde_json::from_value(serde_json::to_value(&target).unwrap()).unwrap();
+        assert!(
+            matches!(decoded, CommitStatusTarget::Github(target) if target.repo == "khuedoan/blog")
+        );
+    }
+}
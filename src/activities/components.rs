@@ -0,0 +1,125 @@
+use super::{
+    app::{pull_source, read_repo_config, source_with_activity_workspace},
+    git::clone_repo,
+    git_auth::git_command_for_url,
+    process::{run_command, run_stdout_command},
+    workspace::TempWorkspace,
+};
+use crate::{
+    core::app::{build_config::BUILD_CONFIG_FILE, source::Source},
+    gitops::deployed_component_tags,
+};
+use serde::{Deserialize, Serialize};
+use std::{env, path::Path};
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanSourceComponentsInput {
+    pub source: Source,
+    pub gitops_url: String,
+    pub gitops_revision: String,
+    pub registry: String,
+    pub environment: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceComponent {
+    pub name: String,
+    pub changed: bool,
+}
+
+pub async fn plan_source_components(
+    ctx: ActivityContext,
+    input: PlanSourceComponentsInput,
+) -> Result<Vec<SourceComponent>, ActivityError> {
+    let (_workspace, source) = source_with_activity_workspace(input.source);
+    let source = pull_source(&ctx, source).await?;
+    let config = read_repo_config(&source).await?;
+    let Source::Git {
+        owner,
+        name,
+        url,
+        revision,
+        path,
+    } = &source
+    else {
+        return Ok(Vec::new());
+    };
+    if config.components.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let gitops = TempWorkspace::new(
+        "gitops-components",
+        &input.gitops_url,
+        &input.gitops_revision,
+    );
+    clone_repo(
+        &ctx,
+        &input.gitops_url,
+        &input.gitops_revision,
+        gitops.path(),
+    )
+    .await?;
+    let deployed = deployed_component_tags(
+        &gitops.path().join("apps"),
+        &input.registry,
+        &format!("{owner}/{name}"),
+        &input.environment,
+    )?;
+
+    let mut components = Vec::new();
+    for component in config.components {
+        let mut changed = true;
+        if let Some(tags) = deployed.get(&component.name) {
+            changed = false;
+            for tag in tags.iter().filter(|tag| *tag != revision) {
+                if path_changed(&ctx, path, url, tag, &component.path).await? {
+                    changed = true;
+                    break;
+                }
+            }
+        }
+
+        info!(component = %component.name, changed, "planned source component");
+        components.push(SourceComponent {
+            name: component.name,
+            changed,
+        });
+    }
+
+    Ok(components)
+}
+
+async fn path_changed(
+    ctx: &ActivityContext,
+    repo: &Path,
+    url: &str,
+    previous: &str,
+    path: &str,
+) -> Result<bool, ActivityError> {
+    let git_username = env::var("GIT_USERNAME").unwrap_or_else(|_| "git".to_string());
+    let git_password = env::var("GIT_PASSWORD").unwrap_or_else(|_| "password".to_string());
+    let mut command = git_command_for_url(url, &git_username, &git_password);
+    command
+        .args(["fetch", "--depth", "1", "origin", previous])
+        .current_dir(repo);
+    let output = run_command(ctx, &mut command, "git fetch previous revision").await?;
+    if !output.status.success() {
+        warn!(
+            revision = previous,
+            "previous revision unavailable; rebuilding component"
+        );
+        return Ok(true);
+    }
+
+    let mut command = Command::new("git");
+    command
+        .args(["diff", "--name-only", "FETCH_HEAD", "HEAD", "--", path])
+        .arg(BUILD_CONFIG_FILE)
+        .current_dir(repo);
+    let changes = run_stdout_command(ctx, &mut command, "git diff component").await?;
+    Ok(!changes.is_empty())
+}
@@ -0,0 +1,421 @@
+use anyhow::anyhow;
+use reqwest::{Method, StatusCode};
+use std::fmt;
+
+pub const COMMIT_STATUS_CONTEXT: &str = "netamos/push-to-deploy";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WebhookScope<'a> {
+    Repo(&'a str),
+    System,
+}
+
+impl fmt::Display for WebhookScope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Repo(repo) => write!(f, "{repo}"),
+            Self::System => write!(f, "system"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Webhook {
+    pub id: u64,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CommitStatus {
+    pub context: String,
+    pub state: String,
+    pub description: String,
+    pub target_url: String,
+}
+
+pub(crate) trait ForgeNotifier {
+    async fn create_commit_status(
+        &self,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> anyhow::Result<()>;
+
+    async fn comment_on_pull_request(
+        &self,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> anyhow::Result<()>;
+}
+
+pub(crate) trait Forge: ForgeNotifier {
+    async fn ensure_user(&self, username: &str, email: &str, password: &str) -> anyhow::Result<()>;
+
+    async fn repo_exists(&self, repo: &str) -> anyhow::Result<bool>;
+
+    async fn create_repo(&self, repo: &str, private: bool) -> anyhow::Result<()>;
+
+    async fn list_webhooks(&self, scope: WebhookScope<'_>) -> anyhow::Result<Vec<Webhook>>;
+
+    async fn create_webhook(
+        &self,
+        scope: WebhookScope<'_>,
+        url: &str,
+        secret: Option<&str>,
+    ) -> anyhow::Result<()>;
+
+    async fn update_webhook(
+        &self,
+        scope: WebhookScope<'_>,
+        id: u64,
+        url: &str,
+        secret: Option<&str>,
+    ) -> anyhow::Result<()>;
+
+    async fn delete_webhook(&self, scope: WebhookScope<'_>, id: u64) -> anyhow::Result<()>;
+
+    async fn ensure_collaborator(
+        &self,
+        repo: &str,
+        username: &str,
+        permission: &str,
+    ) -> anyhow::Result<()>;
+}
+
+pub(crate) async fn ensure_repo<F: Forge>(
+    forge: &F,
+    repo: &str,
+    private: bool,
+) -> anyhow::Result<()> {
+    if !forge.repo_exists(repo).await? {
+        forge.create_repo(repo, private).await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn ensure_webhook<F: Forge>(
+    forge: &F,
+    scope: WebhookScope<'_>,
+    webhook_url: &str,
+    legacy_webhook_url: &str,
+    secret: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut has_webhook = false;
+    for hook in forge.list_webhooks(scope).await? {
+        if hook.url == webhook_url {
+            has_webhook = true;
+            forge
+                .update_webhook(scope, hook.id, webhook_url, secret)
+                .await?;
+        }
+
+        if hook.url == legacy_webhook_url {
+            forge.delete_webhook(scope, hook.id).await?;
+        }
+    }
+
+    if !has_webhook {
+        forge.create_webhook(scope, webhook_url, secret).await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn delete_webhooks<F: Forge>(
+    forge: &F,
+    scope: WebhookScope<'_>,
+    webhook_urls: &[&str],
+) -> anyhow::Result<()> {
+    for hook in forge.list_webhooks(scope).await? {
+        if webhook_urls.contains(&hook.url.as_str()) {
+            forge.delete_webhook(scope, hook.id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn forge_status_error(
+    method: Method,
+    path: &str,
+    status: StatusCode,
+    body: &[u8],
+) -> anyhow::Error {
+    let body = String::from_utf8_lossy(body);
+    anyhow!(
+        "{} {} returned {}: {}",
+        method.as_str(),
+        path,
+        status.as_u16(),
+        body.chars().take(500).collect::<String>()
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        sync::Mutex,
+    };
+
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeForgeState {
+        pub users: BTreeMap<String, String>,
+        pub repos: BTreeMap<String, bool>,
+        pub webhooks: BTreeMap<String, Vec<(Webhook, Option<String>)>>,
+        pub statuses: Vec<(String, String, CommitStatus)>,
+        pub collaborators: BTreeSet<(String, String, String)>,
+        pub comments: Vec<(String, u64, String)>,
+        next_webhook_id: u64,
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeForge {
+        pub state: Mutex<FakeForgeState>,
+    }
+
+    impl FakeForge {
+        pub(crate) fn webhook_urls(&self, scope: WebhookScope<'_>) -> Vec<String> {
+            self.state
+                .lock()
+                .unwrap()
+                .webhooks
+                .get(&scope.to_string())
+                .map(|hooks| hooks.iter().map(|(hook, _)| hook.url.clone()).collect())
+                .unwrap_or_default()
+        }
+    }
+
+    impl ForgeNotifier for FakeForge {
+        async fn create_commit_status(
+            &self,
+            repo: &str,
+            sha: &str,
+            status: &CommitStatus,
+        ) -> anyhow::Result<()> {
+            self.state.lock().unwrap().statuses.push((
+                repo.to_string(),
+                sha.to_string(),
+                status.clone(),
+            ));
+            Ok(())
+        }
+
+        async fn comment_on_pull_request(
+            &self,
+            repo: &str,
+            number: u64,
+            body: &str,
+        ) -> anyhow::Result<()> {
+            self.state
+                .lock()
+                .unwrap()
+                .comments
+                .push((repo.to_string(), number, body.to_string()));
+            Ok(())
+        }
+    }
+
+    impl Forge for FakeForge {
+        async fn ensure_user(
+            &self,
+            username: &str,
+            email: &str,
+            _password: &str,
+        ) -> anyhow::Result<()> {
+            self.state
+                .lock()
+                .unwrap()
+                .users
+                .insert(username.to_string(), email.to_string());
+            Ok(())
+        }
+
+        async fn repo_exists(&self, repo: &str) -> anyhow::Result<bool> {
+            Ok(self.state.lock().unwrap().repos.contains_key(repo))
+        }
+
+        async fn create_repo(&self, repo: &str, private: bool) -> anyhow::Result<()> {
+            self.state
+                .lock()
+                .unwrap()
+                .repos
+                .insert(repo.to_string(), private);
+            Ok(())
+        }
+
+        async fn list_webhooks(&self, scope: WebhookScope<'_>) -> anyhow::Result<Vec<Webhook>> {
+            Ok(self
+                .state
+                .lock()
+                .unwrap()
+                .webhooks
+                .get(&scope.to_string())
+                .map(|hooks| hooks.iter().map(|(hook, _)| hook.clone()).collect())
+                .unwrap_or_default())
+        }
+
+        async fn create_webhook(
+            &self,
+            scope: WebhookScope<'_>,
+            url: &str,
+            secret: Option<&str>,
+        ) -> anyhow::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            state.next_webhook_id += 1;
+            let hook = Webhook {
+                id: state.next_webhook_id,
+                url: url.to_string(),
+            };
+            state
+                .webhooks
+                .entry(scope.to_string())
+                .or_default()
+                .push((hook, secret.map(ToString::to_string)));
+            Ok(())
+        }
+
+        async fn update_webhook(
+            &self,
+            scope: WebhookScope<'_>,
+            id: u64,
+            url: &str,
+            secret: Option<&str>,
+        ) -> anyhow::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            let hook = state
+                .webhooks
+                .get_mut(&scope.to_string())
+                .and_then(|hooks| hooks.iter_mut().find(|(hook, _)| hook.id == id))
+                .ok_or_else(|| anyhow::anyhow!("webhook {id} not found"))?;
+            hook.0.url = url.to_string();
+            hook.1 = secret.map(ToString::to_string);
+            Ok(())
+        }
+
+        async fn delete_webhook(&self, scope: WebhookScope<'_>, id: u64) -> anyhow::Result<()> {
+            if let Some(hooks) = self
+                .state
+                .lock()
+                .unwrap()
+                .webhooks
+                .get_mut(&scope.to_string())
+            {
+                hooks.retain(|(hook, _)| hook.id != id);
+            }
+            Ok(())
+        }
+
+        async fn ensure_collaborator(
+            &self,
+            repo: &str,
+            username: &str,
+            permission: &str,
+        ) -> anyhow::Result<()> {
+            self.state.lock().unwrap().collaborators.insert((
+                repo.to_string(),
+                username.to_string(),
+                permission.to_string(),
+            ));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fake::FakeForge, *};
+
+    #[tokio::test]
+    async fn ensure_repo_creates_missing_repo_once() {
+        let forge = FakeForge::default();
+
+        ensure_repo(&forge, "khuedoan/cloudlab", false)
+            .await
+            .unwrap();
+        ensure_repo(&forge, "khuedoan/cloudlab", true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            forge.state.lock().unwrap().repos.get("khuedoan/cloudlab"),
+            Some(&false)
+        );
+    }
+
+    #[tokio::test]
+    async fn ensure_webhook_replaces_legacy_hook() {
+        let forge = FakeForge::default();
+        let scope = WebhookScope::Repo("khuedoan/blog");
+        forge
+            .create_webhook(scope, "http://netamos:8080", None)
+            .await
+            .unwrap();
+
+        ensure_webhook(
+            &forge,
+            scope,
+            "http://netamos:8080/webhooks/gitea",
+            "http://netamos:8080",
+            None,
+        )
+        .await
+        .unwrap();
+        ensure_webhook(
+            &forge,
+            scope,
+            "http://netamos:8080/webhooks/gitea",
+            "http://netamos:8080",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            forge.webhook_urls(scope),
+            vec!["http://netamos:8080/webhooks/gitea".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn ensure_webhook_rotates_secret_on_existing_hook() {
+        let forge = FakeForge::default();
+        forge
+            .create_webhook(WebhookScope::System, "http://netamos/webhooks/gitea", None)
+            .await
+            .unwrap();
+
+        ensure_webhook(
+            &forge,
+            WebhookScope::System,
+            "http://netamos/webhooks/gitea",
+            "http://netamos",
+            Some("secret"),
+        )
+        .await
+        .unwrap();
+
+        let state = forge.state.lock().unwrap();
+        let hooks = &state.webhooks["system"];
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0].1.as_deref(), Some("secret"));
+    }
+
+    #[tokio::test]
+    async fn delete_webhooks_only_removes_matching_urls() {
+        let forge = FakeForge::default();
+        let scope = WebhookScope::Repo("khuedoan/blog");
+        for url in ["http://netamos/webhooks/gitea", "http://other"] {
+            forge.create_webhook(scope, url, None).await.unwrap();
+        }
+
+        delete_webhooks(&forge, scope, &["http://netamos/webhooks/gitea"])
+            .await
+            .unwrap();
+
+        assert_eq!(forge.webhook_urls(scope), vec!["http://other".to_string()]);
+    }
+}
@@ -1,4 +1,11 @@
-use super::{git_auth::authenticated_git_command, process::run_checked_command};
+use super::{
+    forge::{
+        COMMIT_STATUS_CONTEXT, CommitStatus, Forge, ForgeNotifier, Webhook, WebhookScope,
+        delete_webhooks, ensure_repo, ensure_webhook, forge_status_error,
+    },
+    git_auth::authenticated_git_command,
+    process::run_checked_command,
+};
 use anyhow::{Context, anyhow};
 use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
@@ -9,7 +16,7 @@ use temporalio_sdk::activities::{ActivityContext, ActivityError};
 use tokio::time::{Duration, sleep};
 use tokio::{fs::remove_dir_all, process::Command};
 
-pub const FORGEJO_COMMIT_STATUS_CONTEXT: &str = "netamos/push-to-deploy";
+const WEBHOOK_EVENTS: [&str; 2] = ["push", "pull_request"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForgejoEnsureUserInput {
@@ -43,48 +50,10 @@ pub async fn forgejo_ensure_user(
     _ctx: ActivityContext,
     input: ForgejoEnsureUserInput,
 ) -> Result<(), ActivityError> {
-    let path = format!("/api/v1/users/{}", input.username);
-    let (status, body) = forgejo_request(Method::GET, &input.forgejo_url, &path, None)
-        .await
-        .map_err(|e| anyhow!(e))?;
-
-    match status {
-        StatusCode::OK => {}
-        StatusCode::NOT_FOUND => {
-            let password = env::var("NETAMOS_PASSWORD").context("NETAMOS_PASSWORD is required")?;
-            expect_forgejo_status(
-                Method::POST,
-                &input.forgejo_url,
-                "/api/v1/admin/users",
-                Some(json!({
-                    "email": input.email,
-                    "username": input.username,
-                    "password": password,
-                    "must_change_password": false,
-                    "restricted": false,
-                })),
-                &[StatusCode::CREATED],
-            )
-            .await?;
-        }
-        _ => return Err(forgejo_status_error(Method::GET, &path, status, &body).into()),
-    }
-
     let password = env::var("NETAMOS_PASSWORD").context("NETAMOS_PASSWORD is required")?;
-    let path = format!("/api/v1/admin/users/{}", input.username);
-    expect_forgejo_status(
-        Method::PATCH,
-        &input.forgejo_url,
-        &path,
-        Some(json!({
-            "password": password,
-            "must_change_password": false,
-            "restricted": false,
-        })),
-        &[StatusCode::OK],
-    )
-    .await?;
-
+    ForgejoForge::new(&input.forgejo_url)
+        .ensure_user(&input.username, &input.email, &password)
+        .await?;
     Ok(())
 }
 
@@ -99,7 +68,12 @@ pub async fn forgejo_ensure_repo(
     _ctx: ActivityContext,
     input: ForgejoEnsureRepoInput,
 ) -> Result<(), ActivityError> {
-    ensure_forgejo_repo(&input.forgejo_url, &input.repo, input.private).await?;
+    ensure_repo(
+        &ForgejoForge::new(&input.forgejo_url),
+        &input.repo,
+        input.private,
+    )
+    .await?;
     Ok(())
 }
 
@@ -115,62 +89,14 @@ pub async fn forgejo_ensure_webhook(
     _ctx: ActivityContext,
     input: ForgejoEnsureWebhookInput,
 ) -> Result<(), ActivityError> {
-    let path = format!("/api/v1/repos/{}/hooks", input.repo);
-    let hooks = expect_forgejo_json(Method::GET, &input.forgejo_url, &path, None).await?;
-    let hooks = hooks
-        .as_array()
-        .ok_or_else(|| anyhow!("Forgejo hooks response is not an array"))?;
-
-    let mut has_webhook = false;
-    for hook in hooks {
-        let url = hook
-            .get("config")
-            .and_then(|config| config.get("url"))
-            .or_else(|| hook.get("url"))
-            .and_then(JsonValue::as_str);
-
-        if url == Some(input.webhook_url.as_str()) {
-            has_webhook = true;
-        }
-
-        if url == Some(input.legacy_webhook_url.as_str()) {
-            let hook_id = hook
-                .get("id")
-                .and_then(JsonValue::as_u64)
-                .ok_or_else(|| anyhow!("Forgejo hook is missing id"))?;
-            let path = format!("/api/v1/repos/{}/hooks/{hook_id}", input.repo);
-            expect_forgejo_status(
-                Method::DELETE,
-                &input.forgejo_url,
-                &path,
-                None,
-                &[StatusCode::NO_CONTENT],
-            )
-            .await?;
-        }
-    }
-
-    if has_webhook {
-        return Ok(());
-    }
-
-    expect_forgejo_status(
-        Method::POST,
-        &input.forgejo_url,
-        &path,
-        Some(json!({
-            "type": "gitea",
-            "config": {
-                "url": input.webhook_url,
-                "content_type": "json",
-            },
-            "events": ["push"],
-            "active": true,
-        })),
-        &[StatusCode::CREATED],
+    ensure_webhook(
+        &ForgejoForge::new(&input.forgejo_url),
+        WebhookScope::Repo(&input.repo),
+        &input.webhook_url,
+        &input.legacy_webhook_url,
+        webhook_secret().as_deref(),
     )
     .await?;
-
     Ok(())
 }
 
@@ -185,47 +111,14 @@ pub async fn forgejo_ensure_system_webhook(
     _ctx: ActivityContext,
     input: ForgejoEnsureSystemWebhookInput,
 ) -> Result<(), ActivityError> {
-    let path = "/api/v1/admin/hooks?type=system";
-    let hooks = expect_forgejo_json(Method::GET, &input.forgejo_url, path, None).await?;
-    let hooks = hooks
-        .as_array()
-        .ok_or_else(|| anyhow!("Forgejo system hooks response is not an array"))?;
-
-    let mut has_webhook = false;
-    for hook in hooks {
-        let url = hook_url(hook);
-
-        if url == Some(input.webhook_url.as_str()) {
-            has_webhook = true;
-        }
-
-        if url == Some(input.legacy_webhook_url.as_str()) {
-            delete_admin_hook(&input.forgejo_url, hook).await?;
-        }
-    }
-
-    if has_webhook {
-        return Ok(());
-    }
-
-    expect_forgejo_status(
-        Method::POST,
-        &input.forgejo_url,
-        "/api/v1/admin/hooks",
-        Some(json!({
-            "type": "gitea",
-            "config": {
-                "url": input.webhook_url,
-                "content_type": "json",
-                "is_system_webhook": "true",
-            },
-            "events": ["push"],
-            "active": true,
-        })),
-        &[StatusCode::CREATED],
+    ensure_webhook(
+        &ForgejoForge::new(&input.forgejo_url),
+        WebhookScope::System,
+        &input.webhook_url,
+        &input.legacy_webhook_url,
+        webhook_secret().as_deref(),
     )
     .await?;
-
     Ok(())
 }
 
@@ -241,32 +134,12 @@ pub async fn forgejo_delete_webhook(
     _ctx: ActivityContext,
     input: ForgejoDeleteWebhookInput,
 ) -> Result<(), ActivityError> {
-    let path = format!("/api/v1/repos/{}/hooks", input.repo);
-    let hooks = expect_forgejo_json(Method::GET, &input.forgejo_url, &path, None).await?;
-    let hooks = hooks
-        .as_array()
-        .ok_or_else(|| anyhow!("Forgejo hooks response is not an array"))?;
-
-    for hook in hooks {
-        let url = hook_url(hook);
-        if url == Some(input.webhook_url.as_str()) || url == Some(input.legacy_webhook_url.as_str())
-        {
-            let hook_id = hook
-                .get("id")
-                .and_then(JsonValue::as_u64)
-                .ok_or_else(|| anyhow!("Forgejo hook is missing id"))?;
-            let path = format!("/api/v1/repos/{}/hooks/{hook_id}", input.repo);
-            expect_forgejo_status(
-                Method::DELETE,
-                &input.forgejo_url,
-                &path,
-                None,
-                &[StatusCode::NO_CONTENT],
-            )
-            .await?;
-        }
-    }
-
+    delete_webhooks(
+        &ForgejoForge::new(&input.forgejo_url),
+        WebhookScope::Repo(&input.repo),
+        &[&input.webhook_url, &input.legacy_webhook_url],
+    )
+    .await?;
     Ok(())
 }
 
@@ -277,22 +150,25 @@ fn hook_url(hook: &JsonValue) -> Option<&str> {
         .and_then(JsonValue::as_str)
 }
 
-async fn delete_admin_hook(forgejo_url: &str, hook: &JsonValue) -> Result<(), ActivityError> {
-    let hook_id = hook
-        .get("id")
-        .and_then(JsonValue::as_u64)
-        .ok_or_else(|| anyhow!("Forgejo hook is missing id"))?;
-    let path = format!("/api/v1/admin/hooks/{hook_id}");
-    expect_forgejo_status(
-        Method::DELETE,
-        forgejo_url,
-        &path,
-        None,
-        &[StatusCode::NO_CONTENT],
-    )
-    .await?;
+fn webhook_secret() -> Option<String> {
+    env::var("NETAMOS_WEBHOOK_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
 
-    Ok(())
+fn webhook_config(webhook_url: &str, scope: WebhookScope<'_>, secret: Option<&str>) -> JsonValue {
+    let mut config = json!({
+        "url": webhook_url,
+        "content_type": "json",
+    });
+    if scope == WebhookScope::System {
+        config["is_system_webhook"] = json!("true");
+    }
+    if let Some(secret) = secret {
+        config["secret"] = json!(secret);
+    }
+
+    config
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -314,23 +190,18 @@ pub async fn forgejo_create_commit_status(
     _ctx: ActivityContext,
     input: ForgejoCreateCommitStatusInput,
 ) -> Result<(), ActivityError> {
-    let path = format!(
-        "/api/v1/repos/{}/statuses/{}",
-        input.target.repo, input.target.sha
-    );
-    expect_forgejo_status(
-        Method::POST,
-        &input.target.forgejo_url,
-        &path,
-        Some(json!({
-            "context": FORGEJO_COMMIT_STATUS_CONTEXT,
-            "description": input.description,
-            "state": input.state,
-            "target_url": input.target.target_url,
-        })),
-        &[StatusCode::CREATED],
-    )
-    .await?;
+    ForgejoForge::new(&input.target.forgejo_url)
+        .create_commit_status(
+            &input.target.repo,
+            &input.target.sha,
+            &CommitStatus {
+                context: COMMIT_STATUS_CONTEXT.to_string(),
+                state: input.state,
+                description: input.description,
+                target_url: input.target.target_url,
+            },
+        )
+        .await?;
 
     Ok(())
 }
@@ -347,19 +218,9 @@ pub async fn forgejo_ensure_collaborator(
     _ctx: ActivityContext,
     input: ForgejoEnsureCollaboratorInput,
 ) -> Result<(), ActivityError> {
-    let path = format!(
-        "/api/v1/repos/{}/collaborators/{}",
-        input.repo, input.username
-    );
-    expect_forgejo_status(
-        Method::PUT,
-        &input.forgejo_url,
-        &path,
-        Some(json!({ "permission": input.permission })),
-        &[StatusCode::NO_CONTENT],
-    )
-    .await?;
-
+    ForgejoForge::new(&input.forgejo_url)
+        .ensure_collaborator(&input.repo, &input.username, &input.permission)
+        .await?;
     Ok(())
 }
 
@@ -375,7 +236,7 @@ pub async fn forgejo_ensure_gitops_repo_seeded(
     ctx: ActivityContext,
     input: ForgejoEnsureGitopsRepoSeededInput,
 ) -> Result<(), ActivityError> {
-    ensure_forgejo_repo(&input.forgejo_url, &input.repo, false).await?;
+    ensure_repo(&ForgejoForge::new(&input.forgejo_url), &input.repo, false).await?;
 
     let target_url = format!(
         "{}/{}.git",
@@ -414,29 +275,245 @@ pub async fn forgejo_ensure_gitops_repo_seeded(
     Ok(())
 }
 
-async fn ensure_forgejo_repo(forgejo_url: &str, repo: &str, private: bool) -> anyhow::Result<()> {
-    let (owner, name) = split_repo(repo)?;
-    let path = format!("/api/v1/repos/{owner}/{name}");
-    let (status, body) = forgejo_request(Method::GET, forgejo_url, &path, None).await?;
-
-    match status {
-        StatusCode::OK => Ok(()),
-        StatusCode::NOT_FOUND => {
-            let path = format!("/api/v1/admin/users/{owner}/repos");
-            expect_forgejo_status(
-                Method::POST,
-                forgejo_url,
-                &path,
-                Some(json!({
-                    "name": name,
-                    "private": private,
-                })),
-                &[StatusCode::CREATED, StatusCode::CONFLICT],
-            )
-            .await?;
-            Ok(())
+pub(crate) struct ForgejoForge<'a> {
+    url: &'a str,
+}
+
+impl<'a> ForgejoForge<'a> {
+    pub(crate) fn new(url: &'a str) -> Self {
+        Self { url }
+    }
+
+    fn hooks_path(scope: WebhookScope<'_>) -> String {
+        match scope {
+            WebhookScope::Repo(repo) => format!("/api/v1/repos/{repo}/hooks"),
+            WebhookScope::System => "/api/v1/admin/hooks".to_string(),
         }
-        _ => Err(forgejo_status_error(Method::GET, &path, status, &body)),
+    }
+}
+
+impl ForgeNotifier for ForgejoForge<'_> {
+    async fn create_commit_status(
+        &self,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> anyhow::Result<()> {
+        let path = format!("/api/v1/repos/{repo}/statuses/{sha}");
+        expect_forgejo_status(
+            Method::POST,
+            self.url,
+            &path,
+            Some(json!({
+                "context": status.context,
+                "description": status.description,
+                "state": status.state,
+                "target_url": status.target_url,
+            })),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn comment_on_pull_request(
+        &self,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        let path = format!("/api/v1/repos/{repo}/issues/{number}/comments");
+        expect_forgejo_status(
+            Method::POST,
+            self.url,
+            &path,
+            Some(json!({ "body": body })),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl Forge for ForgejoForge<'_> {
+    async fn ensure_user(&self, username: &str, email: &str, password: &str) -> anyhow::Result<()> {
+        let path = format!("/api/v1/users/{username}");
+        let (status, body) = forgejo_request(Method::GET, self.url, &path, None).await?;
+
+        match status {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => {
+                expect_forgejo_status(
+                    Method::POST,
+                    self.url,
+                    "/api/v1/admin/users",
+                    Some(json!({
+                        "email": email,
+                        "username": username,
+                        "password": password,
+                        "must_change_password": false,
+                        "restricted": false,
+                    })),
+                    &[StatusCode::CREATED],
+                )
+                .await?;
+            }
+            _ => return Err(forge_status_error(Method::GET, &path, status, &body)),
+        }
+
+        let path = format!("/api/v1/admin/users/{username}");
+        expect_forgejo_status(
+            Method::PATCH,
+            self.url,
+            &path,
+            Some(json!({
+                "password": password,
+                "must_change_password": false,
+                "restricted": false,
+            })),
+            &[StatusCode::OK],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn repo_exists(&self, repo: &str) -> anyhow::Result<bool> {
+        let (owner, name) = split_repo(repo)?;
+        let path = format!("/api/v1/repos/{owner}/{name}");
+        let (status, body) = forgejo_request(Method::GET, self.url, &path, None).await?;
+
+        match status {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(forge_status_error(Method::GET, &path, status, &body)),
+        }
+    }
+
+    async fn create_repo(&self, repo: &str, private: bool) -> anyhow::Result<()> {
+        let (owner, name) = split_repo(repo)?;
+        let path = format!("/api/v1/admin/users/{owner}/repos");
+        expect_forgejo_status(
+            Method::POST,
+            self.url,
+            &path,
+            Some(json!({
+                "name": name,
+                "private": private,
+            })),
+            &[StatusCode::CREATED, StatusCode::CONFLICT],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_webhooks(&self, scope: WebhookScope<'_>) -> anyhow::Result<Vec<Webhook>> {
+        let path = match scope {
+            WebhookScope::Repo(_) => Self::hooks_path(scope),
+            WebhookScope::System => "/api/v1/admin/hooks?type=system".to_string(),
+        };
+        let hooks = expect_forgejo_json(Method::GET, self.url, &path, None).await?;
+        let hooks = hooks
+            .as_array()
+            .ok_or_else(|| anyhow!("Forgejo {scope} hooks response is not an array"))?;
+
+        hooks
+            .iter()
+            .filter_map(|hook| hook_url(hook).map(|url| (hook, url)))
+            .map(|(hook, url)| {
+                let id = hook
+                    .get("id")
+                    .and_then(JsonValue::as_u64)
+                    .ok_or_else(|| anyhow!("Forgejo hook is missing id"))?;
+                Ok(Webhook {
+                    id,
+                    url: url.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    async fn create_webhook(
+        &self,
+        scope: WebhookScope<'_>,
+        url: &str,
+        secret: Option<&str>,
+    ) -> anyhow::Result<()> {
+        expect_forgejo_status(
+            Method::POST,
+            self.url,
+            &Self::hooks_path(scope),
+            Some(json!({
+                "type": "gitea",
+                "config": webhook_config(url, scope, secret),
+                "events": WEBHOOK_EVENTS,
+                "active": true,
+            })),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_webhook(
+        &self,
+        scope: WebhookScope<'_>,
+        id: u64,
+        url: &str,
+        secret: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let path = format!("{}/{id}", Self::hooks_path(scope));
+        expect_forgejo_status(
+            Method::PATCH,
+            self.url,
+            &path,
+            Some(json!({
+                "config": webhook_config(url, scope, secret),
+                "events": WEBHOOK_EVENTS,
+                "active": true,
+            })),
+            &[StatusCode::OK],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_webhook(&self, scope: WebhookScope<'_>, id: u64) -> anyhow::Result<()> {
+        let path = format!("{}/{id}", Self::hooks_path(scope));
+        expect_forgejo_status(
+            Method::DELETE,
+            self.url,
+            &path,
+            None,
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn ensure_collaborator(
+        &self,
+        repo: &str,
+        username: &str,
+        permission: &str,
+    ) -> anyhow::Result<()> {
+        let path = format!("/api/v1/repos/{repo}/collaborators/{username}");
+        expect_forgejo_status(
+            Method::PUT,
+            self.url,
+            &path,
+            Some(json!({ "permission": permission })),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+
+        Ok(())
     }
 }
 
@@ -466,7 +543,7 @@ async fn expect_forgejo_status(
     if expected.contains(&status) {
         Ok(body)
     } else {
-        Err(forgejo_status_error(method, path, status, &body))
+        Err(forge_status_error(method, path, status, &body))
     }
 }
 
@@ -499,22 +576,6 @@ async fn forgejo_request(
     Ok((status, body))
 }
 
-fn forgejo_status_error(
-    method: Method,
-    path: &str,
-    status: StatusCode,
-    body: &[u8],
-) -> anyhow::Error {
-    let body = String::from_utf8_lossy(body);
-    anyhow!(
-        "{} {} returned {}: {}",
-        method.as_str(),
-        path,
-        status.as_u16(),
-        body.chars().take(500).collect::<String>()
-    )
-}
-
 fn forgejo_git_command() -> Command {
     authenticated_git_command(
         &env::var("FORGEJO_ADMIN_USERNAME").unwrap_or_default(),
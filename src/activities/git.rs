@@ -1,16 +1,25 @@
 use super::{
-    forgejo::ForgejoCommitStatusTarget,
+    commit_status::CommitStatusTarget,
     git_auth::git_command_for_url,
     process::{run_checked_command, run_stdout_command},
+    registry_auth::{oci_registry_client, oci_repository_access},
+    signing::ensure_image_signed,
     workspace::TempWorkspace,
 };
 use crate::{
-    api::{CreateAppRequest, DeleteAppRequest},
+    api::{
+        CreateAppRequest, DeleteAppRequest, PromoteAppRequest, RemoveComponentsRequest,
+        RollbackAppRequest, UpdateSecretsRequest,
+    },
     core::app::image::Image,
     gitops::{
-        AppImageUpdate, AppsBundle, UpdateAppVersionInput, scan_app_source_targets,
-        update_app_version_inner, write_add_app_manifests, write_apps_bundle,
-        write_create_app_manifests,
+        AppImageUpdate, AppsBundle, PREVIEW_BASE_ENVIRONMENT, UpdateAppVersionInput,
+        image_history_args, parse_image_history, preview_environment, preview_hostname,
+        remove_app_component, rollback_candidates, scan_app_source_targets,
+        scan_environment_images, select_rollback_image, source_image_from_reference,
+        split_image_reference, update_app_version_inner, update_sealed_secret,
+        write_add_app_manifests, write_apps_bundle, write_create_app_manifests,
+        write_preview_environment,
     },
 };
 use anyhow::anyhow;
@@ -28,7 +37,7 @@ pub use crate::gitops::AppTarget;
 const APPS_REPOSITORY: &str = "apps";
 const APPS_TAG: &str = "latest";
 
-fn non_retryable_error(error: anyhow::Error) -> ActivityError {
+pub(super) fn non_retryable_error(error: anyhow::Error) -> ActivityError {
     ActivityError::application(ApplicationFailure::non_retryable(error))
 }
 
@@ -40,7 +49,13 @@ pub struct UpdateGitopsImageInput {
     pub environment: String,
     pub image: Image,
     #[serde(default)]
-    pub commit_status: Option<ForgejoCommitStatusTarget>,
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub commit_status: Option<CommitStatusTarget>,
+    #[serde(default)]
+    pub approved_by: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +86,20 @@ pub struct FindGitopsSourceReposInput {
     pub registry: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindPromotionImagesInput {
+    pub url: String,
+    pub revision: String,
+    pub registry: String,
+    pub request: PromoteAppRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionImage {
+    pub source_repo: String,
+    pub image: Image,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateGitopsAppInput {
     pub url: String,
@@ -92,6 +121,8 @@ pub struct AddGitopsAppInput {
     pub revision: String,
     pub registry: String,
     pub request: CreateAppRequest,
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,6 +130,8 @@ pub struct AddGitopsAppResult {
     pub changed: bool,
     pub commit_sha: Option<String>,
     pub app_path: String,
+    #[serde(default)]
+    pub diff: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +149,75 @@ pub struct DeleteGitopsAppResult {
     pub app_path: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePreviewEnvironmentInput {
+    pub url: String,
+    pub revision: String,
+    pub registry: String,
+    pub source_repo: String,
+    pub number: u64,
+    pub domain: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePreviewEnvironmentResult {
+    pub environment: String,
+    pub created: bool,
+    pub commit_sha: Option<String>,
+    pub hostnames: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackGitopsAppInput {
+    pub url: String,
+    pub revision: String,
+    pub registry: String,
+    pub request: RollbackAppRequest,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackGitopsAppResult {
+    pub changed: bool,
+    pub commit_sha: Option<String>,
+    pub app_path: String,
+    pub image: String,
+    pub rolled_back_from: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveGitopsComponentsInput {
+    pub url: String,
+    pub revision: String,
+    pub registry: String,
+    pub request: RemoveComponentsRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveGitopsComponentsResult {
+    pub changed: bool,
+    pub commit_sha: Option<String>,
+    pub app_path: String,
+    #[serde(default)]
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateGitopsSecretsInput {
+    pub url: String,
+    pub revision: String,
+    pub registry: String,
+    pub request: UpdateSecretsRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateGitopsSecretsResult {
+    pub changed: bool,
+    pub commit_sha: Option<String>,
+    pub app_path: String,
+}
+
 pub async fn enqueue_gitops_publish(
     ctx: ActivityContext,
     input: EnqueueGitopsPublishInput,
@@ -167,10 +269,64 @@ pub async fn find_gitops_source_repos(
     Ok(repos)
 }
 
+pub async fn find_promotion_images(
+    ctx: ActivityContext,
+    input: FindPromotionImagesInput,
+) -> Result<Vec<PromotionImage>, ActivityError> {
+    input
+        .request
+        .validate()
+        .map_err(|error| non_retryable_error(anyhow!(error)))?;
+
+    let workspace = TempWorkspace::new("promote-app", &input.url, &input.revision);
+    clone_repo(&ctx, &input.url, &input.revision, workspace.path()).await?;
+
+    let app_path = input.request.app_path();
+    let project_dir = workspace.path().join("apps").join(&app_path);
+    for environment in [&input.request.from, &input.request.to] {
+        if !project_dir.join(environment).exists() {
+            return Err(non_retryable_error(anyhow!(
+                "apps/{app_path}/{environment} does not exist"
+            )));
+        }
+    }
+
+    let mut images = Vec::new();
+    for image in scan_environment_images(&project_dir.join(&input.request.from))? {
+        let Some(source_image) = source_image_from_reference(&input.registry, &image) else {
+            continue;
+        };
+        let (_, Some(tag), digest) = split_image_reference(&image) else {
+            continue;
+        };
+        images.push(PromotionImage {
+            image: Image {
+                registry: input.registry.clone(),
+                owner: source_image.image_owner(),
+                repository: source_image.image_repository(),
+                tag: tag.to_string(),
+                digest: digest.map(ToString::to_string),
+            },
+            source_repo: source_image.source_repo(),
+        });
+    }
+
+    if images.is_empty() {
+        return Err(non_retryable_error(anyhow!(
+            "apps/{app_path}/{} has no platform-built images to promote",
+            input.request.from
+        )));
+    }
+
+    Ok(images)
+}
+
 pub async fn update_gitops_image(
     ctx: ActivityContext,
     input: UpdateGitopsImageInput,
 ) -> Result<UpdateGitopsImageResult, ActivityError> {
+    ensure_image_signed(&ctx, &input.image).await?;
+
     let workspace = TempWorkspace::new("gitops", &input.url, &input.revision);
     clone_repo(&ctx, &input.url, &input.revision, workspace.path()).await?;
     configure_git_user(&ctx, workspace.path()).await?;
@@ -183,19 +339,25 @@ pub async fn update_gitops_image(
     let changed = update_app_version_inner(UpdateAppVersionInput {
         apps_dir: apps_dir.to_string_lossy().to_string(),
         environment: input.environment.clone(),
+        tenant: input.tenant.clone(),
+        project: input.project.clone(),
         new_images: vec![AppImageUpdate {
             repository,
             tag: input.image.tag.clone(),
+            digest: input.image.digest.clone(),
         }],
     })
     .await?;
 
     let mut commit_sha = None;
     if changed {
-        let commit_message = format!(
+        let mut commit_message = format!(
             "chore(apps): update {} image for {}",
             input.source_repo, input.environment
         );
+        if let Some(approver) = &input.approved_by {
+            commit_message.push_str(&format!("\n\nApproved-by: {approver}"));
+        }
         commit_sha = Some(
             commit_and_push_gitops(
                 &ctx,
@@ -215,6 +377,7 @@ pub async fn update_gitops_image(
         APPS_REPOSITORY,
         APPS_TAG,
         &input.image.registry,
+        &oci_repository_access(),
     )?;
     push_apps_bundle(&ctx, &input.image.registry, &bundle).await?;
 
@@ -234,7 +397,7 @@ pub async fn create_gitops_app(
 
     input
         .request
-        .validate()
+        .validate_create()
         .map_err(|error| non_retryable_error(anyhow!(error)))?;
 
     let workspace = TempWorkspace::new("create-app", &input.url, &input.revision);
@@ -284,6 +447,7 @@ pub async fn create_gitops_app(
         APPS_REPOSITORY,
         APPS_TAG,
         &input.registry,
+        &oci_repository_access(),
     )?;
     push_apps_bundle(&ctx, &input.registry, &bundle).await?;
 
@@ -328,10 +492,23 @@ pub async fn add_gitops_app(
             "apps/{app_path} does not exist; create it first"
         )));
     }
-    write_add_app_manifests(&app_dir, &input.request, &input.registry)?;
+    write_add_app_manifests(&app_dir, &input.request, &input.registry)
+        .map_err(non_retryable_error)?;
 
     let pathspec = format!("apps/{app_path}");
     let changed = git_has_changes(&ctx, workspace.path(), &pathspec).await?;
+    let diff = git_staged_diff(&ctx, workspace.path(), &pathspec).await?;
+    if changed {
+        info!(app = %app_path, "app component changes:\n{diff}");
+    }
+    if input.dry_run {
+        return Ok(AddGitopsAppResult {
+            changed,
+            commit_sha: None,
+            app_path,
+            diff,
+        });
+    }
     let commit_sha = if changed {
         let commit_message = format!("feat(apps): add components to {app_path}");
         Some(
@@ -355,6 +532,7 @@ pub async fn add_gitops_app(
         APPS_REPOSITORY,
         APPS_TAG,
         &input.registry,
+        &oci_repository_access(),
     )?;
     push_apps_bundle(&ctx, &input.registry, &bundle).await?;
 
@@ -362,6 +540,7 @@ pub async fn add_gitops_app(
         changed,
         commit_sha,
         app_path,
+        diff,
     })
 }
 
@@ -421,6 +600,7 @@ pub async fn delete_gitops_app(
         APPS_REPOSITORY,
         APPS_TAG,
         &input.registry,
+        &oci_repository_access(),
     )?;
     push_apps_bundle(&ctx, &input.registry, &bundle).await?;
 
@@ -431,6 +611,355 @@ pub async fn delete_gitops_app(
     })
 }
 
+pub async fn create_preview_environment(
+    ctx: ActivityContext,
+    input: CreatePreviewEnvironmentInput,
+) -> Result<CreatePreviewEnvironmentResult, ActivityError> {
+    if ctx.is_cancelled() {
+        return Err(ActivityError::cancelled());
+    }
+
+    let environment = preview_environment(input.number);
+    let workspace = TempWorkspace::new("preview-app", &input.url, &input.revision);
+    clone_repo(&ctx, &input.url, &input.revision, workspace.path()).await?;
+    configure_git_user(&ctx, workspace.path()).await?;
+
+    let apps_dir = workspace.path().join("apps");
+    let targets = scan_app_source_targets(&apps_dir, &input.registry)?
+        .into_iter()
+        .filter(|mapping| {
+            mapping.source_repo == input.source_repo
+                && mapping.target.environment == PREVIEW_BASE_ENVIRONMENT
+        })
+        .map(|mapping| AppTarget {
+            environment: environment.clone(),
+            ..mapping.target
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut created = false;
+    let mut hostnames = Vec::new();
+    for target in &targets {
+        created |= write_preview_environment(&apps_dir, target, &input.domain)?;
+        hostnames.push(preview_hostname(target, &input.domain));
+    }
+
+    let changed = created && git_has_changes(&ctx, workspace.path(), "apps").await?;
+    let commit_sha = if changed {
+        let commit_message = format!(
+            "chore(apps): create {environment} preview for {}",
+            input.source_repo
+        );
+        Some(
+            commit_and_push_gitops(
+                &ctx,
+                workspace.path(),
+                &input.url,
+                &input.revision,
+                &commit_message,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    Ok(CreatePreviewEnvironmentResult {
+        environment,
+        created: changed,
+        commit_sha,
+        hostnames,
+    })
+}
+
+pub async fn rollback_gitops_app(
+    ctx: ActivityContext,
+    input: RollbackGitopsAppInput,
+) -> Result<RollbackGitopsAppResult, ActivityError> {
+    if ctx.is_cancelled() {
+        return Err(ActivityError::cancelled());
+    }
+
+    input
+        .request
+        .validate()
+        .map_err(|error| non_retryable_error(anyhow!(error)))?;
+
+    let workspace = TempWorkspace::new("rollback-app", &input.url, &input.revision);
+    clone_repo(&ctx, &input.url, &input.revision, workspace.path()).await?;
+    configure_git_user(&ctx, workspace.path()).await?;
+
+    let app_path = input.request.app_path();
+    let apps_dir = workspace.path().join("apps");
+    let app_dir = apps_dir
+        .join(&input.request.tenant)
+        .join(&input.request.project)
+        .join(&input.request.environment);
+
+    if !app_dir.exists() {
+        return Err(non_retryable_error(anyhow!(
+            "apps/{app_path} does not exist"
+        )));
+    }
+
+    let pathspec = format!("apps/{app_path}");
+    let mut command = Command::new("git");
+    command
+        .args(image_history_args(&pathspec))
+        .current_dir(workspace.path());
+    let log = run_stdout_command(&ctx, &mut command, "git log app history").await?;
+
+    let target = AppTarget {
+        tenant: input.request.tenant.clone(),
+        project: input.request.project.clone(),
+        environment: input.request.environment.clone(),
+    };
+    let current_images = scan_environment_images(&app_dir)?;
+    let candidates = rollback_candidates(&parse_image_history(&log), &target, &current_images);
+    let selected = select_rollback_image(&candidates, input.request.image.as_deref())
+        .map_err(non_retryable_error)?;
+
+    let (repository, tag, digest) = split_image_reference(&selected.image);
+    let Some(tag) = tag else {
+        return Err(non_retryable_error(anyhow!(
+            "{} has no tag to roll back to",
+            selected.image
+        )));
+    };
+    let rolled_back_from = current_images
+        .iter()
+        .find(|image| split_image_reference(image).0 == repository)
+        .cloned()
+        .unwrap_or_default();
+    if let Some(source_image) = source_image_from_reference(&input.registry, &selected.image) {
+        let image = Image {
+            registry: input.registry.clone(),
+            owner: source_image.image_owner(),
+            repository: source_image.image_repository(),
+            tag: tag.to_string(),
+            digest: digest.map(ToString::to_string),
+        };
+        ensure_image_signed(&ctx, &image).await?;
+    }
+
+    update_app_version_inner(UpdateAppVersionInput {
+        apps_dir: apps_dir.to_string_lossy().to_string(),
+        environment: input.request.environment.clone(),
+        tenant: Some(input.request.tenant.clone()),
+        project: Some(input.request.project.clone()),
+        new_images: vec![AppImageUpdate {
+            repository: repository.to_string(),
+            tag: tag.to_string(),
+            digest: digest.map(ToString::to_string),
+        }],
+    })
+    .await?;
+
+    let changed = git_has_changes(&ctx, workspace.path(), &pathspec).await?;
+    let commit_sha = if changed {
+        let mut commit_message = format!("chore(apps): roll back {app_path} to {}", selected.image);
+        if let Some(approver) = &input.approved_by {
+            commit_message.push_str(&format!("\n\nApproved-by: {approver}"));
+        }
+        Some(
+            commit_and_push_gitops(
+                &ctx,
+                workspace.path(),
+                &input.url,
+                &input.revision,
+                &commit_message,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let bundle_workspace = TempWorkspace::new("apps-bundle", &input.url, &input.revision);
+    let bundle = write_apps_bundle(
+        bundle_workspace.path(),
+        &apps_dir,
+        APPS_REPOSITORY,
+        APPS_TAG,
+        &input.registry,
+        &oci_repository_access(),
+    )?;
+    push_apps_bundle(&ctx, &input.registry, &bundle).await?;
+
+    Ok(RollbackGitopsAppResult {
+        changed,
+        commit_sha,
+        app_path,
+        image: selected.image,
+        rolled_back_from,
+    })
+}
+
+pub async fn remove_gitops_components(
+    ctx: ActivityContext,
+    input: RemoveGitopsComponentsInput,
+) -> Result<RemoveGitopsComponentsResult, ActivityError> {
+    if ctx.is_cancelled() {
+        return Err(ActivityError::cancelled());
+    }
+
+    input
+        .request
+        .validate()
+        .map_err(|error| non_retryable_error(anyhow!(error)))?;
+
+    let workspace = TempWorkspace::new("remove-components", &input.url, &input.revision);
+    clone_repo(&ctx, &input.url, &input.revision, workspace.path()).await?;
+    configure_git_user(&ctx, workspace.path()).await?;
+
+    let app_path = input.request.app_path();
+    let apps_dir = workspace.path().join("apps");
+    let app_dir = apps_dir
+        .join(&input.request.tenant)
+        .join(&input.request.project)
+        .join(&input.request.environment);
+
+    if !app_dir.exists() {
+        return Err(non_retryable_error(anyhow!(
+            "apps/{app_path} does not exist"
+        )));
+    }
+    for component in &input.request.components {
+        remove_app_component(&app_dir, &input.request.project, component)
+            .map_err(|error| non_retryable_error(anyhow!("{component}: {error}")))?;
+    }
+
+    let pathspec = format!("apps/{app_path}");
+    let changed = git_has_changes(&ctx, workspace.path(), &pathspec).await?;
+    let diff = git_staged_diff(&ctx, workspace.path(), &pathspec).await?;
+    let commit_sha = if changed {
+        let components = input
+            .request
+            .components
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let commit_message = format!("chore(apps): remove {components} from {app_path}");
+        Some(
+            commit_and_push_gitops(
+                &ctx,
+                workspace.path(),
+                &input.url,
+                &input.revision,
+                &commit_message,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let bundle_workspace = TempWorkspace::new("apps-bundle", &input.url, &input.revision);
+    let bundle = write_apps_bundle(
+        bundle_workspace.path(),
+        &apps_dir,
+        APPS_REPOSITORY,
+        APPS_TAG,
+        &input.registry,
+        &oci_repository_access(),
+    )?;
+    push_apps_bundle(&ctx, &input.registry, &bundle).await?;
+
+    info!(app = %app_path, changed, "removed app components");
+    Ok(RemoveGitopsComponentsResult {
+        changed,
+        commit_sha,
+        app_path,
+        diff,
+    })
+}
+
+pub async fn update_gitops_secrets(
+    ctx: ActivityContext,
+    input: UpdateGitopsSecretsInput,
+) -> Result<UpdateGitopsSecretsResult, ActivityError> {
+    if ctx.is_cancelled() {
+        return Err(ActivityError::cancelled());
+    }
+
+    input
+        .request
+        .validate()
+        .map_err(|error| non_retryable_error(anyhow!(error)))?;
+
+    let workspace = TempWorkspace::new("update-secrets", &input.url, &input.revision);
+    clone_repo(&ctx, &input.url, &input.revision, workspace.path()).await?;
+    configure_git_user(&ctx, workspace.path()).await?;
+
+    let app_path = input.request.app_path();
+    let apps_dir = workspace.path().join("apps");
+    let app_dir = apps_dir
+        .join(&input.request.tenant)
+        .join(&input.request.project)
+        .join(&input.request.environment);
+
+    if !app_dir.exists() {
+        return Err(non_retryable_error(anyhow!(
+            "apps/{app_path} does not exist"
+        )));
+    }
+
+    update_sealed_secret(
+        &app_dir,
+        &input.request.project,
+        &input.request.set,
+        &input.request.unset,
+    )
+    .map_err(non_retryable_error)?;
+
+    let pathspec = format!("apps/{app_path}");
+    let changed = git_has_changes(&ctx, workspace.path(), &pathspec).await?;
+    let commit_sha = if changed {
+        let keys = input
+            .request
+            .set
+            .iter()
+            .map(|value| value.key.as_str())
+            .chain(input.request.unset.iter().map(String::as_str))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let commit_message = format!("chore(apps): update secrets for {app_path}\n\nKeys: {keys}");
+        Some(
+            commit_and_push_gitops(
+                &ctx,
+                workspace.path(),
+                &input.url,
+                &input.revision,
+                &commit_message,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let bundle_workspace = TempWorkspace::new("apps-bundle", &input.url, &input.revision);
+    let bundle = write_apps_bundle(
+        bundle_workspace.path(),
+        &apps_dir,
+        APPS_REPOSITORY,
+        APPS_TAG,
+        &input.registry,
+        &oci_repository_access(),
+    )?;
+    push_apps_bundle(&ctx, &input.registry, &bundle).await?;
+
+    info!(app = %app_path, changed, "updated app secrets");
+    Ok(UpdateGitopsSecretsResult {
+        changed,
+        commit_sha,
+        app_path,
+    })
+}
+
 async fn commit_and_push_gitops(
     ctx: &ActivityContext,
     workspace: &Path,
@@ -477,7 +1006,25 @@ async fn git_has_changes(
     Ok(!status.trim().is_empty())
 }
 
-async fn clone_repo(
+async fn git_staged_diff(
+    ctx: &ActivityContext,
+    workspace: &Path,
+    pathspec: &str,
+) -> Result<String, ActivityError> {
+    let mut command = Command::new("git");
+    command
+        .args(["add", "--all", "--", pathspec])
+        .current_dir(workspace);
+    run_checked_command(ctx, &mut command, "git add app changes").await?;
+
+    let mut command = Command::new("git");
+    command
+        .args(["diff", "--cached", "--no-color", "--", pathspec])
+        .current_dir(workspace);
+    run_stdout_command(ctx, &mut command, "git diff app changes").await
+}
+
+pub(super) async fn clone_repo(
     ctx: &ActivityContext,
     url: &str,
     revision: &str,
@@ -533,57 +1080,34 @@ async fn push_apps_bundle(
         "pushing apps OCI artifacts"
     );
 
+    let client = oci_registry_client(registry)?;
+    let mut pushed = 0;
     for app in &bundle.apps {
-        push_flux_artifact(
-            ctx,
-            registry,
-            &app.repository,
+        ctx.record_heartbeat(vec![]);
+        if client
+            .push_flux_artifact(&app.repository, APPS_TAG, &app.name, APPS_TAG, &app.dir)
+            .await?
+        {
+            pushed += 1;
+        }
+    }
+    if client
+        .push_flux_artifact(
+            APPS_REPOSITORY,
             APPS_TAG,
-            &app.name,
-            &app.dir,
+            APPS_REPOSITORY,
+            APPS_TAG,
+            &bundle.root_dir,
         )
-        .await?;
+        .await?
+    {
+        pushed += 1;
     }
-    push_flux_artifact(
-        ctx,
-        registry,
-        APPS_REPOSITORY,
-        APPS_TAG,
-        APPS_REPOSITORY,
-        &bundle.root_dir,
-    )
-    .await?;
-
-    Ok(())
-}
-
-async fn push_flux_artifact(
-    ctx: &ActivityContext,
-    registry: &str,
-    repository: &str,
-    revision: &str,
-    source: &str,
-    path: &Path,
-) -> Result<(), ActivityError> {
-    let artifact_url = format!("oci://{registry}/{repository}:{revision}");
-    let path = path.to_string_lossy().to_string();
-
-    info!(artifact = %artifact_url, path = %path, "pushing Flux OCI artifact");
-
-    let mut command = Command::new("flux");
-    command.args([
-        "push",
-        "artifact",
-        &artifact_url,
-        "--path",
-        &path,
-        "--source",
-        source,
-        "--revision",
-        revision,
-        "--insecure-registry",
-    ]);
-    run_checked_command(ctx, &mut command, "flux push artifact").await?;
 
+    info!(
+        pushed,
+        unchanged = bundle.apps.len() + 1 - pushed,
+        "pushed apps OCI artifacts"
+    );
     Ok(())
 }
@@ -0,0 +1,96 @@
+use super::forge::{CommitStatus, ForgeNotifier, forge_status_error};
+use anyhow::Context;
+use reqwest::{Method, StatusCode};
+use serde_json::{Value as JsonValue, json};
+use std::env;
+
+pub(crate) struct GithubForge<'a> {
+    api_url: &'a str,
+}
+
+impl<'a> GithubForge<'a> {
+    pub(crate) fn new(api_url: &'a str) -> Self {
+        Self { api_url }
+    }
+
+    async fn expect_status(
+        &self,
+        method: Method,
+        path: &str,
+        payload: Option<JsonValue>,
+        expected: &[StatusCode],
+    ) -> anyhow::Result<Vec<u8>> {
+        let (status, body) = github_request(method.clone(), self.api_url, path, payload).await?;
+        if expected.contains(&status) {
+            Ok(body)
+        } else {
+            Err(forge_status_error(method, path, status, &body))
+        }
+    }
+}
+
+impl ForgeNotifier for GithubForge<'_> {
+    async fn create_commit_status(
+        &self,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> anyhow::Result<()> {
+        let path = format!("/repos/{repo}/statuses/{sha}");
+        self.expect_status(
+            Method::POST,
+            &path,
+            Some(json!({
+                "context": status.context,
+                "description": status.description,
+                "state": status.state,
+                "target_url": status.target_url,
+            })),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn comment_on_pull_request(
+        &self,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        let path = format!("/repos/{repo}/issues/{number}/comments");
+        self.expect_status(
+            Method::POST,
+            &path,
+            Some(json!({ "body": body })),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+async fn github_request(
+    method: Method,
+    api_url: &str,
+    path: &str,
+    payload: Option<JsonValue>,
+) -> anyhow::Result<(StatusCode, Vec<u8>)> {
+    let token = env::var("GITHUB_TOKEN").context("GITHUB_TOKEN is required")?;
+    let mut request = reqwest::Client::new()
+        .request(method, format!("{}{path}", api_url.trim_end_matches('/')))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "netamos");
+
+    if let Some(payload) = payload {
+        request = request.json(&payload);
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    let body = response.bytes().await?.to_vec();
+    Ok((status, body))
+}
@@ -0,0 +1,127 @@
+use super::forge::{CommitStatus, ForgeNotifier, forge_status_error};
+use anyhow::Context;
+use reqwest::{Method, StatusCode};
+use serde_json::{Value as JsonValue, json};
+use std::env;
+
+pub(crate) struct GitlabForge<'a> {
+    api_url: &'a str,
+}
+
+impl<'a> GitlabForge<'a> {
+    pub(crate) fn new(api_url: &'a str) -> Self {
+        Self { api_url }
+    }
+
+    async fn expect_status(
+        &self,
+        method: Method,
+        path: &str,
+        payload: Option<JsonValue>,
+        expected: &[StatusCode],
+    ) -> anyhow::Result<Vec<u8>> {
+        let (status, body) = gitlab_request(method.clone(), self.api_url, path, payload).await?;
+        if expected.contains(&status) {
+            Ok(body)
+        } else {
+            Err(forge_status_error(method, path, status, &body))
+        }
+    }
+}
+
+impl ForgeNotifier for GitlabForge<'_> {
+    async fn create_commit_status(
+        &self,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> anyhow::Result<()> {
+        let path = format!("{}/statuses/{sha}", project_path(repo));
+        self.expect_status(
+            Method::POST,
+            &path,
+            Some(json!({
+                "name": status.context,
+                "description": status.description,
+                "state": gitlab_state(&status.state),
+                "target_url": status.target_url,
+            })),
+            &[StatusCode::OK, StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn comment_on_pull_request(
+        &self,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        let path = format!("{}/merge_requests/{number}/notes", project_path(repo));
+        self.expect_status(
+            Method::POST,
+            &path,
+            Some(json!({ "body": body })),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn project_path(repo: &str) -> String {
+    format!("/projects/{}", repo.replace('/', "%2F"))
+}
+
+fn gitlab_state(state: &str) -> &str {
+    match state {
+        "failure" | "error" => "failed",
+        other => other,
+    }
+}
+
+async fn gitlab_request(
+    method: Method,
+    api_url: &str,
+    path: &str,
+    payload: Option<JsonValue>,
+) -> anyhow::Result<(StatusCode, Vec<u8>)> {
+    let token = env::var("GITLAB_TOKEN").context("GITLAB_TOKEN is required")?;
+    let mut request = reqwest::Client::new()
+        .request(
+            method,
+            format!("{}/api/v4{path}", api_url.trim_end_matches('/')),
+        )
+        .header("PRIVATE-TOKEN", token)
+        .header("Accept", "application/json");
+
+    if let Some(payload) = payload {
+        request = request.json(&payload);
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    let body = response.bytes().await?.to_vec();
+    Ok((status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitlab_state_maps_failures() {
+        assert_eq!(gitlab_state("failure"), "failed");
+        assert_eq!(gitlab_state("error"), "failed");
+        assert_eq!(gitlab_state("success"), "success");
+    }
+
+    #[test]
+    fn project_path_accepts_ids_and_paths() {
+        assert_eq!(project_path("42"), "/projects/42");
+        assert_eq!(project_path("group/api"), "/projects/group%2Fapi");
+    }
+}
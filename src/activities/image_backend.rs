@@ -0,0 +1,780 @@
+use super::{
+    build_log::BuildLog,
+    process::{run_command, run_logged_command, run_stdout_command},
+};
+use crate::core::app::{build_config::BuildConfig, image::Image};
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::{collections::BTreeSet, env, path::Path, str::FromStr};
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+const BUILD_CACHE_TAG: &str = "buildcache-image";
+const DEFAULT_BUILDPACKS_BUILDER: &str = "paketobuildpacks/builder-jammy-base";
+const DEFAULT_BUILDX_BUILDER: &str = "netamos";
+const BINFMT_IMAGE: &str = "docker.io/tonistiigi/binfmt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageBackend {
+    Docker,
+    Buildkit,
+    Buildah,
+}
+
+impl FromStr for ImageBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "docker" => Ok(Self::Docker),
+            "buildkit" | "buildctl" => Ok(Self::Buildkit),
+            "buildah" => Ok(Self::Buildah),
+            other => Err(anyhow!(
+                "unsupported build backend {other}; expected docker, buildkit or buildah"
+            )),
+        }
+    }
+}
+
+impl ImageBackend {
+    pub(crate) fn from_env() -> anyhow::Result<Self> {
+        env::var("NETAMOS_BUILD_BACKEND").map_or(Ok(Self::Docker), |value| value.parse())
+    }
+
+    pub(crate) async fn image_exists(
+        self,
+        ctx: &ActivityContext,
+        image: &Image,
+        platforms: &[String],
+    ) -> Result<bool, ActivityError> {
+        let image_ref = format!("{image}");
+        let output = match self {
+            Self::Docker => {
+                let mut command = Command::new("docker");
+                command.args(["manifest", "inspect", &image_ref]);
+                run_command(ctx, &mut command, "docker manifest inspect").await?
+            }
+            Self::Buildkit | Self::Buildah => {
+                let mut command = Command::new("skopeo");
+                command.args(["inspect", "--raw"]);
+                if registry_insecure() {
+                    command.arg("--tls-verify=false");
+                }
+                command.arg(format!("docker://{image_ref}"));
+                run_command(ctx, &mut command, "skopeo inspect").await?
+            }
+        };
+        if !output.status.success() {
+            return Ok(false);
+        }
+
+        if platforms.is_empty() {
+            return Ok(true);
+        }
+        let available = match index_platforms(&String::from_utf8_lossy(&output.stdout)) {
+            Some(available) => available,
+            None => self
+                .image_config_platform(ctx, &image_ref)
+                .await?
+                .into_iter()
+                .collect(),
+        };
+        let complete = has_platforms(&available, platforms);
+        if !complete {
+            info!(image = %image_ref, ?platforms, "image is missing requested platforms");
+        }
+        Ok(complete)
+    }
+
+    async fn image_config_platform(
+        self,
+        ctx: &ActivityContext,
+        image_ref: &str,
+    ) -> Result<Option<ManifestPlatform>, ActivityError> {
+        let output = match self {
+            Self::Docker => {
+                let mut command = Command::new("docker");
+                command
+                    .args(["buildx", "imagetools", "inspect", image_ref])
+                    .args(["--format", "{{json .Image}}"]);
+                run_command(ctx, &mut command, "docker buildx imagetools inspect").await?
+            }
+            Self::Buildkit | Self::Buildah => {
+                let mut command = Command::new("skopeo");
+                command.args(["inspect", "--config"]);
+                if registry_insecure() {
+                    command.arg("--tls-verify=false");
+                }
+                command.arg(format!("docker://{image_ref}"));
+                run_command(ctx, &mut command, "skopeo inspect config").await?
+            }
+        };
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        Ok(serde_json::from_slice(&output.stdout).ok())
+    }
+
+    pub(crate) async fn image_digest(
+        self,
+        ctx: &ActivityContext,
+        image: &Image,
+    ) -> Result<String, ActivityError> {
+        let image_ref = format!("{image}");
+        let digest = match self {
+            Self::Docker => {
+                let mut command = Command::new("docker");
+                command
+                    .args(["buildx", "imagetools", "inspect", &image_ref])
+                    .args(["--format", "{{.Manifest.Digest}}"]);
+                run_stdout_command(ctx, &mut command, "docker buildx imagetools inspect").await?
+            }
+            Self::Buildkit | Self::Buildah => {
+                let mut command = Command::new("skopeo");
+                command.args(["inspect", "--format", "{{.Digest}}"]);
+                if registry_insecure() {
+                    command.arg("--tls-verify=false");
+                }
+                command.arg(format!("docker://{image_ref}"));
+                run_stdout_command(ctx, &mut command, "skopeo inspect digest").await?
+            }
+        };
+        if !is_manifest_digest(&digest) {
+            return Err(
+                anyhow!("registry returned an invalid digest for {image_ref}: {digest}").into(),
+            );
+        }
+        Ok(digest)
+    }
+
+    pub(crate) async fn build_and_push(
+        self,
+        ctx: &ActivityContext,
+        context: &Path,
+        dockerfile: Option<&str>,
+        image: &Image,
+        config: &BuildConfig,
+        log: Option<&BuildLog>,
+    ) -> Result<(), ActivityError> {
+        match self {
+            Self::Docker => {
+                docker_build_and_push(ctx, context, dockerfile, image, config, log).await
+            }
+            Self::Buildkit => {
+                buildctl_build_and_push(ctx, context, dockerfile, image, config, log).await
+            }
+            Self::Buildah => {
+                buildah_build_and_push(ctx, context, dockerfile, image, config, log).await
+            }
+        }
+    }
+
+    pub(crate) async fn buildpacks_build_and_push(
+        self,
+        ctx: &ActivityContext,
+        context: &Path,
+        image: &Image,
+        config: &BuildConfig,
+        log: Option<&BuildLog>,
+    ) -> Result<(), ActivityError> {
+        if self != Self::Docker {
+            return Err(anyhow!("Buildpacks builds require the docker build backend").into());
+        }
+
+        let mut command = Command::new("pack");
+        command
+            .args(["build", &format!("{image}")])
+            .args(["--path", "."])
+            .args(["--builder", &buildpacks_builder()])
+            .arg("--publish");
+        if let Some(network) = build_network() {
+            command.args(["--network", &network]);
+        }
+        if registry_insecure() {
+            command.args(["--insecure-registry", &image.registry]);
+        }
+        if build_registry_cache_enabled() {
+            command.args(["--cache-image", &build_cache_ref(image)]);
+        }
+        for (key, value) in &config.args {
+            command.args(["--env", &format!("{key}={value}")]);
+        }
+        command.current_dir(context);
+        run_logged_command(ctx, &mut command, "pack build", log).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn copy_image(
+        self,
+        ctx: &ActivityContext,
+        source: &Image,
+        image: &Image,
+        log: Option<&BuildLog>,
+    ) -> Result<(), ActivityError> {
+        let source_ref = format!("{source}");
+        let image_ref = format!("{image}");
+        match self {
+            Self::Docker => {
+                let mut command = Command::new("docker");
+                command.args(["pull", &source_ref]);
+                run_logged_command(ctx, &mut command, "docker pull source image", log).await?;
+
+                let mut command = Command::new("docker");
+                command.args(["tag", &source_ref, &image_ref]);
+                run_logged_command(ctx, &mut command, "docker tag source image", log).await?;
+
+                docker_push(ctx, &image_ref, log).await
+            }
+            Self::Buildkit | Self::Buildah => {
+                let mut command = Command::new("skopeo");
+                command.arg("copy");
+                if registry_insecure() {
+                    command.arg("--dest-tls-verify=false");
+                }
+                command
+                    .arg(format!("docker://{source_ref}"))
+                    .arg(format!("docker://{image_ref}"));
+                run_logged_command(ctx, &mut command, "skopeo copy source image", log).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn docker_build_and_push(
+    ctx: &ActivityContext,
+    context: &Path,
+    dockerfile: Option<&str>,
+    image: &Image,
+    config: &BuildConfig,
+    log: Option<&BuildLog>,
+) -> Result<(), ActivityError> {
+    if config.platforms.len() > 1 {
+        return buildx_build_and_push(ctx, context, dockerfile, image, config, log).await;
+    }
+
+    let image_ref = format!("{image}");
+    let cache_ref = pull_docker_build_cache(ctx, image).await?;
+    let mut command = Command::new("docker");
+    command.env("DOCKER_BUILDKIT", "1");
+    command.arg("build");
+    configure_build_network(&mut command);
+    if let Some(cache_ref) = &cache_ref {
+        command.args(["--cache-from", cache_ref]);
+    }
+    if let Some(dockerfile) = dockerfile {
+        command.args(["--file", dockerfile]);
+    }
+    configure_build_options(&mut command, config);
+    command.args(["--tag", &image_ref]);
+    if build_registry_cache_enabled() {
+        command.args(["--tag", &build_cache_ref(image)]);
+    }
+    command.arg(".").current_dir(context);
+    run_logged_command(ctx, &mut command, "docker build", log).await?;
+    push_docker_build_cache(ctx, image).await?;
+    docker_push(ctx, &image_ref, log).await
+}
+
+async fn buildx_build_and_push(
+    ctx: &ActivityContext,
+    context: &Path,
+    dockerfile: Option<&str>,
+    image: &Image,
+    config: &BuildConfig,
+    log: Option<&BuildLog>,
+) -> Result<(), ActivityError> {
+    install_binfmt(ctx, &config.platforms).await?;
+    let builder = ensure_buildx_builder(ctx, log).await?;
+
+    let insecure = registry_insecure();
+    let mut output = format!("type=image,name={image},push=true");
+    if insecure {
+        output.push_str(",registry.insecure=true");
+    }
+
+    let mut command = Command::new("docker");
+    command.args(["buildx", "build", "--builder", &builder]);
+    command.args(["--output", &output]);
+    configure_build_network(&mut command);
+    if build_registry_cache_enabled() {
+        let mut cache = format!("type=registry,ref={}", build_cache_ref(image));
+        if insecure {
+            cache.push_str(",registry.insecure=true");
+        }
+        command
+            .args(["--cache-from", &cache])
+            .args(["--cache-to", &format!("{cache},mode=max")]);
+    }
+    if let Some(dockerfile) = dockerfile {
+        command.args(["--file", dockerfile]);
+    }
+    configure_build_options(&mut command, config);
+    command.arg(".").current_dir(context);
+    run_logged_command(ctx, &mut command, "docker buildx build", log).await?;
+    Ok(())
+}
+
+async fn ensure_buildx_builder(
+    ctx: &ActivityContext,
+    log: Option<&BuildLog>,
+) -> Result<String, ActivityError> {
+    let builder = env::var("DOCKER_BUILDX_BUILDER")
+        .ok()
+        .map(|builder| builder.trim().to_string())
+        .filter(|builder| !builder.is_empty())
+        .unwrap_or_else(|| DEFAULT_BUILDX_BUILDER.to_string());
+
+    let mut command = Command::new("docker");
+    command.args(["buildx", "inspect", &builder]);
+    if run_command(ctx, &mut command, "docker buildx inspect")
+        .await?
+        .status
+        .success()
+    {
+        return Ok(builder);
+    }
+
+    let mut command = Command::new("docker");
+    command.args([
+        "buildx",
+        "create",
+        "--name",
+        &builder,
+        "--driver",
+        "docker-container",
+    ]);
+    if let Some(network) = build_network() {
+        command.args(["--driver-opt", &format!("network={network}")]);
+    }
+    run_logged_command(ctx, &mut command, "docker buildx create", log).await?;
+    Ok(builder)
+}
+
+async fn install_binfmt(ctx: &ActivityContext, platforms: &[String]) -> Result<(), ActivityError> {
+    let architectures = platforms
+        .iter()
+        .filter_map(|platform| platform.split('/').nth(1))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut command = Command::new("docker");
+    command
+        .args(["run", "--privileged", "--rm", BINFMT_IMAGE, "--install"])
+        .arg(&architectures);
+    let output = run_command(ctx, &mut command, "install binfmt emulators").await?;
+    if !output.status.success() {
+        warn!(
+            stderr = %String::from_utf8_lossy(&output.stderr),
+            "failed to install binfmt emulators; relying on host support"
+        );
+    }
+    Ok(())
+}
+
+async fn docker_push(
+    ctx: &ActivityContext,
+    image_ref: &str,
+    log: Option<&BuildLog>,
+) -> Result<(), ActivityError> {
+    let mut command = Command::new("docker");
+    command.args(["push", image_ref]);
+    run_logged_command(ctx, &mut command, "docker push", log).await?;
+    Ok(())
+}
+
+async fn pull_docker_build_cache(
+    ctx: &ActivityContext,
+    image: &Image,
+) -> Result<Option<String>, ActivityError> {
+    if !build_registry_cache_enabled() {
+        return Ok(None);
+    }
+
+    let cache_ref = build_cache_ref(image);
+    let mut command = Command::new("docker");
+    command.args(["pull", &cache_ref]);
+    let output = run_command(ctx, &mut command, "docker pull build cache").await?;
+    if output.status.success() {
+        info!(cache = %cache_ref, "pulled Docker build cache");
+        Ok(Some(cache_ref))
+    } else {
+        warn!(cache = %cache_ref, "Docker build cache unavailable");
+        Ok(None)
+    }
+}
+
+async fn push_docker_build_cache(
+    ctx: &ActivityContext,
+    image: &Image,
+) -> Result<(), ActivityError> {
+    if !build_registry_cache_enabled() {
+        return Ok(());
+    }
+
+    let cache_ref = build_cache_ref(image);
+    let mut command = Command::new("docker");
+    command.args(["push", &cache_ref]);
+    let output = run_command(ctx, &mut command, "docker push build cache").await?;
+    if output.status.success() {
+        info!(cache = %cache_ref, "pushed Docker build cache");
+    } else {
+        warn!(cache = %cache_ref, "failed to push Docker build cache");
+    }
+    Ok(())
+}
+
+async fn buildctl_build_and_push(
+    ctx: &ActivityContext,
+    context: &Path,
+    dockerfile: Option<&str>,
+    image: &Image,
+    config: &BuildConfig,
+    log: Option<&BuildLog>,
+) -> Result<(), ActivityError> {
+    let insecure = registry_insecure();
+    let mut output = format!("type=image,name={image},push=true");
+    if insecure {
+        output.push_str(",registry.insecure=true");
+    }
+
+    let mut command = Command::new("buildctl");
+    command
+        .args(["build", "--frontend", "dockerfile.v0"])
+        .args(["--local", "context=.", "--local", "dockerfile=."])
+        .args([
+            "--opt",
+            &format!("filename={}", dockerfile.unwrap_or("Dockerfile")),
+        ])
+        .args(["--output", &output]);
+    for opt in buildctl_network_opts()
+        .into_iter()
+        .chain(buildctl_build_opts(config))
+    {
+        command.args(["--opt", &opt]);
+    }
+    if build_registry_cache_enabled() {
+        let mut cache = format!("type=registry,ref={}", build_cache_ref(image));
+        if insecure {
+            cache.push_str(",registry.insecure=true");
+        }
+        command
+            .args(["--import-cache", &cache])
+            .args(["--export-cache", &format!("{cache},mode=max")]);
+    }
+    command.current_dir(context);
+    run_logged_command(ctx, &mut command, "buildctl build", log).await?;
+    Ok(())
+}
+
+async fn buildah_build_and_push(
+    ctx: &ActivityContext,
+    context: &Path,
+    dockerfile: Option<&str>,
+    image: &Image,
+    config: &BuildConfig,
+    log: Option<&BuildLog>,
+) -> Result<(), ActivityError> {
+    let image_ref = format!("{image}");
+    let insecure = registry_insecure();
+    let mut command = Command::new("buildah");
+    command.args(["build", "--layers"]);
+    if insecure {
+        command.arg("--tls-verify=false");
+    }
+    configure_build_network(&mut command);
+    if build_registry_cache_enabled() {
+        let cache_repository = buildah_cache_repository(image);
+        command
+            .args(["--cache-from", &cache_repository])
+            .args(["--cache-to", &cache_repository]);
+    }
+    if let Some(dockerfile) = dockerfile {
+        command.args(["--file", dockerfile]);
+    }
+    configure_build_options(&mut command, config);
+    let multi_platform = config.platforms.len() > 1;
+    if multi_platform {
+        command.args(["--manifest", &image_ref]);
+    } else {
+        command.args(["--tag", &image_ref]);
+    }
+    command.arg(".").current_dir(context);
+    run_logged_command(ctx, &mut command, "buildah build", log).await?;
+
+    let mut command = Command::new("buildah");
+    if multi_platform {
+        command.args(["manifest", "push", "--all"]);
+    } else {
+        command.arg("push");
+    }
+    if insecure {
+        command.arg("--tls-verify=false");
+    }
+    if multi_platform {
+        command.arg(&image_ref).arg(format!("docker://{image_ref}"));
+    } else {
+        command.arg(&image_ref);
+    }
+    run_logged_command(ctx, &mut command, "buildah push", log).await?;
+    Ok(())
+}
+
+fn configure_build_network(command: &mut Command) {
+    if let Some(network) = build_network() {
+        command.args(["--network", &network]);
+    }
+
+    for add_host in build_add_hosts() {
+        command.args(["--add-host", &add_host]);
+    }
+}
+
+fn configure_build_options(command: &mut Command, config: &BuildConfig) {
+    for (key, value) in &config.args {
+        command.args(["--build-arg", &format!("{key}={value}")]);
+    }
+    if let Some(target) = &config.target {
+        command.args(["--target", target]);
+    }
+    if !config.platforms.is_empty() {
+        command.args(["--platform", &config.platforms.join(",")]);
+    }
+}
+
+fn buildctl_build_opts(config: &BuildConfig) -> Vec<String> {
+    let mut opts = config
+        .args
+        .iter()
+        .map(|(key, value)| format!("build-arg:{key}={value}"))
+        .collect::<Vec<_>>();
+    if let Some(target) = &config.target {
+        opts.push(format!("target={target}"));
+    }
+    if !config.platforms.is_empty() {
+        opts.push(format!("platform={}", config.platforms.join(",")));
+    }
+    opts
+}
+
+fn buildctl_network_opts() -> Vec<String> {
+    let mut opts = Vec::new();
+    match build_network().as_deref() {
+        Some(network @ ("host" | "none")) => opts.push(format!("force-network-mode={network}")),
+        Some(network) => warn!(
+            network,
+            "BuildKit only supports host or none build networks"
+        ),
+        None => {}
+    }
+
+    let add_hosts = build_add_hosts()
+        .iter()
+        .map(|add_host| add_host.replacen(':', "=", 1))
+        .collect::<Vec<_>>();
+    if !add_hosts.is_empty() {
+        opts.push(format!("add-hosts={}", add_hosts.join(",")));
+    }
+    opts
+}
+
+fn build_network() -> Option<String> {
+    env::var("DOCKER_BUILD_NETWORK")
+        .ok()
+        .map(|network| network.trim().to_string())
+        .filter(|network| !network.is_empty())
+}
+
+fn build_add_hosts() -> Vec<String> {
+    env::var("DOCKER_BUILD_ADD_HOSTS")
+        .map(|add_hosts| {
+            add_hosts
+                .split(',')
+                .map(str::trim)
+                .filter(|add_host| !add_host.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn buildpacks_builder() -> String {
+    env::var("BUILDPACKS_BUILDER")
+        .ok()
+        .map(|builder| builder.trim().to_string())
+        .filter(|builder| !builder.is_empty())
+        .unwrap_or_else(|| DEFAULT_BUILDPACKS_BUILDER.to_string())
+}
+
+fn build_registry_cache_enabled() -> bool {
+    env_flag("DOCKER_BUILD_REGISTRY_CACHE", true)
+}
+
+pub(super) fn registry_insecure() -> bool {
+    env_flag("REGISTRY_INSECURE", false)
+}
+
+pub(super) fn env_flag(name: &str, default: bool) -> bool {
+    env::var(name).map_or(default, |value| {
+        !matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "0" | "false" | "no" | "off"
+        )
+    })
+}
+
+#[derive(Deserialize)]
+struct ManifestIndex {
+    manifests: Vec<ManifestDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct ManifestDescriptor {
+    platform: Option<ManifestPlatform>,
+}
+
+#[derive(Deserialize)]
+struct ManifestPlatform {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+}
+
+fn index_platforms(manifest: &str) -> Option<Vec<ManifestPlatform>> {
+    let index = serde_json::from_str::<ManifestIndex>(manifest).ok()?;
+    Some(
+        index
+            .manifests
+            .into_iter()
+            .filter_map(|descriptor| descriptor.platform)
+            .collect(),
+    )
+}
+
+fn has_platforms(available: &[ManifestPlatform], platforms: &[String]) -> bool {
+    platforms.iter().all(|platform| {
+        let mut parts = platform.split('/');
+        let (os, architecture, variant) = (parts.next(), parts.next(), parts.next());
+        available.iter().any(|available| {
+            Some(available.os.as_str()) == os
+                && Some(available.architecture.as_str()) == architecture
+                && variant.is_none_or(|variant| available.variant.as_deref() == Some(variant))
+        })
+    })
+}
+
+fn is_manifest_digest(digest: &str) -> bool {
+    digest
+        .strip_prefix("sha256:")
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|ch| ch.is_ascii_hexdigit()))
+}
+
+fn build_cache_ref(image: &Image) -> String {
+    format!(
+        "{}/{}/{}:{BUILD_CACHE_TAG}",
+        image.registry, image.owner, image.repository
+    )
+}
+
+fn buildah_cache_repository(image: &Image) -> String {
+    format!(
+        "{}/{}/{}/buildcache",
+        image.registry, image.owner, image.repository
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> Image {
+        Image {
+            registry: "registry.registry.svc.cluster.local".to_string(),
+            owner: "apps/khuedoan".to_string(),
+            repository: "blog".to_string(),
+            tag: "revision".to_string(),
+            digest: None,
+        }
+    }
+
+    #[test]
+    fn build_cache_ref_uses_stable_app_tag() {
+        assert_eq!(
+            build_cache_ref(&image()),
+            "registry.registry.svc.cluster.local/apps/khuedoan/blog:buildcache-image"
+        );
+        assert_eq!(
+            buildah_cache_repository(&image()),
+            "registry.registry.svc.cluster.local/apps/khuedoan/blog/buildcache"
+        );
+    }
+
+    #[test]
+    fn has_platforms_checks_index_entries_and_image_config() {
+        let index = index_platforms(
+            r#"{
+            "schemaVersion": 2,
+            "manifests": [
+                {"digest": "sha256:a", "platform": {"os": "linux", "architecture": "amd64"}},
+                {"digest": "sha256:b", "platform": {"os": "linux", "architecture": "arm64", "variant": "v8"}},
+                {"digest": "sha256:c", "platform": {"os": "unknown", "architecture": "unknown"}}
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert!(
+            index_platforms(
+                r#"{"schemaVersion": 2, "config": {"digest": "sha256:d"}, "layers": []}"#
+            )
+            .is_none()
+        );
+        let single: Vec<ManifestPlatform> = vec![
+            serde_json::from_str(r#"{"architecture": "amd64", "os": "linux", "rootfs": {}}"#)
+                .unwrap(),
+        ];
+        let platforms =
+            |values: &[&str]| values.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert!(has_platforms(&index, &[]));
+        assert!(has_platforms(
+            &index,
+            &platforms(&["linux/amd64", "linux/arm64/v8"])
+        ));
+        assert!(has_platforms(&index, &platforms(&["linux/arm64"])));
+        assert!(!has_platforms(&index, &platforms(&["linux/arm/v7"])));
+        assert!(has_platforms(&single, &platforms(&["linux/amd64"])));
+        assert!(!has_platforms(&single, &platforms(&["linux/arm64"])));
+        assert!(!has_platforms(
+            &single,
+            &platforms(&["linux/amd64", "linux/arm64"])
+        ));
+    }
+
+    #[test]
+    fn is_manifest_digest_requires_sha256() {
+        assert!(is_manifest_digest(&format!("sha256:{}", "a".repeat(64))));
+        assert!(!is_manifest_digest("sha256:abc"));
+        assert!(!is_manifest_digest(&format!("md5:{}", "a".repeat(64))));
+        assert!(!is_manifest_digest(""));
+    }
+
+    #[test]
+    fn image_backend_parses_supported_names() {
+        assert_eq!(
+            "docker".parse::<ImageBackend>().unwrap(),
+            ImageBackend::Docker
+        );
+        assert_eq!(
+            " BuildKit ".parse::<ImageBackend>().unwrap(),
+            ImageBackend::Buildkit
+        );
+        assert_eq!(
+            "buildah".parse::<ImageBackend>().unwrap(),
+            ImageBackend::Buildah
+        );
+        assert!("podman".parse::<ImageBackend>().is_err());
+    }
+}
@@ -1,7 +1,9 @@
+use super::{build_log::BuildLog, registry_auth::configure_registry_env};
 use anyhow::anyhow;
 use std::process::{Output, Stdio};
 use temporalio_sdk::activities::{ActivityContext, ActivityError};
 use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
     process::Command,
     time::{Duration, MissedTickBehavior},
 };
@@ -19,6 +21,7 @@ pub async fn run_command(
 
     ctx.record_heartbeat(vec![]);
     command.kill_on_drop(true);
+    configure_registry_env(command);
     command.stdout(Stdio::piped()).stderr(Stdio::piped());
     let child = command
         .spawn()
@@ -51,6 +54,82 @@ pub async fn run_checked_command(
     Ok(output)
 }
 
+pub(crate) async fn run_logged_command(
+    ctx: &ActivityContext,
+    command: &mut Command,
+    operation: &str,
+    log: Option<&BuildLog>,
+) -> Result<Output, ActivityError> {
+    let Some(log) = log else {
+        return run_checked_command(ctx, command, operation).await;
+    };
+    if ctx.is_cancelled() {
+        return Err(ActivityError::cancelled());
+    }
+
+    ctx.record_heartbeat(vec![]);
+    log.write_line(&format!("==> {operation}")).await;
+    command.kill_on_drop(true);
+    configure_registry_env(command);
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("failed to start {operation}: {e}"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("{operation} stdout was not captured"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow!("{operation} stderr was not captured"))?;
+    let output = async {
+        let (stdout, stderr, status) = tokio::try_join!(
+            copy_lines(stdout, log),
+            copy_lines(stderr, log),
+            child.wait()
+        )?;
+        Ok::<_, std::io::Error>(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    };
+    tokio::pin!(output);
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let output = loop {
+        tokio::select! {
+            result = &mut output => break result.map_err(ActivityError::from)?,
+            _ = heartbeat.tick() => ctx.record_heartbeat(vec![]),
+            _ = ctx.cancelled() => return Err(ActivityError::cancelled()),
+        }
+    };
+    if !output.status.success() {
+        log.write_line(&format!("==> {operation} failed: {}", output.status))
+            .await;
+        return Err(command_error(operation, &output).into());
+    }
+
+    Ok(output)
+}
+
+async fn copy_lines(stream: impl AsyncRead + Unpin, log: &BuildLog) -> std::io::Result<Vec<u8>> {
+    let mut captured = Vec::new();
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        log.write_line(text.trim_end_matches(['\r', '\n'])).await;
+        captured.extend_from_slice(&line);
+        line.clear();
+    }
+
+    Ok(captured)
+}
+
 pub async fn run_stdout_command(
     ctx: &ActivityContext,
     command: &mut Command,
@@ -0,0 +1,68 @@
+use super::{
+    forge::ForgeNotifier, forgejo::ForgejoForge, github::GithubForge, gitlab::GitlabForge,
+};
+use serde::{Deserialize, Serialize};
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum PullRequestTarget {
+    Forgejo {
+        forgejo_url: String,
+        repo: String,
+        number: u64,
+    },
+    Github {
+        api_url: String,
+        repo: String,
+        number: u64,
+    },
+    Gitlab {
+        api_url: String,
+        project_id: u64,
+        number: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentPullRequestInput {
+    pub target: PullRequestTarget,
+    pub body: String,
+}
+
+pub async fn comment_pull_request(
+    _ctx: ActivityContext,
+    input: CommentPullRequestInput,
+) -> Result<(), ActivityError> {
+    match input.target {
+        PullRequestTarget::Forgejo {
+            forgejo_url,
+            repo,
+            number,
+        } => {
+            ForgejoForge::new(&forgejo_url)
+                .comment_on_pull_request(&repo, number, &input.body)
+                .await?
+        }
+        PullRequestTarget::Github {
+            api_url,
+            repo,
+            number,
+        } => {
+            GithubForge::new(&api_url)
+                .comment_on_pull_request(&repo, number, &input.body)
+                .await?
+        }
+        PullRequestTarget::Gitlab {
+            api_url,
+            project_id,
+            number,
+        } => {
+            GitlabForge::new(&api_url)
+                .comment_on_pull_request(&project_id.to_string(), number, &input.body)
+                .await?
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use super::image_backend::registry_insecure;
+use crate::gitops::{OciCredentials, OciRegistryClient, OciRepositoryAccess};
+use anyhow::{Context, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Map, Value as JsonValue, json};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use tokio::process::Command;
+use tracing::info;
+
+const SYSTEM_CA_BUNDLES: [&str; 3] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/ssl/certs/ca-bundle.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+];
+
+static REGISTRY_ENV: OnceLock<Vec<(&'static str, PathBuf)>> = OnceLock::new();
+
+pub fn prepare_registry_auth() -> anyhow::Result<()> {
+    let registry = env::var("REGISTRY").unwrap_or_else(|_| "localhost:5000".to_string());
+    let credentials = registry_credentials()?;
+    let auth_file = env::var("REGISTRY_AUTH_FILE").ok().map(PathBuf::from);
+    let ca_file = env::var("REGISTRY_CA_FILE").ok().map(PathBuf::from);
+    let mut registry_env = Vec::new();
+    if credentials.is_none() && auth_file.is_none() && ca_file.is_none() {
+        let _ = REGISTRY_ENV.set(registry_env);
+        return Ok(());
+    }
+
+    let config_dir = env::var("NETAMOS_REGISTRY_CONFIG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("/tmp/netamos/registry"));
+    fs::create_dir_all(&config_dir)
+        .with_context(|| format!("failed to create {}", config_dir.display()))?;
+
+    if credentials.is_some() || auth_file.is_some() {
+        let existing = match &auth_file {
+            Some(path) => Some(
+                serde_json::from_slice(
+                    &fs::read(path)
+                        .with_context(|| format!("failed to read {}", path.display()))?,
+                )
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let config = docker_config(existing, &registry, credentials.as_ref());
+        let config_path = config_dir.join("config.json");
+        write_private_file(&config_path, &serde_json::to_vec_pretty(&config)?)?;
+        registry_env.push(("DOCKER_CONFIG", config_dir.clone()));
+        registry_env.push(("REGISTRY_AUTH_FILE", config_path));
+    }
+
+    if let Some(ca_file) = &ca_file {
+        let mut bundle = system_ca_bundle()?;
+        bundle.extend(
+            fs::read(ca_file).with_context(|| format!("failed to read {}", ca_file.display()))?,
+        );
+        let bundle_path = config_dir.join("ca-bundle.pem");
+        write_private_file(&bundle_path, &bundle)?;
+        registry_env.push(("SSL_CERT_FILE", bundle_path));
+    }
+
+    info!(
+        registry = %registry,
+        authenticated = credentials.is_some(),
+        custom_ca = ca_file.is_some(),
+        "prepared registry configuration"
+    );
+    let _ = REGISTRY_ENV.set(registry_env);
+    Ok(())
+}
+
+pub(super) fn configure_registry_env(command: &mut Command) {
+    for (name, value) in REGISTRY_ENV.get().into_iter().flatten() {
+        command.env(name, value);
+    }
+}
+
+pub(super) fn oci_repository_access() -> OciRepositoryAccess {
+    let authenticated = env::var("REGISTRY_USERNAME").is_ok()
+        || env::var("REGISTRY_TOKEN").is_ok()
+        || env::var("REGISTRY_TOKEN_FILE").is_ok();
+    OciRepositoryAccess {
+        insecure: registry_insecure(),
+        secret_ref: env::var("REGISTRY_FLUX_SECRET")
+            .ok()
+            .or_else(|| authenticated.then(|| "registry-auth".to_string())),
+        cert_secret_ref: env::var("REGISTRY_FLUX_CERT_SECRET").ok().or_else(|| {
+            env::var("REGISTRY_CA_FILE")
+                .ok()
+                .map(|_| "registry-ca".to_string())
+        }),
+    }
+}
+
+pub(super) fn oci_registry_client(registry: &str) -> anyhow::Result<OciRegistryClient> {
+    let credentials = registry_credentials()?;
+    let ca_pem = match env::var("REGISTRY_CA_FILE") {
+        Ok(path) => Some(fs::read(&path).with_context(|| format!("failed to read {path}"))?),
+        Err(_) => None,
+    };
+    OciRegistryClient::new(
+        registry,
+        registry_insecure(),
+        credentials,
+        ca_pem.as_deref(),
+    )
+}
+
+fn registry_credentials() -> anyhow::Result<Option<OciCredentials>> {
+    let username = env::var("REGISTRY_USERNAME").ok();
+    let token = secret_from_env("REGISTRY_TOKEN")?;
+    let password = secret_from_env("REGISTRY_PASSWORD")?;
+    match (username, password, token) {
+        (_, Some(_), Some(_)) => Err(anyhow!(
+            "set either REGISTRY_PASSWORD or REGISTRY_TOKEN, not both"
+        )),
+        (Some(username), Some(password), None) => Ok(Some(OciCredentials { username, password })),
+        (None, Some(_), None) => Err(anyhow!("REGISTRY_PASSWORD requires REGISTRY_USERNAME")),
+        (username, None, Some(token)) => Ok(Some(OciCredentials {
+            username: username.unwrap_or_else(|| "token".to_string()),
+            password: token,
+        })),
+        (Some(_), None, None) => Err(anyhow!(
+            "REGISTRY_USERNAME requires REGISTRY_PASSWORD or REGISTRY_TOKEN"
+        )),
+        (None, None, None) => Ok(None),
+    }
+}
+
+fn secret_from_env(name: &str) -> anyhow::Result<Option<String>> {
+    if let Ok(value) = env::var(name) {
+        return Ok(Some(value));
+    }
+    let file_name = format!("{name}_FILE");
+    match env::var(&file_name) {
+        Ok(path) => Ok(Some(
+            fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {file_name} {path}"))?
+                .trim()
+                .to_string(),
+        )),
+        Err(_) => Ok(None),
+    }
+}
+
+fn docker_config(
+    existing: Option<JsonValue>,
+    registry: &str,
+    credentials: Option<&OciCredentials>,
+) -> JsonValue {
+    let mut config = match existing {
+        Some(JsonValue::Object(config)) => config,
+        _ => Map::new(),
+    };
+    if let Some(credentials) = credentials {
+        let auths = config
+            .entry("auths")
+            .or_insert_with(|| JsonValue::Object(Map::new()));
+        if !auths.is_object() {
+            *auths = JsonValue::Object(Map::new());
+        }
+        if let JsonValue::Object(auths) = auths {
+            auths.insert(
+                registry.to_string(),
+                json!({
+                    "auth": STANDARD.encode(format!(
+                        "{}:{}",
+                        credentials.username, credentials.password
+                    )),
+                }),
+            );
+        }
+    }
+    JsonValue::Object(config)
+}
+
+fn system_ca_bundle() -> anyhow::Result<Vec<u8>> {
+    let candidates = env::var("SSL_CERT_FILE")
+        .ok()
+        .into_iter()
+        .chain(env::var("NIX_SSL_CERT_FILE").ok())
+        .map(PathBuf::from)
+        .chain(SYSTEM_CA_BUNDLES.into_iter().map(PathBuf::from));
+    for path in candidates {
+        if path.is_file() {
+            let mut bundle = fs::read(&path)?;
+            if !bundle.ends_with(b"\n") {
+                bundle.push(b'\n');
+            }
+            return Ok(bundle);
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn write_private_file(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    file.write_all(contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docker_config_merges_registry_credentials() {
+        let existing = json!({
+            "auths": {
+                "docker.io": { "auth": "ZXhpc3Rpbmc=" },
+            },
+            "credHelpers": { "ghcr.io": "gh" },
+        });
+        let credentials = OciCredentials {
+            username: "netamos".to_string(),
+            password: "secret".to_string(),
+        };
+
+        let config = docker_config(Some(existing), "registry.example.com", Some(&credentials));
+
+        assert_eq!(config["auths"]["docker.io"]["auth"], "ZXhpc3Rpbmc=");
+        assert_eq!(
+            config["auths"]["registry.example.com"]["auth"],
+            STANDARD.encode("netamos:secret")
+        );
+        assert_eq!(config["credHelpers"]["ghcr.io"], "gh");
+    }
+}
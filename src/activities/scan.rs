@@ -0,0 +1,230 @@
+use super::{
+    image_backend::registry_insecure, process::run_checked_command, workspace::TempWorkspace,
+};
+use crate::core::app::image::Image;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+use tokio::process::Command;
+use tracing::info;
+
+const SCAN_ARTIFACT_TYPE: &str = "application/vnd.netamos.image-scan.v1+json";
+const SBOM_FILE: &str = "sbom.spdx.json";
+const VULNERABILITIES_FILE: &str = "vulnerabilities.grype.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Negligible,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "negligible" => Ok(Self::Negligible),
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            "critical" => Ok(Self::Critical),
+            other => Err(format!("unsupported vulnerability severity {other:?}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageScanPolicy {
+    #[serde(default)]
+    pub fail_on: Option<Severity>,
+}
+
+impl FromStr for ImageScanPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Self { fail_on: None }),
+            severity => Ok(Self {
+                fail_on: Some(severity.parse()?),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanImageInput {
+    pub image: Image,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VulnerabilityCounts {
+    pub critical: usize,
+    pub high: usize,
+    pub medium: usize,
+    pub low: usize,
+    pub negligible: usize,
+    pub unknown: usize,
+}
+
+impl VulnerabilityCounts {
+    pub fn at_or_above(&self, severity: Severity) -> usize {
+        [
+            (Severity::Critical, self.critical),
+            (Severity::High, self.high),
+            (Severity::Medium, self.medium),
+            (Severity::Low, self.low),
+            (Severity::Negligible, self.negligible),
+        ]
+        .into_iter()
+        .filter(|(level, _)| *level >= severity)
+        .map(|(_, count)| count)
+        .sum()
+    }
+
+    fn record(&mut self, severity: &str) {
+        match severity.parse() {
+            Ok(Severity::Critical) => self.critical += 1,
+            Ok(Severity::High) => self.high += 1,
+            Ok(Severity::Medium) => self.medium += 1,
+            Ok(Severity::Low) => self.low += 1,
+            Ok(Severity::Negligible) => self.negligible += 1,
+            Err(_) => self.unknown += 1,
+        }
+    }
+}
+
+impl fmt::Display for VulnerabilityCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} critical, {} high, {} medium, {} low",
+            self.critical, self.high, self.medium, self.low
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageScanReport {
+    pub image: String,
+    pub vulnerabilities: VulnerabilityCounts,
+}
+
+impl ImageScanReport {
+    pub fn blocks(&self, policy: &ImageScanPolicy) -> bool {
+        policy
+            .fail_on
+            .is_some_and(|severity| self.vulnerabilities.at_or_above(severity) > 0)
+    }
+}
+
+#[derive(Deserialize)]
+struct GrypeReport {
+    #[serde(default)]
+    matches: Vec<GrypeMatch>,
+}
+
+#[derive(Deserialize)]
+struct GrypeMatch {
+    vulnerability: GrypeVulnerability,
+}
+
+#[derive(Deserialize)]
+struct GrypeVulnerability {
+    #[serde(default)]
+    severity: String,
+}
+
+pub async fn scan_image(
+    ctx: ActivityContext,
+    input: ScanImageInput,
+) -> Result<ImageScanReport, ActivityError> {
+    let image_ref = input.image.pinned_reference();
+    let workspace = TempWorkspace::new("scan", &input.image.repository, &input.image.tag);
+    tokio::fs::create_dir_all(workspace.path())
+        .await
+        .map_err(|error| anyhow!(error))?;
+
+    let mut command = Command::new("syft");
+    command
+        .args(["scan", &format!("registry:{image_ref}")])
+        .args(["--output", &format!("spdx-json={SBOM_FILE}")])
+        .current_dir(workspace.path());
+    if registry_insecure() {
+        command.env("SYFT_REGISTRY_INSECURE_USE_HTTP", "true");
+    }
+    run_checked_command(&ctx, &mut command, "syft scan").await?;
+
+    let mut command = Command::new("grype");
+    command
+        .arg(format!("sbom:{SBOM_FILE}"))
+        .args(["--output", "json", "--file", VULNERABILITIES_FILE])
+        .current_dir(workspace.path());
+    run_checked_command(&ctx, &mut command, "grype scan").await?;
+
+    let raw = tokio::fs::read(workspace.path().join(VULNERABILITIES_FILE))
+        .await
+        .map_err(|error| anyhow!(error))?;
+    let vulnerabilities = count_vulnerabilities(&raw)?;
+
+    let mut command = Command::new("oras");
+    command.args(["attach", "--artifact-type", SCAN_ARTIFACT_TYPE]);
+    if registry_insecure() {
+        command.arg("--plain-http");
+    }
+    command
+        .arg(&image_ref)
+        .arg(format!("{SBOM_FILE}:application/spdx+json"))
+        .arg(format!(
+            "{VULNERABILITIES_FILE}:application/vnd.anchore.grype+json"
+        ))
+        .current_dir(workspace.path());
+    run_checked_command(&ctx, &mut command, "oras attach scan report").await?;
+
+    info!(image = %image_ref, vulnerabilities = %vulnerabilities, "scanned image");
+    Ok(ImageScanReport {
+        image: image_ref,
+        vulnerabilities,
+    })
+}
+
+fn count_vulnerabilities(raw: &[u8]) -> Result<VulnerabilityCounts, ActivityError> {
+    let report: GrypeReport = serde_json::from_slice(raw)
+        .map_err(|error| anyhow!("failed to parse grype report: {error}"))?;
+    let mut counts = VulnerabilityCounts::default();
+    for found in &report.matches {
+        counts.record(&found.vulnerability.severity);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_report_blocks_at_or_above_threshold() {
+        let raw = br#"{"matches":[
+            {"vulnerability":{"id":"CVE-1","severity":"High"}},
+            {"vulnerability":{"id":"CVE-2","severity":"Medium"}},
+            {"vulnerability":{"id":"CVE-3","severity":"Unknown"}}
+        ]}"#;
+        let report = ImageScanReport {
+            image: "registry.example.com/apps/khuedoan/blog:abc".to_string(),
+            vulnerabilities: count_vulnerabilities(raw).unwrap(),
+        };
+
+        assert_eq!(report.vulnerabilities.high, 1);
+        assert_eq!(report.vulnerabilities.unknown, 1);
+        assert!(report.blocks(&"high".parse().unwrap()));
+        assert!(report.blocks(&"medium".parse().unwrap()));
+        assert!(!report.blocks(&"critical".parse().unwrap()));
+        assert!(!report.blocks(&"none".parse().unwrap()));
+        assert!("severe".parse::<ImageScanPolicy>().is_err());
+    }
+}
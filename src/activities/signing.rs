@@ -0,0 +1,260 @@
+use super::{
+    git::non_retryable_error,
+    image_backend::{ImageBackend, env_flag, registry_insecure},
+    process::{run_checked_command, run_command},
+    workspace::TempWorkspace,
+};
+use crate::core::app::{image::Image, source::Source};
+use anyhow::anyhow;
+use serde_json::{Value as JsonValue, json};
+use std::env;
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+use tokio::process::Command;
+use tracing::info;
+
+const PROVENANCE_BUILD_TYPE: &str = "https://github.com/khuedoan/platform-engine/push-to-deploy@v1";
+const PROVENANCE_BUILDER_ID: &str = "https://github.com/khuedoan/platform-engine";
+
+fn signing_key() -> Option<String> {
+    if let Ok(path) = env::var("COSIGN_KEY_FILE") {
+        return Some(path);
+    }
+    env::var("COSIGN_PRIVATE_KEY")
+        .ok()
+        .map(|_| "env://COSIGN_PRIVATE_KEY".to_string())
+}
+
+fn verification_key() -> Option<String> {
+    if let Ok(path) = env::var("COSIGN_PUBLIC_KEY_FILE") {
+        return Some(path);
+    }
+    env::var("COSIGN_PUBLIC_KEY")
+        .ok()
+        .map(|_| "env://COSIGN_PUBLIC_KEY".to_string())
+}
+
+fn require_signed_images() -> bool {
+    env_flag("NETAMOS_REQUIRE_SIGNED_IMAGES", false)
+}
+
+pub(super) async fn sign_image(
+    ctx: &ActivityContext,
+    image: &Image,
+    source: &Source,
+) -> Result<(), ActivityError> {
+    let Some(key) = signing_key() else {
+        info!(image = %image, "no cosign key configured, skipping image signing");
+        return Ok(());
+    };
+    if let Some(public_key) = verification_key()
+        && image_is_signed(ctx, &public_key, image).await?
+    {
+        info!(image = %image, "image is already signed");
+        return Ok(());
+    }
+
+    sign_and_attest_image(ctx, &key, image, source).await
+}
+
+async fn sign_and_attest_image(
+    ctx: &ActivityContext,
+    key: &str,
+    image: &Image,
+    source: &Source,
+) -> Result<(), ActivityError> {
+    let image_ref = signed_reference(image)?;
+    let workflow_id = ctx
+        .info()
+        .workflow_execution
+        .as_ref()
+        .map(|execution| execution.workflow_id.clone())
+        .unwrap_or_default();
+
+    let mut command = cosign_command("sign");
+    command.args(["--key", key, &image_ref]);
+    run_checked_command(ctx, &mut command, "cosign sign").await?;
+
+    let workspace = TempWorkspace::new("attestation", &image_ref, &workflow_id);
+    tokio::fs::create_dir_all(workspace.path())
+        .await
+        .map_err(|error| anyhow!(error))?;
+    let predicate_path = workspace.path().join("provenance.json");
+    let predicate = provenance_predicate(source, &workflow_id);
+    tokio::fs::write(
+        &predicate_path,
+        serde_json::to_vec_pretty(&predicate).map_err(|error| anyhow!(error))?,
+    )
+    .await
+    .map_err(|error| anyhow!(error))?;
+
+    let mut command = cosign_command("attest");
+    command
+        .args(["--key", key, "--type", "slsaprovenance1", "--predicate"])
+        .arg(&predicate_path)
+        .arg(&image_ref);
+    run_checked_command(ctx, &mut command, "cosign attest").await?;
+
+    info!(image = %image_ref, workflow_id = %workflow_id, "signed image and attached provenance");
+    Ok(())
+}
+
+async fn image_is_signed(
+    ctx: &ActivityContext,
+    key: &str,
+    image: &Image,
+) -> Result<bool, ActivityError> {
+    let image = match image.digest {
+        Some(_) => image.clone(),
+        None => Image {
+            digest: Some(ImageBackend::from_env()?.image_digest(ctx, image).await?),
+            ..image.clone()
+        },
+    };
+
+    let mut command = Command::new("cosign");
+    command.args(["verify", "--key", key, "--insecure-ignore-tlog"]);
+    if registry_insecure() {
+        command.arg("--allow-insecure-registry");
+    }
+    command.args(["--output", "json", &signed_reference(&image)?]);
+    let output = run_command(ctx, &mut command, "cosign verify").await?;
+    Ok(output.status.success())
+}
+
+pub(super) async fn ensure_image_signed(
+    ctx: &ActivityContext,
+    image: &Image,
+) -> Result<(), ActivityError> {
+    if !require_signed_images() {
+        return Ok(());
+    }
+    let Some(key) = verification_key() else {
+        return Err(non_retryable_error(anyhow!(
+            "NETAMOS_REQUIRE_SIGNED_IMAGES is set but COSIGN_PUBLIC_KEY_FILE or COSIGN_PUBLIC_KEY is not"
+        )));
+    };
+    if !image_is_signed(ctx, &key, image).await? {
+        return Err(non_retryable_error(anyhow!(
+            "refusing to publish unsigned image {}",
+            image.pinned_reference()
+        )));
+    }
+    Ok(())
+}
+
+fn cosign_command(subcommand: &str) -> Command {
+    let mut command = Command::new("cosign");
+    command.args([subcommand, "--yes"]);
+    if !env_flag("COSIGN_TLOG_UPLOAD", false) {
+        command.arg("--tlog-upload=false");
+    }
+    if registry_insecure() {
+        command.arg("--allow-insecure-registry");
+    }
+    command
+}
+
+fn signed_reference(image: &Image) -> Result<String, ActivityError> {
+    let digest = image.digest.as_ref().ok_or_else(|| {
+        non_retryable_error(anyhow!("cannot sign {image} without a manifest digest"))
+    })?;
+    Ok(format!(
+        "{}/{}/{}@{digest}",
+        image.registry, image.owner, image.repository
+    ))
+}
+
+fn provenance_predicate(source: &Source, workflow_id: &str) -> JsonValue {
+    let (source_uri, source_digest) = match source {
+        Source::Git { url, revision, .. } => {
+            (format!("git+{url}"), json!({ "gitCommit": revision }))
+        }
+        Source::Docker(image) => (
+            format!("docker://{image}"),
+            image
+                .digest
+                .as_ref()
+                .and_then(|digest| digest.split_once(':'))
+                .map_or_else(|| json!({}), |(algorithm, hex)| json!({ algorithm: hex })),
+        ),
+    };
+
+    json!({
+        "buildDefinition": {
+            "buildType": PROVENANCE_BUILD_TYPE,
+            "externalParameters": {
+                "source": {
+                    "uri": source_uri,
+                    "digest": source_digest,
+                },
+            },
+            "internalParameters": {
+                "workflowId": workflow_id,
+            },
+            "resolvedDependencies": [
+                {
+                    "uri": source_uri,
+                    "digest": source_digest,
+                },
+            ],
+        },
+        "runDetails": {
+            "builder": {
+                "id": PROVENANCE_BUILDER_ID,
+            },
+            "metadata": {
+                "invocationId": workflow_id,
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn provenance_predicate_records_source_and_workflow() {
+        let source = Source::Git {
+            name: "blog".to_string(),
+            owner: "khuedoan".to_string(),
+            url: "https://code.example.com/khuedoan/blog".to_string(),
+            revision: "6fbd90b77a81e0bcb330fddaa230feff744a7010".to_string(),
+            path: PathBuf::from("/tmp/blog"),
+        };
+
+        let predicate = provenance_predicate(&source, "push-to-deploy-blog-6fbd90b77a81");
+
+        assert_eq!(
+            predicate["buildDefinition"]["externalParameters"]["source"]["uri"],
+            "git+https://code.example.com/khuedoan/blog"
+        );
+        assert_eq!(
+            predicate["buildDefinition"]["externalParameters"]["source"]["digest"]["gitCommit"],
+            "6fbd90b77a81e0bcb330fddaa230feff744a7010"
+        );
+        assert_eq!(
+            predicate["runDetails"]["metadata"]["invocationId"],
+            "push-to-deploy-blog-6fbd90b77a81"
+        );
+    }
+
+    #[test]
+    fn signed_reference_requires_digest() {
+        let mut image = Image {
+            registry: "registry.example.com".to_string(),
+            owner: "apps/khuedoan".to_string(),
+            repository: "blog".to_string(),
+            tag: "6fbd90b".to_string(),
+            digest: None,
+        };
+        assert!(signed_reference(&image).is_err());
+
+        image.digest = Some("sha256:abc".to_string());
+        assert_eq!(
+            signed_reference(&image).unwrap(),
+            "registry.example.com/apps/khuedoan/blog@sha256:abc"
+        );
+    }
+}
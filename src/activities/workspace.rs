@@ -37,7 +37,7 @@ impl Drop for TempWorkspace {
     }
 }
 
-fn sanitize_path_component(input: &str) -> String {
+pub(super) fn sanitize_path_component(input: &str) -> String {
     input
         .chars()
         .map(|ch| {
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+const RESERVED_ENVIRONMENTS: [&str; 1] = ["promote"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthConfig {
     pub issuer: String,
@@ -14,6 +16,8 @@ pub struct UserInfo {
     pub subject: String,
     pub username: Option<String>,
     pub email: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,9 +28,18 @@ pub struct ProjectSummary {
     pub resources: Vec<String>,
     pub hostnames: Vec<String>,
     pub images: Vec<String>,
+    #[serde(default)]
+    pub image_versions: Vec<ImageVersion>,
     pub source_repos: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ImageVersion {
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowStarted {
     pub workflow_id: String,
@@ -39,6 +52,46 @@ pub struct WorkflowStatus {
     pub url: Option<String>,
     pub result: Option<serde_json::Value>,
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalStatus {
+    pub state: ApprovalState,
+    pub environment: String,
+    pub approver: Option<String>,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+}
+
+impl ApprovalStatus {
+    pub fn is_pending(&self) -> bool {
+        self.state == ApprovalState::Pending
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalState {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ApprovalState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalDecision {
+    pub approver: String,
 }
 
 impl WorkflowStatus {
@@ -61,8 +114,12 @@ pub struct CreateAppRequest {
     pub http_route: Option<CreateHttpRoute>,
     pub config: Vec<KeyValue>,
     pub secrets: Vec<KeyValue>,
+    #[serde(default)]
+    pub sealed_secrets: Vec<KeyValue>,
     pub volumes: Vec<CreateVolume>,
     pub postgres: Option<CreatePostgres>,
+    #[serde(default)]
+    pub autoscale: Option<CreateAutoscale>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +142,272 @@ impl DeleteAppRequest {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSecretsRequest {
+    pub tenant: String,
+    pub project: String,
+    pub environment: String,
+    #[serde(default)]
+    pub set: Vec<KeyValue>,
+    #[serde(default)]
+    pub unset: Vec<String>,
+}
+
+impl UpdateSecretsRequest {
+    pub fn app_path(&self) -> String {
+        format!("{}/{}/{}", self.tenant, self.project, self.environment)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_dns_name("tenant", &self.tenant)?;
+        validate_dns_name("project", &self.project)?;
+        validate_dns_name("environment", &self.environment)?;
+        if self.set.is_empty() && self.unset.is_empty() {
+            return Err("secret update needs at least one key to set or unset".to_string());
+        }
+        for key in self.set.iter().map(|item| &item.key).chain(&self.unset) {
+            validate_env_key(key)?;
+        }
+        if let Some(key) = self.set.iter().find(|item| self.unset.contains(&item.key)) {
+            return Err(format!("{} cannot be both set and unset", key.key));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentKind {
+    Deployment,
+    Service,
+    HttpRoute,
+    Config,
+    Secret,
+    Volume,
+    Postgres,
+    Autoscaler,
+}
+
+impl ComponentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deployment => "deployment",
+            Self::Service => "service",
+            Self::HttpRoute => "httproute",
+            Self::Config => "config",
+            Self::Secret => "secret",
+            Self::Volume => "volume",
+            Self::Postgres => "postgres",
+            Self::Autoscaler => "autoscaler",
+        }
+    }
+}
+
+impl std::str::FromStr for ComponentKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "deployment" => Ok(Self::Deployment),
+            "service" => Ok(Self::Service),
+            "httproute" | "route" => Ok(Self::HttpRoute),
+            "config" | "configmap" => Ok(Self::Config),
+            "secret" => Ok(Self::Secret),
+            "volume" | "persistentvolumeclaim" | "pvc" => Ok(Self::Volume),
+            "postgres" => Ok(Self::Postgres),
+            "autoscaler" | "horizontalpodautoscaler" | "hpa" => Ok(Self::Autoscaler),
+            other => Err(format!(
+                "unsupported component kind {other:?}; expected deployment, service, httproute, config, secret, volume, postgres or autoscaler"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ComponentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppComponent {
+    pub kind: ComponentKind,
+    pub name: String,
+}
+
+impl std::fmt::Display for AppComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.kind, self.name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveComponentsRequest {
+    pub tenant: String,
+    pub project: String,
+    pub environment: String,
+    pub components: Vec<AppComponent>,
+}
+
+impl RemoveComponentsRequest {
+    pub fn app_path(&self) -> String {
+        format!("{}/{}/{}", self.tenant, self.project, self.environment)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_dns_name("tenant", &self.tenant)?;
+        validate_dns_name("project", &self.project)?;
+        validate_dns_name("environment", &self.environment)?;
+        if self.components.is_empty() {
+            return Err("remove needs at least one component".to_string());
+        }
+        for component in &self.components {
+            match component.kind {
+                ComponentKind::Config | ComponentKind::Secret => validate_env_key(&component.name)?,
+                _ => validate_dns_name("component name", &component.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackAppRequest {
+    pub tenant: String,
+    pub project: String,
+    pub environment: String,
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+impl RollbackAppRequest {
+    pub fn app_path(&self) -> String {
+        format!("{}/{}/{}", self.tenant, self.project, self.environment)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_dns_name("tenant", &self.tenant)?;
+        validate_dns_name("project", &self.project)?;
+        validate_dns_name("environment", &self.environment)?;
+        if let Some(image) = &self.image
+            && image.trim().is_empty()
+        {
+            return Err("rollback image cannot be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageRevision {
+    pub commit_sha: String,
+    pub author: String,
+    pub timestamp: String,
+    pub image: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoteAppRequest {
+    pub tenant: String,
+    pub project: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl PromoteAppRequest {
+    pub fn app_path(&self) -> String {
+        format!("{}/{}", self.tenant, self.project)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_dns_name("tenant", &self.tenant)?;
+        validate_dns_name("project", &self.project)?;
+        validate_dns_name("from environment", &self.from)?;
+        validate_dns_name("to environment", &self.to)?;
+        if self.from == self.to {
+            return Err("from and to environments must be different".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogsQuery {
+    #[serde(default)]
+    pub offset: u64,
+    #[serde(default)]
+    pub follow: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddAppQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+impl HistoryQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(tenant) = &self.tenant {
+            validate_dns_name("tenant", tenant)?;
+        }
+        if let Some(project) = &self.project {
+            validate_dns_name("project", project)?;
+        }
+        if let Some(environment) = &self.environment {
+            validate_dns_name("environment", environment)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentHistoryEntry {
+    pub tenant: String,
+    pub project: String,
+    pub environment: String,
+    pub commit_sha: String,
+    pub author: String,
+    pub timestamp: String,
+    pub image: String,
+    #[serde(default)]
+    pub change: DeploymentChange,
+    pub source_repo: Option<String>,
+    pub source_revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeploymentChange {
+    #[default]
+    Update,
+    Rollback,
+    Create,
+    Add,
+    Other,
+}
+
+impl DeploymentChange {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Update => "update",
+            Self::Rollback => "rollback",
+            Self::Create => "create",
+            Self::Add => "add",
+            Self::Other => "other",
+        }
+    }
+}
+
 impl CreateAppRequest {
     pub fn app_path(&self) -> String {
         format!("{}/{}/{}", self.tenant, self.project, self.environment)
@@ -96,21 +419,53 @@ impl CreateAppRequest {
             || self.http_route.is_some()
             || !self.config.is_empty()
             || !self.secrets.is_empty()
+            || !self.sealed_secrets.is_empty()
             || !self.volumes.is_empty()
             || self.postgres.is_some()
+            || self.autoscale.is_some()
     }
 
-    pub fn validate(&self) -> Result<(), String> {
-        validate_dns_name("tenant", &self.tenant)?;
-        validate_dns_name("project", &self.project)?;
-        validate_dns_name("environment", &self.environment)?;
+    pub fn validate_create(&self) -> Result<(), String> {
+        self.validate()?;
+        validate_new_environment(&self.environment)?;
         if let Some(deployment) = &self.deployment
             && deployment.image.is_none()
             && deployment.source_repo.is_none()
         {
             return Err("deployment needs either an image or a source repo".to_string());
         }
-        for item in self.config.iter().chain(self.secrets.iter()) {
+        if let Some(deployment) = &self.deployment
+            && deployment.port.is_none()
+            && deployment.has_http_probe()
+        {
+            return Err("HTTP probes need a deployment port".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_dns_name("tenant", &self.tenant)?;
+        validate_dns_name("project", &self.project)?;
+        validate_dns_name("environment", &self.environment)?;
+        if let Some(deployment) = &self.deployment {
+            deployment.validate()?;
+        }
+        if let Some(autoscale) = &self.autoscale {
+            autoscale.validate()?;
+            if self
+                .deployment
+                .as_ref()
+                .is_some_and(|deployment| deployment.replicas.is_some())
+            {
+                return Err("set either replicas or autoscale, not both".to_string());
+            }
+        }
+        for item in self
+            .config
+            .iter()
+            .chain(&self.secrets)
+            .chain(&self.sealed_secrets)
+        {
             validate_env_key(&item.key)?;
         }
         for volume in &self.volumes {
@@ -135,6 +490,13 @@ impl CreateAppRequest {
     }
 }
 
+pub fn validate_new_environment(environment: &str) -> Result<(), String> {
+    if RESERVED_ENVIRONMENTS.contains(&environment) {
+        return Err(format!("environment {environment:?} is reserved"));
+    }
+    Ok(())
+}
+
 fn validate_dns_name(field: &str, value: &str) -> Result<(), String> {
     if value.is_empty() {
         return Err(format!("{field} is required"));
@@ -164,6 +526,21 @@ fn validate_dns_name(field: &str, value: &str) -> Result<(), String> {
     Ok(())
 }
 
+fn validate_quantity(field: &str, value: &str) -> Result<(), String> {
+    let unit = value.trim_start_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+    if unit.len() == value.len()
+        || !matches!(
+            unit,
+            "" | "m" | "k" | "M" | "G" | "T" | "Ki" | "Mi" | "Gi" | "Ti"
+        )
+    {
+        return Err(format!(
+            "{field} {value:?} must be a Kubernetes quantity such as 250m or 512Mi"
+        ));
+    }
+    Ok(())
+}
+
 fn validate_env_key(value: &str) -> Result<(), String> {
     if value.is_empty() {
         return Err("config and secret keys cannot be empty".to_string());
@@ -183,8 +560,187 @@ fn validate_env_key(value: &str) -> Result<(), String> {
 pub struct CreateDeployment {
     pub image: Option<String>,
     pub source_repo: Option<String>,
-    pub replicas: u32,
+    #[serde(default)]
+    pub replicas: Option<u32>,
     pub port: Option<u16>,
+    #[serde(default)]
+    pub resources: Option<ContainerResources>,
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<KeyValue>,
+    #[serde(default)]
+    pub probes: DeploymentProbes,
+    #[serde(default)]
+    pub security_context: Option<CreateSecurityContext>,
+}
+
+impl CreateDeployment {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(resources) = &self.resources {
+            resources.validate()?;
+        }
+        if self.command.iter().any(|part| part.is_empty()) {
+            return Err("deployment command cannot contain empty arguments".to_string());
+        }
+        for (index, item) in self.env.iter().enumerate() {
+            validate_env_key(&item.key)?;
+            if self.env[..index].iter().any(|other| other.key == item.key) {
+                return Err(format!("env {} is set more than once", item.key));
+            }
+        }
+        for (name, probe) in self.probes.iter() {
+            probe.validate(name)?;
+        }
+        if let Some(security_context) = &self.security_context
+            && security_context.run_as_non_root
+            && security_context.run_as_user == Some(0)
+        {
+            return Err("run as non-root cannot be combined with user 0".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn has_http_probe(&self) -> bool {
+        self.probes
+            .iter()
+            .any(|(_, probe)| probe.http_path.is_some())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeploymentProbes {
+    #[serde(default)]
+    pub liveness: Option<CreateProbe>,
+    #[serde(default)]
+    pub readiness: Option<CreateProbe>,
+    #[serde(default)]
+    pub startup: Option<CreateProbe>,
+}
+
+impl DeploymentProbes {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &CreateProbe)> {
+        [
+            ("liveness", &self.liveness),
+            ("readiness", &self.readiness),
+            ("startup", &self.startup),
+        ]
+        .into_iter()
+        .filter_map(|(name, probe)| probe.as_ref().map(|probe| (name, probe)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateProbe {
+    #[serde(default)]
+    pub http_path: Option<String>,
+    #[serde(default)]
+    pub tcp_port: Option<u16>,
+    #[serde(default)]
+    pub exec: Vec<String>,
+    #[serde(default)]
+    pub initial_delay_seconds: Option<u32>,
+    #[serde(default)]
+    pub period_seconds: Option<u32>,
+    #[serde(default)]
+    pub failure_threshold: Option<u32>,
+}
+
+impl CreateProbe {
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        let handlers = usize::from(self.http_path.is_some())
+            + usize::from(self.tcp_port.is_some())
+            + usize::from(!self.exec.is_empty());
+        if handlers != 1 {
+            return Err(format!(
+                "{name} probe needs exactly one of an HTTP path, a TCP port or a command"
+            ));
+        }
+        if let Some(path) = &self.http_path
+            && !path.starts_with('/')
+        {
+            return Err(format!("{name} probe path must start with '/'"));
+        }
+        if self.tcp_port == Some(0) {
+            return Err(format!("{name} probe port must be greater than 0"));
+        }
+        if self.period_seconds == Some(0) || self.failure_threshold == Some(0) {
+            return Err(format!(
+                "{name} probe period and failure threshold must be greater than 0"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSecurityContext {
+    #[serde(default)]
+    pub run_as_user: Option<u64>,
+    #[serde(default)]
+    pub run_as_group: Option<u64>,
+    #[serde(default)]
+    pub run_as_non_root: bool,
+    #[serde(default)]
+    pub read_only_root_filesystem: bool,
+    #[serde(default)]
+    pub allow_privilege_escalation: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContainerResources {
+    #[serde(default)]
+    pub cpu_request: Option<String>,
+    #[serde(default)]
+    pub cpu_limit: Option<String>,
+    #[serde(default)]
+    pub memory_request: Option<String>,
+    #[serde(default)]
+    pub memory_limit: Option<String>,
+}
+
+impl ContainerResources {
+    pub fn validate(&self) -> Result<(), String> {
+        let quantities = [
+            ("cpu request", &self.cpu_request),
+            ("cpu limit", &self.cpu_limit),
+            ("memory request", &self.memory_request),
+            ("memory limit", &self.memory_limit),
+        ];
+        if quantities.iter().all(|(_, value)| value.is_none()) {
+            return Err("resources need at least a cpu or memory value".to_string());
+        }
+        for (field, value) in quantities {
+            if let Some(value) = value {
+                validate_quantity(field, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAutoscale {
+    pub min_replicas: u32,
+    pub max_replicas: u32,
+    pub cpu_utilization: u32,
+}
+
+impl CreateAutoscale {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_replicas == 0 {
+            return Err("autoscale min replicas must be at least 1".to_string());
+        }
+        if self.max_replicas < self.min_replicas {
+            return Err("autoscale max replicas must be at least min replicas".to_string());
+        }
+        if self.cpu_utilization == 0 {
+            return Err("autoscale cpu utilization must be greater than 0".to_string());
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -231,6 +787,14 @@ pub fn deploy_workflow_id(repo_name: &str, revision: &str) -> String {
     )
 }
 
+pub fn preview_workflow_id(repo_name: &str, number: u64, revision: &str) -> String {
+    format!(
+        "preview-{}-pr-{number}-{}",
+        sanitize_workflow_part(repo_name),
+        revision.chars().take(12).collect::<String>()
+    )
+}
+
 fn sanitize_workflow_part(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     for ch in input.chars() {
@@ -258,29 +822,184 @@ mod tests {
             http_route: None,
             config: Vec::new(),
             secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
             volumes: Vec::new(),
             postgres: None,
+            autoscale: None,
         }
     }
 
+    #[test]
+    fn remove_components_request_validates_component_names() {
+        let mut request = RemoveComponentsRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            components: vec![
+                AppComponent {
+                    kind: "configmap".parse().unwrap(),
+                    name: "LOG_LEVEL".to_string(),
+                },
+                AppComponent {
+                    kind: "pvc".parse().unwrap(),
+                    name: "data".to_string(),
+                },
+            ],
+        };
+        request.validate().unwrap();
+        assert_eq!(request.components[0].to_string(), "config/LOG_LEVEL");
+
+        request.components[1].name = "Data".to_string();
+        assert!(request.validate().is_err());
+        assert!("ingress".parse::<ComponentKind>().is_err());
+    }
+
     #[test]
     fn create_app_request_accepts_empty_resource_set() {
         empty_request().validate().unwrap();
     }
 
+    #[test]
+    fn create_app_request_reserves_promote_only_on_create() {
+        let mut request = empty_request();
+        request.environment = "promote".to_string();
+        request.validate().unwrap();
+        assert!(request.validate_create().is_err());
+
+        let delete = DeleteAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "promote".to_string(),
+        };
+        delete.validate().unwrap();
+    }
+
     #[test]
     fn create_app_request_accepts_source_deployment() {
         let mut request = empty_request();
         request.deployment = Some(CreateDeployment {
             image: None,
             source_repo: Some("khuedoan/example-service".to_string()),
-            replicas: 1,
+            replicas: Some(1),
             port: Some(3000),
+            resources: None,
+            command: Vec::new(),
+            args: Vec::new(),
+            env: Vec::new(),
+            probes: DeploymentProbes::default(),
+            security_context: None,
         });
 
         request.validate().unwrap();
     }
 
+    #[test]
+    fn create_app_request_validates_scaling() {
+        let mut request = empty_request();
+        request.deployment = Some(CreateDeployment {
+            image: None,
+            source_repo: None,
+            replicas: None,
+            port: None,
+            resources: Some(ContainerResources {
+                cpu_request: Some("250m".to_string()),
+                memory_limit: Some("512Mi".to_string()),
+                ..ContainerResources::default()
+            }),
+            command: Vec::new(),
+            args: Vec::new(),
+            env: Vec::new(),
+            probes: DeploymentProbes::default(),
+            security_context: None,
+        });
+        request.autoscale = Some(CreateAutoscale {
+            min_replicas: 2,
+            max_replicas: 5,
+            cpu_utilization: 80,
+        });
+        request.validate().unwrap();
+        assert!(request.validate_create().is_err());
+
+        request.deployment.as_mut().unwrap().replicas = Some(3);
+        assert!(request.validate().is_err());
+
+        request.deployment.as_mut().unwrap().replicas = None;
+        request.deployment.as_mut().unwrap().resources = Some(ContainerResources {
+            memory_request: Some("lots".to_string()),
+            ..ContainerResources::default()
+        });
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn create_app_request_validates_container_settings() {
+        let mut request = empty_request();
+        request.deployment = Some(CreateDeployment {
+            image: Some("nginx:1.27".to_string()),
+            source_repo: None,
+            replicas: None,
+            port: Some(80),
+            resources: None,
+            command: vec!["nginx".to_string()],
+            args: vec!["-g".to_string(), "daemon off;".to_string()],
+            env: vec![KeyValue {
+                key: "LOG_LEVEL".to_string(),
+                value: "info".to_string(),
+            }],
+            probes: DeploymentProbes {
+                liveness: Some(CreateProbe {
+                    http_path: Some("/healthz".to_string()),
+                    ..CreateProbe::default()
+                }),
+                readiness: Some(CreateProbe {
+                    tcp_port: Some(80),
+                    ..CreateProbe::default()
+                }),
+                startup: None,
+            },
+            security_context: Some(CreateSecurityContext {
+                run_as_user: Some(1000),
+                run_as_non_root: true,
+                ..CreateSecurityContext::default()
+            }),
+        });
+        request.validate().unwrap();
+
+        let deployment = request.deployment.as_mut().unwrap();
+        deployment.security_context.as_mut().unwrap().run_as_user = Some(0);
+        assert!(request.validate().is_err());
+
+        let deployment = request.deployment.as_mut().unwrap();
+        deployment.security_context = None;
+        deployment.probes.startup = Some(CreateProbe {
+            http_path: Some("/ready".to_string()),
+            tcp_port: Some(80),
+            ..CreateProbe::default()
+        });
+        assert!(request.validate().is_err());
+
+        let deployment = request.deployment.as_mut().unwrap();
+        deployment.probes.startup = Some(CreateProbe {
+            http_path: Some("ready".to_string()),
+            ..CreateProbe::default()
+        });
+        assert!(request.validate().is_err());
+
+        let deployment = request.deployment.as_mut().unwrap();
+        deployment.probes.startup = None;
+        deployment.port = None;
+        request.validate().unwrap();
+        assert!(request.validate_create().is_err());
+
+        let deployment = request.deployment.as_mut().unwrap();
+        deployment.port = Some(80);
+        deployment.env.push(KeyValue {
+            key: "LOG_LEVEL".to_string(),
+            value: "debug".to_string(),
+        });
+        assert!(request.validate().is_err());
+    }
+
     #[test]
     fn delete_app_request_validates_app_path() {
         let request = DeleteAppRequest {
@@ -293,6 +1012,62 @@ mod tests {
         assert_eq!(request.app_path(), "test/example/production");
     }
 
+    #[test]
+    fn update_secrets_request_validates_keys() {
+        let mut request = UpdateSecretsRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            set: vec![KeyValue {
+                key: "API_TOKEN".to_string(),
+                value: "sealed".to_string(),
+            }],
+            unset: vec!["OLD_TOKEN".to_string()],
+        };
+        request.validate().unwrap();
+
+        request.unset.push("API_TOKEN".to_string());
+        assert!(request.validate().is_err());
+
+        request.set.clear();
+        request.unset = vec!["lowercase".to_string()];
+        assert!(request.validate().is_err());
+
+        request.unset.clear();
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn promote_app_request_rejects_same_environment() {
+        let mut request = PromoteAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            from: "staging".to_string(),
+            to: "production".to_string(),
+        };
+        request.validate().unwrap();
+
+        request.to = "staging".to_string();
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn approval_status_uses_lowercase_states() {
+        let status: ApprovalStatus = serde_json::from_value(serde_json::json!({
+            "state": "pending",
+            "environment": "production",
+            "approver": null,
+        }))
+        .unwrap();
+        assert!(status.is_pending());
+
+        let status = ApprovalStatus {
+            state: ApprovalState::Approved,
+            ..status
+        };
+        assert_eq!(serde_json::to_value(&status).unwrap()["state"], "approved");
+    }
+
     #[test]
     fn deploy_workflow_id_matches_push_convention() {
         assert_eq!(
@@ -300,4 +1075,12 @@ mod tests {
             "push-to-deploy-example-service-6c1c137dc62d"
         );
     }
+
+    #[test]
+    fn preview_workflow_id_includes_pull_request_number() {
+        assert_eq!(
+            preview_workflow_id("example-service", 42, "6c1c137dc62d1234567890"),
+            "preview-example-service-pr-42-6c1c137dc62d"
+        );
+    }
 }
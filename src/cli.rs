@@ -1,21 +1,24 @@
 use std::{
     collections::{BTreeMap, BTreeSet},
     fs,
-    io::{self, IsTerminal},
+    io::{self, IsTerminal, Write},
     path::PathBuf,
     process::Command as ProcessCommand,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crate::api::{
-    AuthConfig, CreateAppRequest, CreateDeployment, CreateHttpRoute, CreatePostgres, CreateService,
-    CreateVolume, DeleteAppRequest, DeployRequest, KeyValue, ProjectSummary, UserInfo,
+    AppComponent, AuthConfig, ComponentKind, ContainerResources, CreateAppRequest, CreateAutoscale,
+    CreateDeployment, CreateHttpRoute, CreatePostgres, CreateProbe, CreateSecurityContext,
+    CreateService, CreateVolume, DeleteAppRequest, DeployRequest, DeploymentHistoryEntry,
+    DeploymentProbes, HistoryQuery, ImageRevision, KeyValue, LogsQuery, ProjectSummary,
+    PromoteAppRequest, RemoveComponentsRequest, RollbackAppRequest, UpdateSecretsRequest, UserInfo,
     WorkflowStarted, WorkflowStatus, deploy_workflow_id,
 };
 use anyhow::{Context, Result, anyhow, bail};
 use clap::{Args, Parser, Subcommand};
 use comfy_table::{Attribute, Cell, ContentArrangement, Table, presets::NOTHING};
-use inquire::{CustomType, MultiSelect, Select, Text};
+use inquire::{Confirm, CustomType, MultiSelect, Password, Select, Text};
 use openidconnect::{
     AdditionalProviderMetadata, AuthType, ClientId, DeviceAuthorizationUrl, IssuerUrl, Nonce,
     OAuth2TokenResponse, ProviderMetadata, RefreshToken, Scope, TokenResponse as OidcTokenResponse,
@@ -27,7 +30,7 @@ use openidconnect::{
     },
     reqwest as oidc_reqwest,
 };
-use reqwest::{Client, Method};
+use reqwest::{Client, Method, StatusCode};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use tokio::time::sleep;
 
@@ -47,11 +50,21 @@ enum Commands {
     Logout,
     Whoami,
     List(ListArgs),
+    History(HistoryArgs),
     Create(CreateArgs),
     Delete(DeleteArgs),
     Add(AddArgs),
+    Remove(RemoveArgs),
+    Scale(ScaleArgs),
+    Resources(ResourcesArgs),
     Deploy(DeployArgs),
+    Approve(ApproveArgs),
+    Promote(PromoteArgs),
+    Rollback(RollbackArgs),
+    #[command(subcommand)]
+    Secret(SecretCommand),
     Status(StatusArgs),
+    Logs(LogsArgs),
     Open(OpenArgs),
 }
 
@@ -61,6 +74,18 @@ struct ListArgs {
     json: bool,
 }
 
+#[derive(Args)]
+struct HistoryArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+    #[arg(long)]
+    json: bool,
+}
+
 #[derive(Args)]
 struct CreateArgs {
     #[arg(long)]
@@ -75,8 +100,8 @@ struct CreateArgs {
     image: Option<String>,
     #[arg(long)]
     source_repo: Option<String>,
-    #[arg(long, default_value_t = 1)]
-    replicas: u32,
+    #[arg(long)]
+    replicas: Option<u32>,
     #[arg(long)]
     port: Option<u16>,
     #[arg(long)]
@@ -95,10 +120,78 @@ struct CreateArgs {
     postgres: bool,
     #[arg(long, default_value = "1Gi")]
     postgres_size: String,
+    #[command(flatten)]
+    container: ContainerArgs,
     #[arg(long = "watch", hide = true)]
     watch: bool,
 }
 
+#[derive(Args)]
+struct ContainerArgs {
+    #[arg(long, value_name = "COMMAND", help = "Override the image entrypoint")]
+    command: Option<String>,
+    #[arg(long = "arg", allow_hyphen_values = true)]
+    args: Vec<String>,
+    #[arg(long = "env", value_name = "KEY=VALUE")]
+    env: Vec<String>,
+    #[arg(
+        long,
+        value_name = "PROBE",
+        help = "http:/path, tcp:PORT or exec:COMMAND"
+    )]
+    liveness_probe: Option<String>,
+    #[arg(
+        long,
+        value_name = "PROBE",
+        help = "http:/path, tcp:PORT or exec:COMMAND"
+    )]
+    readiness_probe: Option<String>,
+    #[arg(
+        long,
+        value_name = "PROBE",
+        help = "http:/path, tcp:PORT or exec:COMMAND"
+    )]
+    startup_probe: Option<String>,
+    #[arg(long)]
+    run_as_user: Option<u64>,
+    #[arg(long)]
+    run_as_non_root: bool,
+    #[arg(long)]
+    read_only_root_filesystem: bool,
+}
+
+impl ContainerArgs {
+    fn is_empty(&self) -> bool {
+        self.command.is_none()
+            && self.args.is_empty()
+            && self.env.is_empty()
+            && self.liveness_probe.is_none()
+            && self.readiness_probe.is_none()
+            && self.startup_probe.is_none()
+            && self.security_context().is_none()
+    }
+
+    fn probes(&self) -> Result<DeploymentProbes> {
+        let parse = |value: &Option<String>| value.as_deref().map(parse_probe).transpose();
+        Ok(DeploymentProbes {
+            liveness: parse(&self.liveness_probe)?,
+            readiness: parse(&self.readiness_probe)?,
+            startup: parse(&self.startup_probe)?,
+        })
+    }
+
+    fn security_context(&self) -> Option<CreateSecurityContext> {
+        (self.run_as_user.is_some() || self.run_as_non_root || self.read_only_root_filesystem).then(
+            || CreateSecurityContext {
+                run_as_user: self.run_as_user,
+                run_as_non_root: self.run_as_non_root,
+                read_only_root_filesystem: self.read_only_root_filesystem,
+                ..CreateSecurityContext::default()
+            },
+        )
+    }
+}
+
 #[derive(Args)]
 struct DeleteArgs {
     #[arg(long)]
@@ -123,8 +216,8 @@ struct AddArgs {
     image: Option<String>,
     #[arg(long)]
     source_repo: Option<String>,
-    #[arg(long, default_value_t = 1)]
-    replicas: u32,
+    #[arg(long)]
+    replicas: Option<u32>,
     #[arg(long)]
     port: Option<u16>,
     #[arg(long)]
@@ -143,6 +236,75 @@ struct AddArgs {
     postgres: bool,
     #[arg(long, default_value = "1Gi")]
     postgres_size: String,
+    #[arg(long, help = "Apply without confirming the planned diff")]
+    yes: bool,
+    #[command(flatten)]
+    container: ContainerArgs,
+}
+
+#[derive(Args)]
+struct RemoveArgs {
+    #[arg(help = "deployment, service, httproute, config, secret, volume, postgres or autoscaler")]
+    kind: ComponentKind,
+    #[arg(help = "Resource name, or the key for config and secret")]
+    name: String,
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Args)]
+struct ScaleArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+    #[arg(
+        long,
+        required_unless_present = "autoscale",
+        conflicts_with = "autoscale"
+    )]
+    replicas: Option<u32>,
+    #[arg(
+        long,
+        value_name = "MIN:MAX:CPU%",
+        help = "Add or update a HorizontalPodAutoscaler, e.g. 2:10:80%"
+    )]
+    autoscale: Option<String>,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Args)]
+struct ResourcesArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+    #[arg(
+        long,
+        value_name = "REQUEST[:LIMIT]",
+        required_unless_present = "memory",
+        help = "CPU request and optional limit, e.g. 250m:1"
+    )]
+    cpu: Option<String>,
+    #[arg(
+        long,
+        value_name = "REQUEST[:LIMIT]",
+        help = "Memory request and optional limit, e.g. 256Mi:512Mi"
+    )]
+    memory: Option<String>,
+    #[arg(long)]
+    watch: bool,
 }
 
 #[derive(Args)]
@@ -157,6 +319,95 @@ struct DeployArgs {
     watch: bool,
 }
 
+#[derive(Args)]
+struct ApproveArgs {
+    workflow_id: String,
+    #[arg(long, help = "Reject the deployment instead of approving it")]
+    reject: bool,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Args)]
+struct PromoteArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    from: Option<String>,
+    #[arg(long)]
+    to: Option<String>,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Args)]
+struct RollbackArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+    #[arg(
+        long,
+        help = "Previously deployed image or tag; defaults to the previous one"
+    )]
+    image: Option<String>,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Subcommand)]
+enum SecretCommand {
+    Set(SecretSetArgs),
+    Unset(SecretUnsetArgs),
+    List(SecretListArgs),
+}
+
+#[derive(Args)]
+struct SecretTargetArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+}
+
+#[derive(Args)]
+struct SecretSetArgs {
+    #[command(flatten)]
+    target: SecretTargetArgs,
+    #[arg(
+        required = true,
+        value_name = "KEY[=VALUE]",
+        help = "Secret to set; a bare KEY prompts for the value"
+    )]
+    values: Vec<String>,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Args)]
+struct SecretUnsetArgs {
+    #[command(flatten)]
+    target: SecretTargetArgs,
+    #[arg(required = true, value_name = "KEY")]
+    keys: Vec<String>,
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(Args)]
+struct SecretListArgs {
+    #[command(flatten)]
+    target: SecretTargetArgs,
+    #[arg(long)]
+    json: bool,
+}
+
 #[derive(Args)]
 struct StatusArgs {
     #[arg(
@@ -169,6 +420,13 @@ struct StatusArgs {
     watch: bool,
 }
 
+#[derive(Args)]
+struct LogsArgs {
+    workflow_id: String,
+    #[arg(long, help = "Stream new output until the workflow finishes")]
+    follow: bool,
+}
+
 #[derive(Args)]
 struct OpenArgs {
     workflow_id: String,
@@ -239,6 +497,23 @@ pub async fn run() -> Result<()> {
             }
             Ok(())
         }
+        Commands::History(args) => {
+            let query = HistoryQuery {
+                tenant: args.tenant,
+                project: args.project,
+                environment: args.environment,
+            };
+            query.validate().map_err(anyhow::Error::msg)?;
+            let api = ApiSession::load(&http, cli.server).await?;
+            let history: Vec<DeploymentHistoryEntry> =
+                api.get_with_query("/api/v1/history", &query).await?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&history)?);
+            } else {
+                print_history(&history);
+            }
+            Ok(())
+        }
         Commands::Create(args) => {
             let (api, projects) = if create_needs_inventory(&args) {
                 let api = ApiSession::load(&http, cli.server.clone()).await?;
@@ -279,6 +554,7 @@ pub async fn run() -> Result<()> {
             } else {
                 (None, Vec::new())
             };
+            let yes = args.yes;
             let request = add_request(args, &projects)?;
             let api = match api {
                 Some(api) => api,
@@ -288,9 +564,101 @@ pub async fn run() -> Result<()> {
                 "/api/v1/apps/{}/{}/{}",
                 request.tenant, request.project, request.environment
             );
+            let planned: WorkflowStarted =
+                api.patch(&format!("{path}?dry_run=true"), &request).await?;
+            api.watch_workflow(&planned.workflow_id).await?;
+            if !api.print_workflow_diff(&planned.workflow_id).await? {
+                println!("no changes");
+                return Ok(());
+            }
+            if !yes
+                && !Confirm::new("Apply these changes?")
+                    .with_default(false)
+                    .prompt()?
+            {
+                return Ok(());
+            }
+
             let started: WorkflowStarted = api.patch(&path, &request).await?;
             println!("{}", started.workflow_id);
-            api.watch_workflow(&started.workflow_id).await?;
+            api.watch_workflow(&started.workflow_id).await
+        }
+        Commands::Scale(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let target = (args.tenant, args.project, args.environment);
+            let projects = if deployment_needs_inventory(&target) {
+                api.get("/api/v1/projects").await?
+            } else {
+                Vec::new()
+            };
+            let autoscale = args.autoscale.as_deref().map(parse_autoscale).transpose()?;
+            let deployment = args.replicas.map(|replicas| CreateDeployment {
+                image: None,
+                source_repo: None,
+                replicas: Some(replicas),
+                port: None,
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            });
+            let request = deployment_request(target, &projects, deployment, autoscale)?;
+            api.patch_deployment(&request, args.watch).await
+        }
+        Commands::Resources(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let target = (args.tenant, args.project, args.environment);
+            let projects = if deployment_needs_inventory(&target) {
+                api.get("/api/v1/projects").await?
+            } else {
+                Vec::new()
+            };
+            let (cpu_request, cpu_limit) = parse_quantity_pair(args.cpu.as_deref());
+            let (memory_request, memory_limit) = parse_quantity_pair(args.memory.as_deref());
+            let deployment = CreateDeployment {
+                image: None,
+                source_repo: None,
+                replicas: None,
+                port: None,
+                resources: Some(ContainerResources {
+                    cpu_request,
+                    cpu_limit,
+                    memory_request,
+                    memory_limit,
+                }),
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            };
+            let request = deployment_request(target, &projects, Some(deployment), None)?;
+            api.patch_deployment(&request, args.watch).await
+        }
+        Commands::Remove(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let projects = if remove_needs_inventory(&args) {
+                api.get("/api/v1/projects").await?
+            } else {
+                Vec::new()
+            };
+            let watch = args.watch;
+            let request = remove_request(args, &projects)?;
+            let component = &request.components[0];
+            let path = format!(
+                "/api/v1/apps/{}/components/{}/{}",
+                request.app_path(),
+                component.kind,
+                component.name
+            );
+            let started: WorkflowStarted = api.delete(&path).await?;
+            println!("{}", started.workflow_id);
+            if watch {
+                api.watch_workflow(&started.workflow_id).await?;
+                api.print_workflow_diff(&started.workflow_id).await?;
+            }
             Ok(())
         }
         Commands::Deploy(args) => {
@@ -304,6 +672,114 @@ pub async fn run() -> Result<()> {
             }
             Ok(())
         }
+        Commands::Approve(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let action = if args.reject { "reject" } else { "approve" };
+            let status: WorkflowStatus = api
+                .post(
+                    &format!("/api/v1/workflows/{}/{action}", args.workflow_id),
+                    &(),
+                )
+                .await?;
+            print_workflow_status(&status);
+            if args.watch && !args.reject {
+                api.watch_workflow(&args.workflow_id).await?;
+            }
+            Ok(())
+        }
+        Commands::Promote(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let projects = if promote_needs_inventory(&args) {
+                api.get("/api/v1/projects").await?
+            } else {
+                Vec::new()
+            };
+            let watch = args.watch;
+            let request = promote_request(args, &projects)?;
+            let path = format!(
+                "/api/v1/apps/{}/{}/promote",
+                request.tenant, request.project
+            );
+            let started: WorkflowStarted = api.post(&path, &request).await?;
+            println!("{}", started.workflow_id);
+            if watch {
+                api.watch_workflow(&started.workflow_id).await?;
+            }
+            Ok(())
+        }
+        Commands::Rollback(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let projects = if rollback_needs_inventory(&args) {
+                api.get("/api/v1/projects").await?
+            } else {
+                Vec::new()
+            };
+            let watch = args.watch;
+            let mut request = rollback_request(args, &projects)?;
+            let path = format!(
+                "/api/v1/apps/{}/{}/{}/rollback",
+                request.tenant, request.project, request.environment
+            );
+            if request.image.is_none() && io::stdin().is_terminal() {
+                let revisions: Vec<ImageRevision> = api.get(&path).await?;
+                request.image = Some(prompt_rollback_image(&revisions)?);
+            }
+            let started: WorkflowStarted = api.post(&path, &request).await?;
+            println!("{}", started.workflow_id);
+            if watch {
+                api.watch_workflow(&started.workflow_id).await?;
+            }
+            Ok(())
+        }
+        Commands::Secret(command) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let target = match &command {
+                SecretCommand::Set(args) => &args.target,
+                SecretCommand::Unset(args) => &args.target,
+                SecretCommand::List(args) => &args.target,
+            };
+            let projects = if secret_needs_inventory(target) {
+                api.get("/api/v1/projects").await?
+            } else {
+                Vec::new()
+            };
+            match command {
+                SecretCommand::List(args) => {
+                    let request = secret_request(args.target, &projects, Vec::new(), Vec::new())?;
+                    let path = format!("/api/v1/apps/{}/secrets", request.app_path());
+                    let keys: Vec<String> = api.get(&path).await?;
+                    if args.json {
+                        println!("{}", serde_json::to_string_pretty(&keys)?);
+                    } else {
+                        for key in keys {
+                            println!("{key}");
+                        }
+                    }
+                    Ok(())
+                }
+                SecretCommand::Set(args) => {
+                    let set = parse_secret_values(args.values)?;
+                    let request = secret_request(args.target, &projects, set, Vec::new())?;
+                    let path = format!("/api/v1/apps/{}/secrets", request.app_path());
+                    let started: WorkflowStarted = api.patch(&path, &request).await?;
+                    println!("{}", started.workflow_id);
+                    if args.watch {
+                        api.watch_workflow(&started.workflow_id).await?;
+                    }
+                    Ok(())
+                }
+                SecretCommand::Unset(args) => {
+                    let request = secret_request(args.target, &projects, Vec::new(), args.keys)?;
+                    let path = format!("/api/v1/apps/{}/secrets", request.app_path());
+                    let started: WorkflowStarted = api.patch(&path, &request).await?;
+                    println!("{}", started.workflow_id);
+                    if args.watch {
+                        api.watch_workflow(&started.workflow_id).await?;
+                    }
+                    Ok(())
+                }
+            }
+        }
         Commands::Status(args) => {
             let commit = git_commit(args.commit.as_deref())?;
             let repo = repo_from_git_remote()?;
@@ -318,6 +794,23 @@ pub async fn run() -> Result<()> {
                 Ok(())
             }
         }
+        Commands::Logs(args) => {
+            let api = ApiSession::load(&http, cli.server).await?;
+            let query = LogsQuery {
+                offset: 0,
+                follow: args.follow,
+            };
+            let mut response = api.logs(&args.workflow_id, &query).await?;
+            if !response.status().is_success() {
+                return Err(api_error(response).await);
+            }
+            let mut stdout = io::stdout();
+            while let Some(chunk) = response.chunk().await? {
+                stdout.write_all(&chunk)?;
+                stdout.flush()?;
+            }
+            Ok(())
+        }
         Commands::Open(args) => {
             let api = ApiSession::load(&http, cli.server).await?;
             let status = api.workflow_status(&args.workflow_id).await?;
@@ -351,6 +844,19 @@ impl<'a> ApiSession<'a> {
         self.request(Method::GET, path, Option::<&()>::None).await
     }
 
+    async fn get_with_query<T, Q>(&self, path: &str, query: &Q) -> Result<T>
+    where
+        T: DeserializeOwned,
+        Q: Serialize,
+    {
+        let request = self
+            .http
+            .get(format!("{}{}", self.server, path))
+            .bearer_auth(&self.token)
+            .query(query);
+        decode_api_response(request.send().await?).await
+    }
+
     async fn post<T, B>(&self, path: &str, body: &B) -> Result<T>
     where
         T: DeserializeOwned,
@@ -391,9 +897,45 @@ impl<'a> ApiSession<'a> {
         self.get(&format!("/api/v1/workflows/{workflow_id}")).await
     }
 
+    async fn logs(&self, workflow_id: &str, query: &LogsQuery) -> Result<reqwest::Response> {
+        let response = self
+            .http
+            .get(format!(
+                "{}/api/v1/workflows/{workflow_id}/logs",
+                self.server
+            ))
+            .bearer_auth(&self.token)
+            .query(query)
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    async fn print_new_logs(&self, workflow_id: &str, offset: &mut u64) -> Result<()> {
+        let query = LogsQuery {
+            offset: *offset,
+            follow: false,
+        };
+        let response = self.logs(workflow_id, &query).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        let bytes = response.bytes().await?;
+        *offset += bytes.len() as u64;
+        let mut stdout = io::stdout();
+        stdout.write_all(&bytes)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
     async fn watch_workflow(&self, workflow_id: &str) -> Result<()> {
+        let mut log_offset = 0;
         loop {
             let status = self.workflow_status(workflow_id).await?;
+            self.print_new_logs(workflow_id, &mut log_offset).await?;
             print_workflow_status(&status);
             if status.is_terminal() {
                 if status.status == "completed" {
@@ -405,9 +947,36 @@ impl<'a> ApiSession<'a> {
         }
     }
 
+    async fn patch_deployment(&self, request: &CreateAppRequest, watch: bool) -> Result<()> {
+        let path = format!("/api/v1/apps/{}", request.app_path());
+        let started: WorkflowStarted = self.patch(&path, request).await?;
+        println!("{}", started.workflow_id);
+        if watch {
+            self.watch_workflow(&started.workflow_id).await?;
+            self.print_workflow_diff(&started.workflow_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn print_workflow_diff(&self, workflow_id: &str) -> Result<bool> {
+        let status = self.workflow_status(workflow_id).await?;
+        let Some(diff) = status
+            .result
+            .as_ref()
+            .and_then(|result| result["diff"].as_str())
+            .filter(|diff| !diff.is_empty())
+        else {
+            return Ok(false);
+        };
+        print!("{diff}");
+        Ok(true)
+    }
+
     async fn watch_commit_workflow(&self, commit: &str, workflow_id: &str) -> Result<()> {
+        let mut log_offset = 0;
         loop {
             let status = self.workflow_status(workflow_id).await?;
+            self.print_new_logs(workflow_id, &mut log_offset).await?;
             print_commit_status(commit, &status);
             if status.is_terminal() {
                 if status.status == "completed" {
@@ -549,12 +1118,17 @@ async fn decode_api_response<T: DeserializeOwned>(response: reqwest::Response) -
         return Ok(response.json().await?);
     }
 
+    Err(api_error(response).await)
+}
+
+async fn api_error(response: reqwest::Response) -> anyhow::Error {
+    let status = response.status();
     let body = response
         .json::<ApiErrorBody>()
         .await
         .map(|body| body.error)
         .unwrap_or_else(|_| status.to_string());
-    Err(anyhow!("request failed: {body}"))
+    anyhow!("request failed: {body}")
 }
 
 fn create_needs_inventory(args: &CreateArgs) -> bool {
@@ -566,13 +1140,44 @@ fn add_needs_inventory(args: &AddArgs) -> bool {
         && (args.tenant.is_none() || args.project.is_none() || args.environment.is_none())
 }
 
+fn promote_needs_inventory(args: &PromoteArgs) -> bool {
+    io::stdin().is_terminal()
+        && (args.tenant.is_none()
+            || args.project.is_none()
+            || args.from.is_none()
+            || args.to.is_none())
+}
+
+fn rollback_needs_inventory(args: &RollbackArgs) -> bool {
+    io::stdin().is_terminal()
+        && (args.tenant.is_none() || args.project.is_none() || args.environment.is_none())
+}
+
+fn secret_needs_inventory(args: &SecretTargetArgs) -> bool {
+    io::stdin().is_terminal()
+        && (args.tenant.is_none() || args.project.is_none() || args.environment.is_none())
+}
+
+fn deployment_needs_inventory(
+    (tenant, project, environment): &(Option<String>, Option<String>, Option<String>),
+) -> bool {
+    io::stdin().is_terminal() && (tenant.is_none() || project.is_none() || environment.is_none())
+}
+
+fn remove_needs_inventory(args: &RemoveArgs) -> bool {
+    io::stdin().is_terminal()
+        && (args.tenant.is_none() || args.project.is_none() || args.environment.is_none())
+}
+
 fn create_request(args: CreateArgs, projects: &[ProjectSummary]) -> Result<CreateAppRequest> {
     let mut args = args;
     let _ = args.watch;
     let tenant = prompt_tenant(args.tenant.take(), projects, true)?;
     let project = prompt_project(args.project.take(), &tenant, projects, true)?;
     let environment = prompt_required(args.environment.take(), "Environment", "--environment")?;
-    component_request(args, tenant, project, environment)
+    let request = component_request(args, tenant, project, environment)?;
+    request.validate_create().map_err(anyhow::Error::msg)?;
+    Ok(request)
 }
 
 fn add_request(args: AddArgs, projects: &[ProjectSummary]) -> Result<CreateAppRequest> {
@@ -606,6 +1211,7 @@ impl AddArgs {
             volumes: self.volumes,
             postgres: self.postgres,
             postgres_size: self.postgres_size,
+            container: self.container,
             watch: false,
         }
     }
@@ -636,7 +1242,8 @@ fn component_request(
     let include_deployment = components.contains(&"Deployment")
         || args.image.is_some()
         || args.source_repo.is_some()
-        || args.port.is_some();
+        || args.port.is_some()
+        || !args.container.is_empty();
     let include_service =
         components.contains(&"Service") || components.contains(&"HTTPRoute") || args.service;
     let include_route = components.contains(&"HTTPRoute") || args.hostname.is_some();
@@ -675,6 +1282,17 @@ fn component_request(
             source_repo: args.source_repo,
             replicas: args.replicas,
             port: args.port,
+            resources: None,
+            command: args
+                .container
+                .command
+                .as_deref()
+                .map(|command| command.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+            probes: args.container.probes()?,
+            security_context: args.container.security_context(),
+            args: args.container.args,
+            env: parse_key_values(args.container.env)?,
         })
     } else {
         None
@@ -710,8 +1328,10 @@ fn component_request(
         http_route,
         config,
         secrets,
+        sealed_secrets: Vec::new(),
         volumes,
         postgres,
+        autoscale: None,
     };
     request.validate().map_err(anyhow::Error::msg)?;
 
@@ -729,6 +1349,7 @@ fn has_create_resource_flags(args: &CreateArgs) -> bool {
         || !args.secrets.is_empty()
         || !args.volumes.is_empty()
         || args.postgres
+        || !args.container.is_empty()
 }
 
 fn prompt_create_components() -> Result<Vec<&'static str>> {
@@ -940,6 +1561,125 @@ fn delete_request(args: DeleteArgs) -> Result<(DeleteAppRequest, bool)> {
     Ok((request, args.watch))
 }
 
+fn promote_request(args: PromoteArgs, projects: &[ProjectSummary]) -> Result<PromoteAppRequest> {
+    let tenant = prompt_tenant(args.tenant, projects, false)?;
+    let project = prompt_project(args.project, &tenant, projects, false)?;
+    let from = prompt_environment(args.from, &tenant, &project, projects)?;
+    let to = prompt_environment(args.to, &tenant, &project, projects)?;
+    let request = PromoteAppRequest {
+        tenant,
+        project,
+        from,
+        to,
+    };
+    request.validate().map_err(anyhow::Error::msg)?;
+    Ok(request)
+}
+
+fn rollback_request(args: RollbackArgs, projects: &[ProjectSummary]) -> Result<RollbackAppRequest> {
+    let tenant = prompt_tenant(args.tenant, projects, false)?;
+    let project = prompt_project(args.project, &tenant, projects, false)?;
+    let environment = prompt_environment(args.environment, &tenant, &project, projects)?;
+    let request = RollbackAppRequest {
+        tenant,
+        project,
+        environment,
+        image: args.image,
+    };
+    request.validate().map_err(anyhow::Error::msg)?;
+    Ok(request)
+}
+
+fn deployment_request(
+    (tenant, project, environment): (Option<String>, Option<String>, Option<String>),
+    projects: &[ProjectSummary],
+    deployment: Option<CreateDeployment>,
+    autoscale: Option<CreateAutoscale>,
+) -> Result<CreateAppRequest> {
+    let tenant = prompt_tenant(tenant, projects, false)?;
+    let project = prompt_project(project, &tenant, projects, false)?;
+    let environment = prompt_environment(environment, &tenant, &project, projects)?;
+    let request = CreateAppRequest {
+        tenant,
+        project,
+        environment,
+        force: false,
+        deployment,
+        service: None,
+        http_route: None,
+        config: Vec::new(),
+        secrets: Vec::new(),
+        sealed_secrets: Vec::new(),
+        volumes: Vec::new(),
+        postgres: None,
+        autoscale,
+    };
+    request.validate().map_err(anyhow::Error::msg)?;
+    Ok(request)
+}
+
+fn remove_request(
+    args: RemoveArgs,
+    projects: &[ProjectSummary],
+) -> Result<RemoveComponentsRequest> {
+    let tenant = prompt_tenant(args.tenant, projects, false)?;
+    let project = prompt_project(args.project, &tenant, projects, false)?;
+    let environment = prompt_environment(args.environment, &tenant, &project, projects)?;
+    let request = RemoveComponentsRequest {
+        tenant,
+        project,
+        environment,
+        components: vec![AppComponent {
+            kind: args.kind,
+            name: args.name,
+        }],
+    };
+    request.validate().map_err(anyhow::Error::msg)?;
+    Ok(request)
+}
+
+fn secret_request(
+    args: SecretTargetArgs,
+    projects: &[ProjectSummary],
+    set: Vec<KeyValue>,
+    unset: Vec<String>,
+) -> Result<UpdateSecretsRequest> {
+    let tenant = prompt_tenant(args.tenant, projects, false)?;
+    let project = prompt_project(args.project, &tenant, projects, false)?;
+    let environment = prompt_environment(args.environment, &tenant, &project, projects)?;
+    let request = UpdateSecretsRequest {
+        tenant,
+        project,
+        environment,
+        set,
+        unset,
+    };
+    if !request.set.is_empty() || !request.unset.is_empty() {
+        request.validate().map_err(anyhow::Error::msg)?;
+    }
+    Ok(request)
+}
+
+fn prompt_rollback_image(revisions: &[ImageRevision]) -> Result<String> {
+    if revisions.is_empty() {
+        bail!("no previously deployed image to roll back to");
+    }
+    let options = revisions
+        .iter()
+        .map(|revision| {
+            format!(
+                "{}  {}  {}  {}",
+                revision.image,
+                revision.commit_sha.chars().take(12).collect::<String>(),
+                revision.timestamp,
+                revision.author
+            )
+        })
+        .collect::<Vec<_>>();
+    let choice = Select::new("Roll back to", options).raw_prompt()?;
+    Ok(revisions[choice.index].image.clone())
+}
+
 fn git_commit(commit: Option<&str>) -> Result<String> {
     git_output(["rev-parse", commit.unwrap_or("HEAD")])
 }
@@ -952,19 +1692,39 @@ fn repo_name_from_slug(repo: &str) -> Result<&str> {
 }
 
 fn print_workflow_status(status: &WorkflowStatus) {
+    let state = workflow_state_label(status);
     if let Some(url) = &status.url {
-        println!("{}\t{}\t{}", status.workflow_id, status.status, url);
+        println!("{}\t{state}\t{}", status.workflow_id, url);
     } else {
-        println!("{}\t{}", status.workflow_id, status.status);
+        println!("{}\t{state}", status.workflow_id);
+    }
+}
+
+fn workflow_state_label(status: &WorkflowStatus) -> String {
+    match &status.approval {
+        Some(approval) if approval.is_pending() => {
+            format!(
+                "{} (awaiting approval for {})",
+                status.status, approval.environment
+            )
+        }
+        Some(approval) => format!(
+            "{} ({} by {})",
+            status.status,
+            approval.state.as_str(),
+            approval.approver.as_deref().unwrap_or("unknown")
+        ),
+        None => status.status.clone(),
     }
 }
 
 fn print_commit_status(commit: &str, status: &WorkflowStatus) {
     let commit = commit.chars().take(12).collect::<String>();
+    let state = workflow_state_label(status);
     if let Some(url) = &status.url {
-        println!("{commit}\t{}\t{url}", status.status);
+        println!("{commit}\t{state}\t{url}");
     } else {
-        println!("{commit}\t{}", status.status);
+        println!("{commit}\t{state}");
     }
 }
 
@@ -989,6 +1749,40 @@ fn print_projects(projects: &[ProjectSummary]) {
     println!("{table}");
 }
 
+fn print_history(history: &[DeploymentHistoryEntry]) {
+    let header = [
+        "TIME", "TENANT", "PROJECT", "ENV", "CHANGE", "SOURCE", "REVISION", "COMMIT", "AUTHOR",
+    ]
+    .map(|title| Cell::new(title).add_attribute(Attribute::Bold));
+    let mut table = Table::new();
+    table
+        .load_preset(NOTHING)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(header);
+
+    for entry in history {
+        table.add_row([
+            entry.timestamp.as_str(),
+            entry.tenant.as_str(),
+            entry.project.as_str(),
+            entry.environment.as_str(),
+            entry.change.as_str(),
+            entry.source_repo.as_deref().unwrap_or("-"),
+            &entry
+                .source_revision
+                .as_deref()
+                .unwrap_or("-")
+                .chars()
+                .take(12)
+                .collect::<String>(),
+            &entry.commit_sha.chars().take(12).collect::<String>(),
+            entry.author.as_str(),
+        ]);
+    }
+
+    println!("{table}");
+}
+
 async fn server_credentials(
     http: &Client,
     server: Option<String>,
@@ -1112,6 +1906,74 @@ fn parse_key_values(values: Vec<String>) -> Result<Vec<KeyValue>> {
         .collect()
 }
 
+fn parse_secret_values(values: Vec<String>) -> Result<Vec<KeyValue>> {
+    values
+        .into_iter()
+        .map(|value| match value.split_once('=') {
+            Some((key, value)) => Ok(KeyValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            None => {
+                ensure_interactive(&format!("{value}=VALUE"))?;
+                let secret = Password::new(&value).without_confirmation().prompt()?;
+                Ok(KeyValue {
+                    key: value,
+                    value: secret,
+                })
+            }
+        })
+        .collect()
+}
+
+fn parse_quantity_pair(value: Option<&str>) -> (Option<String>, Option<String>) {
+    let Some(value) = value else {
+        return (None, None);
+    };
+    let (request, limit) = value.split_once(':').unwrap_or((value, ""));
+    let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    (non_empty(request), non_empty(limit))
+}
+
+fn parse_autoscale(value: &str) -> Result<CreateAutoscale> {
+    let parts = value.split(':').collect::<Vec<_>>();
+    let [min, max, cpu] = parts.as_slice() else {
+        bail!("{value}: expected MIN:MAX:CPU%");
+    };
+    let number = |part: &str, name: &str| {
+        part.trim_end_matches('%')
+            .parse::<u32>()
+            .with_context(|| format!("{value}: invalid {name} {part:?}"))
+    };
+    Ok(CreateAutoscale {
+        min_replicas: number(min, "min replicas")?,
+        max_replicas: number(max, "max replicas")?,
+        cpu_utilization: number(cpu, "cpu utilization")?,
+    })
+}
+
+fn parse_probe(value: &str) -> Result<CreateProbe> {
+    let probe = match value.split_once(':') {
+        Some(("http", path)) => CreateProbe {
+            http_path: Some(path.to_string()),
+            ..CreateProbe::default()
+        },
+        Some(("tcp", port)) => CreateProbe {
+            tcp_port: Some(
+                port.parse()
+                    .with_context(|| format!("{value}: invalid port {port:?}"))?,
+            ),
+            ..CreateProbe::default()
+        },
+        Some(("exec", command)) => CreateProbe {
+            exec: command.split_whitespace().map(str::to_string).collect(),
+            ..CreateProbe::default()
+        },
+        _ => bail!("{value}: expected http:/path, tcp:PORT or exec:COMMAND"),
+    };
+    Ok(probe)
+}
+
 fn parse_volumes(values: Vec<String>) -> Result<Vec<CreateVolume>> {
     values
         .into_iter()
@@ -1,3 +1,4 @@
+pub mod build_config;
 pub mod builder;
 pub mod image;
 pub mod source;
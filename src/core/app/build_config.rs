@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path},
+};
+
+pub const BUILD_CONFIG_FILE: &str = "netamos.yaml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub build: BuildConfig,
+    pub components: Vec<ComponentConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentConfig {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub build: BuildConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuilderKind {
+    Dockerfile,
+    Nixpacks,
+    Buildpacks,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    pub builder: Option<BuilderKind>,
+    pub dockerfile: Option<String>,
+    pub context: Option<String>,
+    pub args: BTreeMap<String, String>,
+    pub target: Option<String>,
+    pub platforms: Vec<String>,
+}
+
+impl RepoConfig {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        if contents.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let config: Self = yaml_serde::from_str(contents)
+            .map_err(|error| format!("{BUILD_CONFIG_FILE}: {error}"))?;
+        config
+            .validate()
+            .map_err(|error| format!("{BUILD_CONFIG_FILE}: {error}"))?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.build.validate()?;
+        let mut names = BTreeSet::new();
+        for component in &self.components {
+            validate_component_name(&component.name)?;
+            if !names.insert(component.name.as_str()) {
+                return Err(format!("component {} is declared twice", component.name));
+            }
+            validate_relative_path(
+                &format!("components.{}.path", component.name),
+                &component.path,
+            )?;
+            component
+                .build
+                .validate()
+                .map_err(|error| format!("components.{}.{error}", component.name))?;
+        }
+        Ok(())
+    }
+
+    pub fn component_build(&self, name: Option<&str>) -> Result<BuildConfig, String> {
+        let Some(name) = name else {
+            return Ok(self.build.clone());
+        };
+        let component = self
+            .components
+            .iter()
+            .find(|component| component.name == name)
+            .ok_or_else(|| format!("component {name} is not declared in {BUILD_CONFIG_FILE}"))?;
+
+        let mut build = component.build.clone();
+        build.context = Some(match &build.context {
+            Some(context) => Path::new(&component.path)
+                .join(context)
+                .to_string_lossy()
+                .to_string(),
+            None => component.path.clone(),
+        });
+        Ok(build)
+    }
+}
+
+impl BuildConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(context) = &self.context {
+            validate_relative_path("build.context", context)?;
+        }
+        if let Some(dockerfile) = &self.dockerfile {
+            validate_relative_path("build.dockerfile", dockerfile)?;
+            if self
+                .builder
+                .is_some_and(|builder| builder != BuilderKind::Dockerfile)
+            {
+                return Err("build.dockerfile requires the dockerfile builder".to_string());
+            }
+        }
+        if let Some(target) = &self.target {
+            if target.trim().is_empty() {
+                return Err("build.target must not be empty".to_string());
+            }
+            if self
+                .builder
+                .is_some_and(|builder| builder != BuilderKind::Dockerfile)
+            {
+                return Err("build.target requires the dockerfile builder".to_string());
+            }
+        }
+        for key in self.args.keys() {
+            if key.is_empty() || key.contains('=') || key.chars().any(char::is_whitespace) {
+                return Err(format!("build.args has an invalid name {key:?}"));
+            }
+        }
+        if self.builder == Some(BuilderKind::Buildpacks) && !self.platforms.is_empty() {
+            return Err("build.platforms is not supported with the buildpacks builder".to_string());
+        }
+        for platform in &self.platforms {
+            validate_platform(platform)?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_relative_path(field: &str, value: &str) -> Result<(), String> {
+    let path = Path::new(value);
+    if value.trim().is_empty() {
+        return Err(format!("{field} must not be empty"));
+    }
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!(
+            "{field} must be a relative path inside the repository"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_component_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-');
+    if !valid {
+        return Err(format!(
+            "component name {name:?} must contain only lowercase letters, digits, and '-'"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_platform(platform: &str) -> Result<(), String> {
+    let parts = platform.split('/').collect::<Vec<_>>();
+    let valid = matches!(parts.len(), 2 | 3)
+        && parts.iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_')
+        });
+    if !valid {
+        return Err(format!(
+            "build.platforms entry {platform:?} must look like os/arch or os/arch/variant"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_build_config() {
+        let config = RepoConfig::parse(
+            r#"
+build:
+  builder: dockerfile
+  context: services/api
+  dockerfile: docker/Dockerfile
+  target: runtime
+  args:
+    NODE_ENV: production
+  platforms:
+    - linux/amd64
+    - linux/arm64/v8
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.build.builder, Some(BuilderKind::Dockerfile));
+        assert_eq!(config.build.context.as_deref(), Some("services/api"));
+        assert_eq!(config.build.args["NODE_ENV"], "production");
+        assert_eq!(config.build.platforms.len(), 2);
+    }
+
+    #[test]
+    fn resolves_component_build_context() {
+        let config = RepoConfig::parse(
+            r#"
+components:
+  - name: api
+    path: services/api
+  - name: web
+    path: services/web
+    build:
+      context: app
+      builder: nixpacks
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config
+                .component_build(Some("api"))
+                .unwrap()
+                .context
+                .as_deref(),
+            Some("services/api")
+        );
+        assert_eq!(
+            config
+                .component_build(Some("web"))
+                .unwrap()
+                .context
+                .as_deref(),
+            Some("services/web/app")
+        );
+        assert!(config.component_build(Some("worker")).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_build_config() {
+        for contents in [
+            "build:\n  builder: bazel\n",
+            "build:\n  context: ../other\n",
+            "build:\n  dockerfile: /etc/Dockerfile\n",
+            "build:\n  builder: nixpacks\n  target: runtime\n",
+            "build:\n  platforms: [amd64]\n",
+            "build:\n  args:\n    'A=B': c\n",
+            "build:\n  unknown: true\n",
+            "components:\n  - name: API\n    path: api\n",
+            "components:\n  - name: api\n    path: ../api\n",
+            "components:\n  - name: api\n    path: a\n  - name: api\n    path: b\n",
+        ] {
+            assert!(RepoConfig::parse(contents).is_err(), "{contents}");
+        }
+    }
+}
@@ -6,5 +6,6 @@ use std::path::PathBuf;
 pub enum Builder {
     Dockerfile(PathBuf, Image),
     Nixpacks(PathBuf, Image),
+    Buildpacks(PathBuf, Image),
     Vendor(Image, Image),
 }
@@ -7,6 +7,17 @@ pub struct Image {
     pub owner: String,
     pub repository: String,
     pub tag: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl Image {
+    pub fn pinned_reference(&self) -> String {
+        match &self.digest {
+            Some(digest) => format!("{self}@{digest}"),
+            None => self.to_string(),
+        }
+    }
 }
 
 impl fmt::Display for Image {
@@ -1,16 +1,36 @@
 mod bundle;
 mod create;
+mod history;
 mod inventory;
 mod manifest;
+mod merge;
+mod oci;
+mod preview;
+mod remove;
+mod secrets;
 mod update;
 
-pub(crate) use bundle::{AppsBundle, write_apps_bundle};
+pub(crate) use bundle::{AppsBundle, OciRepositoryAccess, write_apps_bundle};
 pub(crate) use create::{write_add_app_manifests, write_create_app_manifests};
-#[cfg(test)]
-use inventory::source_repo_from_image;
-pub use inventory::{scan_app_inventory, scan_app_source_targets};
+pub(crate) use history::image_history_args;
+pub use history::{
+    ImageChange, commit_change, parse_image_history, rollback_candidates, select_rollback_image,
+    update_commit_source_repo,
+};
+pub use inventory::{SourceImage, scan_app_inventory, scan_app_source_targets};
+pub(crate) use inventory::{
+    deployed_component_tags, scan_environment_images, source_image_from_reference,
+    split_image_reference,
+};
+pub(crate) use oci::{OciCredentials, OciRegistryClient};
+pub(crate) use preview::write_preview_environment;
+pub use preview::{PREVIEW_BASE_ENVIRONMENT, preview_environment, preview_hostname};
+pub(crate) use remove::remove_app_component;
+pub use secrets::sealed_secret_keys;
+pub(crate) use secrets::update_sealed_secret;
 pub(crate) use update::update_app_version_inner;
 
+use crate::api::ImageVersion;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -34,6 +54,7 @@ pub struct AppInventory {
     pub resources: Vec<String>,
     pub hostnames: Vec<String>,
     pub images: Vec<String>,
+    pub image_versions: Vec<ImageVersion>,
     pub source_repos: Vec<String>,
 }
 
@@ -41,20 +62,29 @@ pub struct AppInventory {
 pub(crate) struct AppImageUpdate {
     pub repository: String,
     pub tag: String,
+    #[serde(default)]
+    pub digest: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct UpdateAppVersionInput {
     pub apps_dir: String,
     pub environment: String,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub project: Option<String>,
     pub new_images: Vec<AppImageUpdate>,
 }
 
 #[cfg(test)]
 mod tests {
+    use super::inventory::source_repo_from_image;
     use super::*;
     use crate::api::{
-        CreateAppRequest, CreateDeployment, CreateHttpRoute, CreateService, CreateVolume, KeyValue,
+        AppComponent, ComponentKind, ContainerResources, CreateAppRequest, CreateAutoscale,
+        CreateDeployment, CreateHttpRoute, CreateProbe, CreateSecurityContext, CreateService,
+        CreateVolume, DeploymentChange, DeploymentProbes, KeyValue,
     };
     use std::fs;
     use std::path::{Path, PathBuf};
@@ -114,9 +144,12 @@ metadata:
         let changed = update_app_version_inner(UpdateAppVersionInput {
             apps_dir: tmp.to_string_lossy().to_string(),
             environment: "production".to_string(),
+            tenant: None,
+            project: None,
             new_images: vec![AppImageUpdate {
                 repository: "registry.registry.svc.cluster.local/apps/khuedoan/blog".to_string(),
                 tag: "test-tag-123".to_string(),
+                digest: None,
             }],
         })
         .await
@@ -143,9 +176,12 @@ metadata:
         let changed = update_app_version_inner(UpdateAppVersionInput {
             apps_dir: tmp.to_string_lossy().to_string(),
             environment: "production".to_string(),
+            tenant: None,
+            project: None,
             new_images: vec![AppImageUpdate {
                 repository: "registry.registry.svc.cluster.local/apps/khuedoan/blog".to_string(),
                 tag: "6fbd90b77a81e0bcb330fddaa230feff744a7010".to_string(),
+                digest: None,
             }],
         })
         .await
@@ -154,6 +190,49 @@ metadata:
         assert!(!changed);
     }
 
+    #[tokio::test]
+    async fn test_update_app_version_pins_digest() {
+        let tmp = PathBuf::from("/tmp/test-cloudlab-apps-digest");
+        let _ = tokio::fs::remove_dir_all(&tmp).await;
+        tokio::fs::create_dir_all(&tmp).await.unwrap();
+        write_app_fixture(
+            &tmp,
+            "registry.registry.svc.cluster.local/apps/khuedoan/blog:old-tag",
+        );
+        let digest = format!("sha256:{}", "a".repeat(64));
+
+        let changed = update_app_version_inner(UpdateAppVersionInput {
+            apps_dir: tmp.to_string_lossy().to_string(),
+            environment: "production".to_string(),
+            tenant: None,
+            project: None,
+            new_images: vec![AppImageUpdate {
+                repository: "registry.registry.svc.cluster.local/apps/khuedoan/blog".to_string(),
+                tag: "test-tag-123".to_string(),
+                digest: Some(digest.clone()),
+            }],
+        })
+        .await
+        .unwrap();
+
+        assert!(changed);
+        let deployment =
+            fs::read_to_string(tmp.join("khuedoan/blog/production/deployment-blog.yaml")).unwrap();
+        assert!(deployment.contains(&format!(
+            "image: registry.registry.svc.cluster.local/apps/khuedoan/blog:test-tag-123@{digest}"
+        )));
+
+        let inventory = scan_app_inventory(&tmp, "registry.registry.svc.cluster.local").unwrap();
+        assert_eq!(
+            inventory[0].image_versions,
+            vec![ImageVersion {
+                repository: "registry.registry.svc.cluster.local/apps/khuedoan/blog".to_string(),
+                tag: Some("test-tag-123".to_string()),
+                digest: Some(digest),
+            }]
+        );
+    }
+
     #[test]
     fn test_source_repo_from_image() {
         let registry = "registry.registry.svc.cluster.local";
@@ -184,6 +263,13 @@ metadata:
                 registry,
                 "registry.registry.svc.cluster.local/apps/khuedoan/team/blog:1"
             ),
+            Some("khuedoan/team".to_string())
+        );
+        assert_eq!(
+            source_repo_from_image(
+                registry,
+                "registry.registry.svc.cluster.local/apps/khuedoan/team/blog/extra:1"
+            ),
             None
         );
         assert_eq!(
@@ -238,6 +324,49 @@ spec:
         );
     }
 
+    #[test]
+    fn test_deployed_component_tags() {
+        let source = PathBuf::from("/tmp/test-cloudlab-component-tags");
+        let _ = fs::remove_dir_all(&source);
+        let app_dir = source.join("khuedoan").join("shop").join("production");
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::write(
+            app_dir.join("deployment-shop.yaml"),
+            r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: shop
+spec:
+  template:
+    spec:
+      containers:
+        - name: api
+          image: registry.registry.svc.cluster.local/apps/khuedoan/shop/api:abc123
+        - name: web
+          image: registry.registry.svc.cluster.local/apps/khuedoan/shop/web:def456
+        - name: other
+          image: registry.registry.svc.cluster.local/apps/khuedoan/other/api:zzz
+"#,
+        )
+        .unwrap();
+
+        let tags = deployed_component_tags(
+            &source,
+            "registry.registry.svc.cluster.local",
+            "khuedoan/shop",
+            "production",
+        )
+        .unwrap();
+
+        assert_eq!(tags.len(), 2);
+        assert!(tags["api"].contains("abc123"));
+        assert!(tags["web"].contains("def456"));
+        assert_eq!(
+            SourceImage::new("khuedoan", "shop", Some("api")).image_owner(),
+            "apps/khuedoan/shop"
+        );
+    }
+
     #[test]
     fn test_write_apps_bundle() {
         let source = PathBuf::from("/tmp/test-cloudlab-apps-bundle-source");
@@ -252,6 +381,11 @@ spec:
             "apps",
             "latest",
             "registry.registry.svc.cluster.local",
+            &OciRepositoryAccess {
+                insecure: true,
+                secret_ref: Some("registry-auth".to_string()),
+                cert_secret_ref: None,
+            },
         )
         .unwrap();
 
@@ -271,7 +405,7 @@ spec:
             fs::read_to_string(output.join("root/ocirepository-khuedoan-blog-production.yaml"))
                 .unwrap()
                 .contains(
-                    "url: oci://registry.registry.svc.cluster.local/apps/khuedoan/blog/production"
+                    "url: oci://registry.registry.svc.cluster.local/apps/khuedoan/blog/production\n  insecure: true\n  secretRef:\n    name: registry-auth\n"
                 )
         );
         assert!(
@@ -372,6 +506,7 @@ metadata:
                 "apps",
                 "latest",
                 "registry.registry.svc.cluster.local",
+                &OciRepositoryAccess::default(),
             )
             .expect_err(name);
             assert!(
@@ -395,8 +530,14 @@ metadata:
             deployment: Some(CreateDeployment {
                 image: None,
                 source_repo: Some("khuedoan/example-service".to_string()),
-                replicas: 1,
+                replicas: Some(1),
                 port: Some(3000),
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
             }),
             service: Some(CreateService { port: 3000 }),
             http_route: Some(CreateHttpRoute {
@@ -407,9 +548,10 @@ metadata:
                 key: "GREETING".to_string(),
                 value: "hello".to_string(),
             }],
-            secrets: vec![KeyValue {
+            secrets: Vec::new(),
+            sealed_secrets: vec![KeyValue {
                 key: "TOKEN".to_string(),
-                value: "vault:secret/data/example#token".to_string(),
+                value: "AgBy3i4OJSWK+PiTySYZZA==".to_string(),
             }],
             volumes: vec![CreateVolume {
                 name: "data".to_string(),
@@ -417,6 +559,7 @@ metadata:
                 mount_path: "/data".to_string(),
             }],
             postgres: None,
+            autoscale: None,
         };
 
         let app_dir = output.join("test/example/staging");
@@ -469,8 +612,10 @@ metadata:
             http_route: None,
             config: Vec::new(),
             secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
             volumes: Vec::new(),
             postgres: None,
+            autoscale: None,
         };
 
         let app_dir = output.join("test/empty/production");
@@ -509,8 +654,10 @@ metadata:
             http_route: None,
             config: Vec::new(),
             secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
             volumes: Vec::new(),
             postgres: None,
+            autoscale: None,
         };
 
         let app_dir = output.join("test/example/production");
@@ -564,4 +711,838 @@ metadata:
                 .contains(&"HTTPRoute/example".to_string())
         );
     }
+
+    #[test]
+    fn test_write_add_app_manifests_merges_existing_components() {
+        let output = PathBuf::from("/tmp/test-cloudlab-add-app-merge");
+        let _ = fs::remove_dir_all(&output);
+        let app_dir = output.join("test/example/production");
+        fs::create_dir_all(&app_dir).unwrap();
+
+        let create_request = CreateAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            force: false,
+            deployment: Some(CreateDeployment {
+                image: None,
+                source_repo: Some("khuedoan/example-service".to_string()),
+                replicas: Some(3),
+                port: Some(3000),
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            }),
+            service: None,
+            http_route: None,
+            config: vec![KeyValue {
+                key: "GREETING".to_string(),
+                value: "hello".to_string(),
+            }],
+            secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
+            volumes: Vec::new(),
+            postgres: None,
+            autoscale: None,
+        };
+        write_create_app_manifests(
+            &app_dir,
+            &create_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+        let deployment_path = app_dir.join("deployment-example.yaml");
+        let deployment = fs::read_to_string(&deployment_path)
+            .unwrap()
+            .replace(
+                "registry.registry.svc.cluster.local/apps/khuedoan/example-service:latest",
+                "registry.registry.svc.cluster.local/apps/khuedoan/example-service:abc123",
+            )
+            .replace("replicas: 3", "replicas: 3\n  revisionHistoryLimit: 2");
+        fs::write(&deployment_path, deployment).unwrap();
+
+        let add_request = CreateAppRequest {
+            deployment: Some(CreateDeployment {
+                image: None,
+                source_repo: Some("khuedoan/example-service".to_string()),
+                replicas: None,
+                port: Some(8080),
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            }),
+            config: vec![KeyValue {
+                key: "LOG_LEVEL".to_string(),
+                value: "debug".to_string(),
+            }],
+            volumes: vec![CreateVolume {
+                name: "data".to_string(),
+                size: "1Gi".to_string(),
+                mount_path: "/data".to_string(),
+            }],
+            ..create_request
+        };
+        let count = write_add_app_manifests(
+            &app_dir,
+            &add_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+
+        assert_eq!(count, 3);
+        let config_map = fs::read_to_string(app_dir.join("configmap-example.yaml")).unwrap();
+        assert!(config_map.contains("GREETING: hello"));
+        assert!(config_map.contains("LOG_LEVEL: debug"));
+
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(deployment.contains("replicas: 3"));
+        assert!(deployment.contains("revisionHistoryLimit: 2"));
+        assert!(deployment.contains("apps/khuedoan/example-service:abc123"));
+        assert!(deployment.contains("containerPort: 8080"));
+        assert!(!deployment.contains("containerPort: 3000"));
+        assert_eq!(deployment.matches("configMapRef").count(), 1);
+        assert!(deployment.contains("mountPath: /data"));
+        assert!(deployment.contains("claimName: data"));
+    }
+
+    #[test]
+    fn test_write_add_app_manifests_scales_deployment() {
+        let output = PathBuf::from("/tmp/test-cloudlab-scale-app");
+        let _ = fs::remove_dir_all(&output);
+        let app_dir = output.join("test/example/production");
+        fs::create_dir_all(&app_dir).unwrap();
+
+        let create_request = CreateAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            force: false,
+            deployment: Some(CreateDeployment {
+                image: Some("nginx:1.27".to_string()),
+                source_repo: None,
+                replicas: Some(2),
+                port: Some(80),
+                resources: Some(ContainerResources {
+                    cpu_request: Some("100m".to_string()),
+                    ..ContainerResources::default()
+                }),
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            }),
+            service: None,
+            http_route: None,
+            config: Vec::new(),
+            secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
+            volumes: Vec::new(),
+            postgres: None,
+            autoscale: None,
+        };
+        write_create_app_manifests(
+            &app_dir,
+            &create_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+        let deployment_path = app_dir.join("deployment-example.yaml");
+
+        let resources_request = CreateAppRequest {
+            deployment: Some(CreateDeployment {
+                image: None,
+                source_repo: None,
+                replicas: None,
+                port: None,
+                resources: Some(ContainerResources {
+                    memory_request: Some("256Mi".to_string()),
+                    memory_limit: Some("512Mi".to_string()),
+                    ..ContainerResources::default()
+                }),
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            }),
+            ..create_request.clone()
+        };
+        write_add_app_manifests(
+            &app_dir,
+            &resources_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(deployment.contains("cpu: 100m"));
+        assert!(deployment.contains("memory: 256Mi"));
+        assert!(deployment.contains("memory: 512Mi"));
+        assert!(deployment.contains("replicas: 2"));
+
+        let autoscale_request = CreateAppRequest {
+            deployment: None,
+            autoscale: Some(CreateAutoscale {
+                min_replicas: 3,
+                max_replicas: 10,
+                cpu_utilization: 80,
+            }),
+            ..create_request.clone()
+        };
+        let count = write_add_app_manifests(
+            &app_dir,
+            &autoscale_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+        let autoscaler_path = app_dir.join("horizontalpodautoscaler-example.yaml");
+        manifest::validate_app_manifest(
+            &autoscaler_path,
+            &manifest::read_app_manifest(&autoscaler_path).unwrap(),
+        )
+        .unwrap();
+        let autoscaler = fs::read_to_string(&autoscaler_path).unwrap();
+        assert!(autoscaler.contains("kind: HorizontalPodAutoscaler"));
+        assert!(autoscaler.contains("minReplicas: 3"));
+        assert!(autoscaler.contains("averageUtilization: 80"));
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(!deployment.contains("replicas:"));
+
+        assert!(
+            write_add_app_manifests(
+                &app_dir,
+                &create_request,
+                "registry.registry.svc.cluster.local",
+            )
+            .is_err()
+        );
+
+        remove_app_component(
+            &app_dir,
+            "example",
+            &AppComponent {
+                kind: ComponentKind::Autoscaler,
+                name: "example".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(!autoscaler_path.exists());
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(deployment.contains("replicas: 3"));
+    }
+
+    #[test]
+    fn test_write_add_app_manifests_configures_container() {
+        let output = PathBuf::from("/tmp/test-cloudlab-container-app");
+        let _ = fs::remove_dir_all(&output);
+        let app_dir = output.join("test/example/production");
+        fs::create_dir_all(&app_dir).unwrap();
+
+        let create_request = CreateAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            force: false,
+            deployment: Some(CreateDeployment {
+                image: Some("nginx:1.27".to_string()),
+                source_repo: None,
+                replicas: None,
+                port: Some(80),
+                resources: None,
+                command: vec!["nginx".to_string()],
+                args: vec!["-g".to_string(), "daemon off;".to_string()],
+                env: vec![KeyValue {
+                    key: "LOG_LEVEL".to_string(),
+                    value: "info".to_string(),
+                }],
+                probes: DeploymentProbes {
+                    liveness: Some(CreateProbe {
+                        http_path: Some("/healthz".to_string()),
+                        period_seconds: Some(10),
+                        ..CreateProbe::default()
+                    }),
+                    readiness: None,
+                    startup: None,
+                },
+                security_context: Some(CreateSecurityContext {
+                    run_as_user: Some(1000),
+                    run_as_non_root: true,
+                    ..CreateSecurityContext::default()
+                }),
+            }),
+            service: None,
+            http_route: None,
+            config: Vec::new(),
+            secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
+            volumes: Vec::new(),
+            postgres: None,
+            autoscale: None,
+        };
+        write_create_app_manifests(
+            &app_dir,
+            &create_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+        let deployment_path = app_dir.join("deployment-example.yaml");
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(deployment.contains("- nginx"));
+        assert!(deployment.contains("daemon off;"));
+        assert!(deployment.contains("name: LOG_LEVEL"));
+        assert!(deployment.contains("livenessProbe:"));
+        assert!(deployment.contains("path: /healthz"));
+        assert!(deployment.contains("runAsUser: 1000"));
+        assert!(deployment.contains("runAsNonRoot: true"));
+
+        let add_request = CreateAppRequest {
+            deployment: Some(CreateDeployment {
+                image: None,
+                source_repo: None,
+                replicas: None,
+                port: None,
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: vec![
+                    KeyValue {
+                        key: "LOG_LEVEL".to_string(),
+                        value: "debug".to_string(),
+                    },
+                    KeyValue {
+                        key: "WORKERS".to_string(),
+                        value: "4".to_string(),
+                    },
+                ],
+                probes: DeploymentProbes {
+                    liveness: None,
+                    readiness: Some(CreateProbe {
+                        tcp_port: Some(80),
+                        ..CreateProbe::default()
+                    }),
+                    startup: None,
+                },
+                security_context: Some(CreateSecurityContext {
+                    read_only_root_filesystem: true,
+                    ..CreateSecurityContext::default()
+                }),
+            }),
+            ..create_request.clone()
+        };
+        write_add_app_manifests(
+            &app_dir,
+            &add_request,
+            "registry.registry.svc.cluster.local",
+        )
+        .unwrap();
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(deployment.contains("- nginx"));
+        assert!(deployment.contains("value: debug"));
+        assert!(!deployment.contains("value: info"));
+        assert!(deployment.contains("name: WORKERS"));
+        assert!(deployment.contains("path: /healthz"));
+        assert!(deployment.contains("readinessProbe:"));
+        assert!(deployment.contains("tcpSocket:"));
+        assert!(deployment.contains("runAsUser: 1000"));
+        assert!(deployment.contains("readOnlyRootFilesystem: true"));
+    }
+
+    #[test]
+    fn test_add_http_probe_requires_http_port() {
+        let tmp = PathBuf::from("/tmp/test-cloudlab-add-http-probe");
+        let _ = fs::remove_dir_all(&tmp);
+        write_app_fixture(&tmp, "registry.example.com/apps/khuedoan/blog:old");
+        let app_dir = tmp.join("khuedoan/blog/production");
+        let mut request = CreateAppRequest {
+            tenant: "khuedoan".to_string(),
+            project: "blog".to_string(),
+            environment: "production".to_string(),
+            force: false,
+            deployment: Some(CreateDeployment {
+                image: None,
+                source_repo: None,
+                replicas: None,
+                port: None,
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes {
+                    liveness: None,
+                    readiness: Some(CreateProbe {
+                        http_path: Some("/ready".to_string()),
+                        ..CreateProbe::default()
+                    }),
+                    startup: None,
+                },
+                security_context: None,
+            }),
+            service: None,
+            http_route: None,
+            config: Vec::new(),
+            secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
+            volumes: Vec::new(),
+            postgres: None,
+            autoscale: None,
+        };
+
+        assert!(write_add_app_manifests(&app_dir, &request, "registry.example.com").is_err());
+
+        request.deployment.as_mut().unwrap().port = Some(8080);
+        write_add_app_manifests(&app_dir, &request, "registry.example.com").unwrap();
+        let deployment = fs::read_to_string(app_dir.join("deployment-blog.yaml")).unwrap();
+        assert!(deployment.contains("containerPort: 8080"));
+        assert!(deployment.contains("path: /ready"));
+        let _ = fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_remove_app_component_cleans_up_deployment_references() {
+        let output = PathBuf::from("/tmp/test-cloudlab-remove-app-component");
+        let _ = fs::remove_dir_all(&output);
+        let app_dir = output.join("test/example/production");
+        fs::create_dir_all(&app_dir).unwrap();
+
+        let request = CreateAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            force: false,
+            deployment: Some(CreateDeployment {
+                image: Some("nginx:1.27".to_string()),
+                source_repo: None,
+                replicas: None,
+                port: Some(80),
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            }),
+            service: Some(CreateService { port: 80 }),
+            http_route: Some(CreateHttpRoute {
+                hostname: "example.khuedoan.com".to_string(),
+                port: 80,
+            }),
+            config: vec![
+                KeyValue {
+                    key: "GREETING".to_string(),
+                    value: "hello".to_string(),
+                },
+                KeyValue {
+                    key: "LOG_LEVEL".to_string(),
+                    value: "debug".to_string(),
+                },
+            ],
+            secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
+            volumes: vec![CreateVolume {
+                name: "data".to_string(),
+                size: "1Gi".to_string(),
+                mount_path: "/data".to_string(),
+            }],
+            postgres: None,
+            autoscale: None,
+        };
+        write_create_app_manifests(&app_dir, &request, "registry.registry.svc.cluster.local")
+            .unwrap();
+        let remove = |kind: &str, name: &str| {
+            remove_app_component(
+                &app_dir,
+                "example",
+                &AppComponent {
+                    kind: kind.parse().unwrap(),
+                    name: name.to_string(),
+                },
+            )
+        };
+        let deployment_path = app_dir.join("deployment-example.yaml");
+
+        remove("config", "LOG_LEVEL").unwrap();
+        let config_map = fs::read_to_string(app_dir.join("configmap-example.yaml")).unwrap();
+        assert!(config_map.contains("GREETING: hello"));
+        assert!(!config_map.contains("LOG_LEVEL"));
+        assert!(remove("config", "LOG_LEVEL").is_err());
+
+        remove("config", "GREETING").unwrap();
+        assert!(!app_dir.join("configmap-example.yaml").exists());
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(!deployment.contains("envFrom"));
+
+        remove("volume", "data").unwrap();
+        assert!(!app_dir.join("persistentvolumeclaim-data.yaml").exists());
+        let deployment = fs::read_to_string(&deployment_path).unwrap();
+        assert!(!deployment.contains("volumeMounts"));
+        assert!(!deployment.contains("claimName"));
+        assert!(deployment.contains("containerPort: 80"));
+
+        remove("httproute", "example").unwrap();
+        assert!(!app_dir.join("httproute-example.yaml").exists());
+        assert!(app_dir.join("service-example.yaml").exists());
+        assert!(remove("postgres", "example-postgres").is_err());
+    }
+
+    #[test]
+    fn test_update_sealed_secret_toggles_deployment_secret_ref() {
+        let output = PathBuf::from("/tmp/test-cloudlab-update-sealed-secret");
+        let _ = fs::remove_dir_all(&output);
+        let app_dir = output.join("test/example/production");
+        fs::create_dir_all(&app_dir).unwrap();
+
+        let request = CreateAppRequest {
+            tenant: "test".to_string(),
+            project: "example".to_string(),
+            environment: "production".to_string(),
+            force: false,
+            deployment: Some(CreateDeployment {
+                image: Some("nginx:1.27".to_string()),
+                source_repo: None,
+                replicas: Some(1),
+                port: Some(80),
+                resources: None,
+                command: Vec::new(),
+                args: Vec::new(),
+                env: Vec::new(),
+                probes: DeploymentProbes::default(),
+                security_context: None,
+            }),
+            service: None,
+            http_route: None,
+            config: Vec::new(),
+            secrets: Vec::new(),
+            sealed_secrets: Vec::new(),
+            volumes: Vec::new(),
+            postgres: None,
+            autoscale: None,
+        };
+        write_create_app_manifests(&app_dir, &request, "registry.registry.svc.cluster.local")
+            .unwrap();
+
+        update_sealed_secret(
+            &app_dir,
+            "example",
+            &[
+                KeyValue {
+                    key: "TOKEN".to_string(),
+                    value: "AgBy3i4OJSWK+PiTySYZZA==".to_string(),
+                },
+                KeyValue {
+                    key: "API_KEY".to_string(),
+                    value: "AgCx9f1ZQ2pLm0aV8eTyRg==".to_string(),
+                },
+            ],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            sealed_secret_keys(&app_dir, "example").unwrap(),
+            vec!["API_KEY", "TOKEN"]
+        );
+        let sealed = fs::read_to_string(app_dir.join("sealedsecret-example.yaml")).unwrap();
+        assert!(sealed.contains("kind: SealedSecret"));
+        assert!(sealed.contains("TOKEN: AgBy3i4OJSWK+PiTySYZZA=="));
+        let deployment = fs::read_to_string(app_dir.join("deployment-example.yaml")).unwrap();
+        assert!(deployment.contains("secretRef:"));
+
+        update_sealed_secret(&app_dir, "example", &[], &["TOKEN".to_string()]).unwrap();
+        assert_eq!(
+            sealed_secret_keys(&app_dir, "example").unwrap(),
+            vec!["API_KEY"]
+        );
+
+        update_sealed_secret(&app_dir, "example", &[], &["API_KEY".to_string()]).unwrap();
+        assert!(!app_dir.join("sealedsecret-example.yaml").exists());
+        let deployment = fs::read_to_string(app_dir.join("deployment-example.yaml")).unwrap();
+        assert!(!deployment.contains("secretRef:"));
+        assert!(!deployment.contains("envFrom"));
+
+        fs::write(app_dir.join("secret-example.yaml"), "kind: Secret\n").unwrap();
+        assert!(update_sealed_secret(&app_dir, "example", &[], &["TOKEN".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_app_version_scoped_to_project() {
+        let tmp = PathBuf::from("/tmp/test-cloudlab-apps-scoped");
+        let _ = tokio::fs::remove_dir_all(&tmp).await;
+        tokio::fs::create_dir_all(&tmp).await.unwrap();
+        write_app_fixture(
+            &tmp,
+            "registry.registry.svc.cluster.local/apps/khuedoan/blog:old-tag",
+        );
+
+        let changed = update_app_version_inner(UpdateAppVersionInput {
+            apps_dir: tmp.to_string_lossy().to_string(),
+            environment: "production".to_string(),
+            tenant: Some("khuedoan".to_string()),
+            project: Some("docs".to_string()),
+            new_images: vec![AppImageUpdate {
+                repository: "registry.registry.svc.cluster.local/apps/khuedoan/blog".to_string(),
+                tag: "new-tag".to_string(),
+                digest: None,
+            }],
+        })
+        .await
+        .unwrap();
+
+        assert!(!changed);
+    }
+
+    #[test]
+    fn test_split_image_reference() {
+        assert_eq!(
+            split_image_reference("localhost:5000/apps/khuedoan/blog:abc123"),
+            ("localhost:5000/apps/khuedoan/blog", Some("abc123"), None)
+        );
+        assert_eq!(
+            split_image_reference("localhost:5000/apps/khuedoan/blog"),
+            ("localhost:5000/apps/khuedoan/blog", None, None)
+        );
+        assert_eq!(
+            split_image_reference("ghcr.io/khuedoan/blog:1@sha256:abc"),
+            ("ghcr.io/khuedoan/blog", Some("1"), Some("sha256:abc"))
+        );
+    }
+
+    fn image_history_fixture() -> String {
+        [
+            "\u{1e}commit ccc\u{1f}Bot\u{1f}2025-01-03T00:00:00+00:00\u{1f}chore(apps): update khuedoan/blog image for production",
+            "",
+            "diff --git a/apps/khuedoan/blog/production/deployment-blog.yaml b/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "--- a/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "+++ b/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "@@ -10 +10 @@ spec:",
+            "-        image: localhost:5000/apps/khuedoan/blog:bbb",
+            "+        image: localhost:5000/apps/khuedoan/blog:ccc",
+            "\u{1e}commit bbb\u{1f}Bot\u{1f}2025-01-02T00:00:00+00:00\u{1f}chore(apps): update khuedoan/blog image for production",
+            "",
+            "diff --git a/apps/khuedoan/blog/production/deployment-blog.yaml b/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "--- a/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "+++ b/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "@@ -10 +10 @@ spec:",
+            "-        image: localhost:5000/apps/khuedoan/blog:aaa",
+            "+        image: localhost:5000/apps/khuedoan/blog:bbb",
+            "\u{1e}commit aaa\u{1f}Admin\u{1f}2025-01-01T00:00:00+00:00\u{1f}feat(apps): create khuedoan/blog/production",
+            "",
+            "diff --git a/apps/khuedoan/blog/production/deployment-blog.yaml b/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "--- /dev/null",
+            "+++ b/apps/khuedoan/blog/production/deployment-blog.yaml",
+            "@@ -0,0 +1,12 @@",
+            "+apiVersion: apps/v1",
+            "+      - image: localhost:5000/apps/khuedoan/blog:aaa",
+            "+        name: blog",
+        ]
+        .join("\n")
+    }
+
+    #[test]
+    fn test_parse_image_history() {
+        let history = parse_image_history(&image_history_fixture());
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].commit_sha, "ccc");
+        assert_eq!(history[0].image, "localhost:5000/apps/khuedoan/blog:ccc");
+        assert_eq!(history[0].target.environment, "production");
+        assert_eq!(history[2].author, "Admin");
+        assert_eq!(history[2].image, "localhost:5000/apps/khuedoan/blog:aaa");
+    }
+
+    #[test]
+    fn test_rollback_candidates_skip_current_image() {
+        let history = parse_image_history(&image_history_fixture());
+        let target = AppTarget {
+            tenant: "khuedoan".to_string(),
+            project: "blog".to_string(),
+            environment: "production".to_string(),
+        };
+        let current = ["localhost:5000/apps/khuedoan/blog:ccc".to_string()]
+            .into_iter()
+            .collect();
+
+        let candidates = rollback_candidates(&history, &target, &current);
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(
+            select_rollback_image(&candidates, None).unwrap().image,
+            "localhost:5000/apps/khuedoan/blog:bbb"
+        );
+        assert_eq!(
+            select_rollback_image(&candidates, Some("aaa"))
+                .unwrap()
+                .commit_sha,
+            "aaa"
+        );
+        assert!(select_rollback_image(&candidates, Some("ccc")).is_err());
+    }
+
+    #[test]
+    fn test_update_commit_source_repo() {
+        assert_eq!(
+            update_commit_source_repo("chore(apps): update khuedoan/blog image for production"),
+            Some("khuedoan/blog".to_string())
+        );
+        assert_eq!(
+            update_commit_source_repo("chore(apps): roll back khuedoan/blog/production to x"),
+            None
+        );
+        assert_eq!(
+            update_commit_source_repo("feat(apps): create khuedoan/blog/production"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_commit_change() {
+        let history = parse_image_history(&image_history_fixture());
+        assert_eq!(commit_change(&history[0].subject), DeploymentChange::Update);
+        assert_eq!(commit_change(&history[2].subject), DeploymentChange::Create);
+        assert_eq!(
+            commit_change("chore(apps): roll back khuedoan/blog/production to x"),
+            DeploymentChange::Rollback
+        );
+        assert_eq!(
+            commit_change("feat(apps): add components to khuedoan/blog/production"),
+            DeploymentChange::Add
+        );
+        assert_eq!(
+            commit_change("chore(apps): remove deployment/blog from khuedoan/blog/production"),
+            DeploymentChange::Other
+        );
+    }
+
+    #[test]
+    fn test_write_preview_environment() {
+        let tmp = PathBuf::from("/tmp/test-cloudlab-preview");
+        let _ = fs::remove_dir_all(&tmp);
+        write_app_fixture(&tmp, "registry.example.com/apps/khuedoan/blog:old");
+        fs::write(
+            tmp.join("khuedoan/blog/production/httproute-blog.yaml"),
+            r#"apiVersion: gateway.networking.k8s.io/v1
+kind: HTTPRoute
+metadata:
+  name: blog
+spec:
+  hostnames:
+    - blog.example.com
+"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("khuedoan/blog/production/deployment-blog.yaml"),
+            r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: blog
+spec:
+  template:
+    spec:
+      containers:
+        - name: blog
+          image: registry.example.com/apps/khuedoan/blog:old
+          envFrom:
+            - configMapRef:
+                name: blog
+            - secretRef:
+                name: blog
+          volumeMounts:
+            - name: data
+              mountPath: /data
+      volumes:
+        - name: data
+          persistentVolumeClaim:
+            claimName: data
+"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("khuedoan/blog/production/configmap-blog.yaml"),
+            r#"apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: blog
+data:
+  LOG_LEVEL: info
+"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("khuedoan/blog/production/pvc-data.yaml"),
+            r#"apiVersion: v1
+kind: PersistentVolumeClaim
+metadata:
+  name: data
+spec:
+  resources:
+    requests:
+      storage: 1Gi
+"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("khuedoan/blog/production/sealedsecret-blog.yaml"),
+            r#"apiVersion: bitnami.com/v1alpha1
+kind: SealedSecret
+metadata:
+  name: blog
+  namespace: khuedoan-blog-production
+spec:
+  encryptedData:
+    TOKEN: AgB...
+"#,
+        )
+        .unwrap();
+        let target = AppTarget {
+            tenant: "khuedoan".to_string(),
+            project: "blog".to_string(),
+            environment: preview_environment(12),
+        };
+
+        assert!(write_preview_environment(&tmp, &target, "preview.example.com").unwrap());
+        assert!(!write_preview_environment(&tmp, &target, "preview.example.com").unwrap());
+
+        let inventory = scan_app_inventory(&tmp, "registry.example.com").unwrap();
+        let preview = inventory
+            .iter()
+            .find(|app| app.environment == "pr-12")
+            .unwrap();
+        assert_eq!(
+            preview.hostnames,
+            vec!["pr-12-blog-khuedoan.preview.example.com".to_string()]
+        );
+        assert_eq!(
+            preview.images,
+            vec!["registry.example.com/apps/khuedoan/blog:old".to_string()]
+        );
+        let namespace = fs::read_to_string(tmp.join("khuedoan/blog/pr-12/namespace.yaml")).unwrap();
+        assert!(namespace.contains("name: khuedoan-blog-pr-12"));
+        assert!(
+            !tmp.join("khuedoan/blog/pr-12/sealedsecret-blog.yaml")
+                .exists()
+        );
+        assert!(!tmp.join("khuedoan/blog/pr-12/pvc-data.yaml").exists());
+        assert!(tmp.join("khuedoan/blog/pr-12/configmap-blog.yaml").exists());
+        let deployment =
+            fs::read_to_string(tmp.join("khuedoan/blog/pr-12/deployment-blog.yaml")).unwrap();
+        assert!(deployment.contains("configMapRef:"));
+        assert!(!deployment.contains("secretRef:"));
+        assert!(!deployment.contains("persistentVolumeClaim:"));
+        assert!(deployment.contains("emptyDir: {}"));
+        assert!(deployment.contains("mountPath: /data"));
+
+        let missing = AppTarget {
+            project: "missing".to_string(),
+            ..target
+        };
+        assert!(write_preview_environment(&tmp, &missing, "preview.example.com").is_err());
+        let _ = fs::remove_dir_all(&tmp);
+    }
 }
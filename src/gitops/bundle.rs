@@ -20,6 +20,13 @@ pub(crate) struct AppsBundle {
     pub(crate) root_dir: PathBuf,
 }
 
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OciRepositoryAccess {
+    pub(crate) insecure: bool,
+    pub(crate) secret_ref: Option<String>,
+    pub(crate) cert_secret_ref: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct AppArtifact {
     pub(crate) dir: PathBuf,
@@ -33,6 +40,7 @@ pub(crate) fn write_apps_bundle(
     repository: &str,
     tag: &str,
     registry: &str,
+    access: &OciRepositoryAccess,
 ) -> anyhow::Result<AppsBundle> {
     fs::create_dir_all(output_dir)?;
 
@@ -60,7 +68,7 @@ pub(crate) fn write_apps_bundle(
     }
 
     let root_dir = output_dir.join("root");
-    write_root_bundle(&root_dir, &apps, tag, registry)?;
+    write_root_bundle(&root_dir, &apps, tag, registry, access)?;
 
     Ok(AppsBundle {
         apps,
@@ -131,9 +139,21 @@ fn write_root_bundle(
     apps: &[AppArtifact],
     tag: &str,
     registry: &str,
+    access: &OciRepositoryAccess,
 ) -> anyhow::Result<()> {
     fs::create_dir_all(output_dir)?;
 
+    let mut access_spec = String::new();
+    if access.insecure {
+        access_spec.push_str("  insecure: true\n");
+    }
+    if let Some(secret) = &access.secret_ref {
+        access_spec.push_str(&format!("  secretRef:\n    name: {secret}\n"));
+    }
+    if let Some(secret) = &access.cert_secret_ref {
+        access_spec.push_str(&format!("  certSecretRef:\n    name: {secret}\n"));
+    }
+
     for app in apps {
         write_file(
             &output_dir.join(format!("ocirepository-{}.yaml", app.name)),
@@ -146,8 +166,7 @@ metadata:
 spec:
   interval: {source_interval}
   url: oci://{registry}/{repository}
-  insecure: true
-  ref:
+{access_spec}  ref:
     tag: {tag}
 "#,
                 name = app.name,
@@ -1,9 +1,16 @@
-use super::manifest::{validate_app_manifest, write_yaml_manifest};
+use super::{
+    manifest::{validate_app_manifest, write_yaml_manifest},
+    merge::{
+        merge_autoscaler, merge_config_map, merge_deployment, merge_http_route, merge_postgres,
+        merge_pvc, merge_service, update_manifest,
+    },
+    secrets::{sealed_secret_manifest, sealed_values, update_sealed_secret},
+};
 use crate::api::{
-    CreateAppRequest, CreateDeployment, CreateHttpRoute, CreatePostgres, CreateService,
-    CreateVolume, KeyValue,
+    ContainerResources, CreateAppRequest, CreateAutoscale, CreateDeployment, CreateHttpRoute,
+    CreatePostgres, CreateProbe, CreateSecurityContext, CreateService, CreateVolume, KeyValue,
 };
-use anyhow::Context;
+use anyhow::{Context, anyhow};
 use serde_json::{Value as JsonValue, json};
 use std::path::Path;
 
@@ -29,59 +36,122 @@ fn write_app_manifests(
     registry: &str,
     include_namespace: bool,
 ) -> anyhow::Result<usize> {
+    if !request.secrets.is_empty() {
+        return Err(anyhow!(
+            "plaintext secrets must be sealed before they are written to GitOps"
+        ));
+    }
+
+    let merge = !include_namespace;
     let mut count = 0;
     if include_namespace {
         write_json_manifest(&app_dir.join("namespace.yaml"), namespace_manifest(request))?;
         count += 1;
     }
 
-    if let Some(deployment) = &request.deployment {
+    let deployment_path = app_dir.join(format!("deployment-{}.yaml", request.project));
+    if merge
+        && request
+            .deployment
+            .as_ref()
+            .is_some_and(|deployment| deployment.replicas.is_some())
+        && app_dir
+            .join(format!("horizontalpodautoscaler-{}.yaml", request.project))
+            .exists()
+    {
+        return Err(anyhow!(
+            "{} is autoscaled; change the autoscaler or remove it before setting replicas",
+            request.project
+        ));
+    }
+    if merge && deployment_path.exists() {
+        let image = request
+            .deployment
+            .as_ref()
+            .and_then(|deployment| deployment_image(deployment, registry));
+        update_manifest(&deployment_path, |manifest| {
+            merge_deployment(
+                manifest,
+                request,
+                request.deployment.as_ref(),
+                image.as_deref(),
+            )
+        })?;
+        if request.deployment.is_some() {
+            count += 1;
+        }
+    } else if let Some(deployment) = &request.deployment {
         write_json_manifest(
-            &app_dir.join(format!("deployment-{}.yaml", request.project)),
+            &deployment_path,
             deployment_manifest(request, deployment, registry)?,
         )?;
         count += 1;
     }
     if let Some(service) = &request.service {
-        write_json_manifest(
-            &app_dir.join(format!("service-{}.yaml", request.project)),
-            service_manifest(request, service),
-        )?;
+        let path = app_dir.join(format!("service-{}.yaml", request.project));
+        if merge && path.exists() {
+            update_manifest(&path, |manifest| merge_service(manifest, service))?;
+        } else {
+            write_json_manifest(&path, service_manifest(request, service))?;
+        }
         count += 1;
     }
     if let Some(route) = &request.http_route {
-        write_json_manifest(
-            &app_dir.join(format!("httproute-{}.yaml", request.project)),
-            http_route_manifest(request, route),
-        )?;
+        let path = app_dir.join(format!("httproute-{}.yaml", request.project));
+        if merge && path.exists() {
+            update_manifest(&path, |manifest| merge_http_route(manifest, request, route))?;
+        } else {
+            write_json_manifest(&path, http_route_manifest(request, route))?;
+        }
         count += 1;
     }
     if !request.config.is_empty() {
-        write_json_manifest(
-            &app_dir.join(format!("configmap-{}.yaml", request.project)),
-            config_map_manifest(request, &request.config),
-        )?;
+        let path = app_dir.join(format!("configmap-{}.yaml", request.project));
+        if merge && path.exists() {
+            update_manifest(&path, |manifest| {
+                merge_config_map(manifest, &request.config)
+            })?;
+        } else {
+            write_json_manifest(&path, config_map_manifest(request, &request.config))?;
+        }
         count += 1;
     }
-    if !request.secrets.is_empty() {
-        write_json_manifest(
-            &app_dir.join(format!("secret-{}.yaml", request.project)),
-            secret_manifest(request, &request.secrets),
-        )?;
+    if !request.sealed_secrets.is_empty() {
+        if merge {
+            update_sealed_secret(app_dir, &request.project, &request.sealed_secrets, &[])?;
+        } else {
+            write_json_manifest(
+                &app_dir.join(format!("sealedsecret-{}.yaml", request.project)),
+                sealed_secret_manifest(&request.project, &sealed_values(&request.sealed_secrets)),
+            )?;
+        }
         count += 1;
     }
     for volume in &request.volumes {
-        write_json_manifest(
-            &app_dir.join(format!("persistentvolumeclaim-{}.yaml", volume.name)),
-            pvc_manifest(volume),
-        )?;
+        let path = app_dir.join(format!("persistentvolumeclaim-{}.yaml", volume.name));
+        if merge && path.exists() {
+            update_manifest(&path, |manifest| merge_pvc(manifest, volume))?;
+        } else {
+            write_json_manifest(&path, pvc_manifest(volume))?;
+        }
         count += 1;
     }
     if let Some(postgres) = &request.postgres {
-        write_json_manifest(
-            &app_dir.join(format!("cluster-{}-postgres.yaml", request.project)),
-            postgres_manifest(request, postgres),
-        )?;
+        let path = app_dir.join(format!("cluster-{}-postgres.yaml", request.project));
+        if merge && path.exists() {
+            update_manifest(&path, |manifest| merge_postgres(manifest, postgres))?;
+        } else {
+            write_json_manifest(&path, postgres_manifest(request, postgres))?;
+        }
+        count += 1;
+    }
+    if let Some(autoscale) = &request.autoscale {
+        let path = app_dir.join(format!("horizontalpodautoscaler-{}.yaml", request.project));
+        if merge && path.exists() {
+            update_manifest(&path, |manifest| merge_autoscaler(manifest, autoscale))?;
+        } else {
+            write_json_manifest(&path, autoscaler_manifest(request, autoscale))?;
+        }
         count += 1;
     }
 
@@ -104,34 +174,50 @@ fn deployment_manifest(
     deployment: &CreateDeployment,
     registry: &str,
 ) -> anyhow::Result<JsonValue> {
-    let image = deployment
-        .image
-        .clone()
-        .or_else(|| {
-            deployment
-                .source_repo
-                .as_ref()
-                .map(|repo| format!("{}/apps/{repo}:latest", registry.trim_end_matches('/')))
-        })
+    let image = deployment_image(deployment, registry)
         .context("deployment needs either an image or a source repo")?;
     let label = json!({ "app.kubernetes.io/name": &request.project });
     let mut container = json!({
         "name": &request.project,
         "image": image,
     });
+    if !deployment.command.is_empty() {
+        container["command"] = json!(&deployment.command);
+    }
+    if !deployment.args.is_empty() {
+        container["args"] = json!(&deployment.args);
+    }
     if let Some(port) = deployment.port {
         container["ports"] = json!([{ "containerPort": port, "name": "http" }]);
     }
-    if !request.config.is_empty() || !request.secrets.is_empty() {
+    if !deployment.env.is_empty() {
+        container["env"] = json!(
+            deployment
+                .env
+                .iter()
+                .map(|item| json!({ "name": &item.key, "value": &item.value }))
+                .collect::<Vec<_>>()
+        );
+    }
+    if !request.config.is_empty() || !request.sealed_secrets.is_empty() {
         let mut env_from = Vec::new();
         if !request.config.is_empty() {
             env_from.push(json!({ "configMapRef": { "name": &request.project } }));
         }
-        if !request.secrets.is_empty() {
+        if !request.sealed_secrets.is_empty() {
             env_from.push(json!({ "secretRef": { "name": &request.project } }));
         }
         container["envFrom"] = json!(env_from);
     }
+    if let Some(resources) = &deployment.resources {
+        container["resources"] = resources_json(resources);
+    }
+    for (name, probe) in deployment.probes.iter() {
+        container[format!("{name}Probe")] = probe_json(probe);
+    }
+    if let Some(security_context) = &deployment.security_context {
+        container["securityContext"] = security_context_json(security_context);
+    }
     if !request.volumes.is_empty() {
         container["volumeMounts"] = json!(
             request
@@ -147,7 +233,7 @@ fn deployment_manifest(
         "kind": "Deployment",
         "metadata": { "name": &request.project },
         "spec": {
-            "replicas": deployment.replicas,
+            "replicas": deployment.replicas.unwrap_or(1),
             "selector": { "matchLabels": label },
             "template": {
                 "metadata": {
@@ -157,6 +243,11 @@ fn deployment_manifest(
             },
         },
     });
+    if request.autoscale.is_some()
+        && let Some(spec) = manifest["spec"].as_object_mut()
+    {
+        spec.remove("replicas");
+    }
     if !request.volumes.is_empty() {
         manifest["spec"]["template"]["spec"]["volumes"] = json!(
             request
@@ -173,6 +264,15 @@ fn deployment_manifest(
     Ok(manifest)
 }
 
+fn deployment_image(deployment: &CreateDeployment, registry: &str) -> Option<String> {
+    deployment.image.clone().or_else(|| {
+        deployment
+            .source_repo
+            .as_ref()
+            .map(|repo| format!("{}/apps/{repo}:latest", registry.trim_end_matches('/')))
+    })
+}
+
 fn service_manifest(request: &CreateAppRequest, service: &CreateService) -> JsonValue {
     json!({
         "apiVersion": "v1",
@@ -219,23 +319,6 @@ fn config_map_manifest(request: &CreateAppRequest, values: &[KeyValue]) -> JsonV
     })
 }
 
-fn secret_manifest(request: &CreateAppRequest, values: &[KeyValue]) -> JsonValue {
-    json!({
-        "apiVersion": "v1",
-        "kind": "Secret",
-        "metadata": {
-            "name": &request.project,
-            "annotations": {
-                "vault.security.banzaicloud.io/vault-addr": "http://vault.vault.svc.cluster.local:8200",
-                "vault.security.banzaicloud.io/vault-path": "kubernetes",
-                "vault.security.banzaicloud.io/vault-role": "default",
-            },
-        },
-        "type": "Opaque",
-        "stringData": key_values(values),
-    })
-}
-
 fn pvc_manifest(volume: &CreateVolume) -> JsonValue {
     json!({
         "apiVersion": "v1",
@@ -268,6 +351,92 @@ fn postgres_manifest(request: &CreateAppRequest, postgres: &CreatePostgres) -> J
     })
 }
 
+fn autoscaler_manifest(request: &CreateAppRequest, autoscale: &CreateAutoscale) -> JsonValue {
+    json!({
+        "apiVersion": "autoscaling/v2",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": { "name": &request.project },
+        "spec": {
+            "scaleTargetRef": {
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "name": &request.project,
+            },
+            "minReplicas": autoscale.min_replicas,
+            "maxReplicas": autoscale.max_replicas,
+            "metrics": autoscaler_metrics(autoscale),
+        },
+    })
+}
+
+pub(super) fn autoscaler_metrics(autoscale: &CreateAutoscale) -> JsonValue {
+    json!([{
+        "type": "Resource",
+        "resource": {
+            "name": "cpu",
+            "target": {
+                "type": "Utilization",
+                "averageUtilization": autoscale.cpu_utilization,
+            },
+        },
+    }])
+}
+
+pub(super) fn resources_json(resources: &ContainerResources) -> JsonValue {
+    let mut value = json!({});
+    for (section, name, quantity) in [
+        ("requests", "cpu", &resources.cpu_request),
+        ("requests", "memory", &resources.memory_request),
+        ("limits", "cpu", &resources.cpu_limit),
+        ("limits", "memory", &resources.memory_limit),
+    ] {
+        if let Some(quantity) = quantity {
+            value[section][name] = json!(quantity);
+        }
+    }
+    value
+}
+
+pub(super) fn probe_json(probe: &CreateProbe) -> JsonValue {
+    let mut value = if let Some(path) = &probe.http_path {
+        json!({ "httpGet": { "path": path, "port": "http" } })
+    } else if let Some(port) = probe.tcp_port {
+        json!({ "tcpSocket": { "port": port } })
+    } else {
+        json!({ "exec": { "command": &probe.exec } })
+    };
+    for (name, setting) in [
+        ("initialDelaySeconds", probe.initial_delay_seconds),
+        ("periodSeconds", probe.period_seconds),
+        ("failureThreshold", probe.failure_threshold),
+    ] {
+        if let Some(setting) = setting {
+            value[name] = json!(setting);
+        }
+    }
+    value
+}
+
+pub(super) fn security_context_json(security_context: &CreateSecurityContext) -> JsonValue {
+    let mut value = json!({});
+    if let Some(user) = security_context.run_as_user {
+        value["runAsUser"] = json!(user);
+    }
+    if let Some(group) = security_context.run_as_group {
+        value["runAsGroup"] = json!(group);
+    }
+    if security_context.run_as_non_root {
+        value["runAsNonRoot"] = json!(true);
+    }
+    if security_context.read_only_root_filesystem {
+        value["readOnlyRootFilesystem"] = json!(true);
+    }
+    if let Some(allow) = security_context.allow_privilege_escalation {
+        value["allowPrivilegeEscalation"] = json!(allow);
+    }
+    value
+}
+
 fn key_values(values: &[KeyValue]) -> serde_json::Map<String, JsonValue> {
     values
         .iter()
@@ -275,7 +444,7 @@ fn key_values(values: &[KeyValue]) -> serde_json::Map<String, JsonValue> {
         .collect()
 }
 
-fn write_json_manifest(path: &Path, value: JsonValue) -> anyhow::Result<()> {
+pub(super) fn write_json_manifest(path: &Path, value: JsonValue) -> anyhow::Result<()> {
     let manifest = yaml_serde::to_value(value)?;
     validate_app_manifest(path, &manifest)?;
     write_yaml_manifest(path, &manifest)
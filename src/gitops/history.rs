@@ -0,0 +1,164 @@
+use super::{AppTarget, inventory::split_image_reference};
+use crate::api::DeploymentChange;
+use anyhow::anyhow;
+use std::collections::BTreeSet;
+
+const COMMIT_MARKER: &str = "\u{1e}commit ";
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageChange {
+    pub commit_sha: String,
+    pub author: String,
+    pub timestamp: String,
+    pub subject: String,
+    pub target: AppTarget,
+    pub image: String,
+}
+
+pub(crate) fn image_history_args(pathspec: &str) -> Vec<String> {
+    vec![
+        "log".to_string(),
+        "--format=%x1ecommit %H%x1f%an%x1f%aI%x1f%s".to_string(),
+        "--patch".to_string(),
+        "--unified=0".to_string(),
+        "--no-color".to_string(),
+        "--no-renames".to_string(),
+        "--".to_string(),
+        pathspec.to_string(),
+    ]
+}
+
+pub fn parse_image_history(log: &str) -> Vec<ImageChange> {
+    let mut changes = Vec::new();
+    let mut commit: Option<(String, String, String, String)> = None;
+    let mut target: Option<AppTarget> = None;
+
+    for line in log.lines() {
+        if let Some(header) = line.strip_prefix(COMMIT_MARKER) {
+            let mut fields = header.splitn(4, FIELD_SEPARATOR);
+            commit = Some((
+                fields.next().unwrap_or_default().to_string(),
+                fields.next().unwrap_or_default().to_string(),
+                fields.next().unwrap_or_default().to_string(),
+                fields.next().unwrap_or_default().to_string(),
+            ));
+            target = None;
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("+++ ") {
+            target = path.strip_prefix("b/").and_then(app_target_from_path);
+            continue;
+        }
+
+        let (Some((commit_sha, author, timestamp, subject)), Some(target)) = (&commit, &target)
+        else {
+            continue;
+        };
+        let Some(image) = line.strip_prefix('+').and_then(image_from_yaml_line) else {
+            continue;
+        };
+        changes.push(ImageChange {
+            commit_sha: commit_sha.clone(),
+            author: author.clone(),
+            timestamp: timestamp.clone(),
+            subject: subject.clone(),
+            target: target.clone(),
+            image,
+        });
+    }
+
+    changes
+}
+
+pub fn rollback_candidates(
+    history: &[ImageChange],
+    target: &AppTarget,
+    current_images: &BTreeSet<String>,
+) -> Vec<ImageChange> {
+    let mut seen = BTreeSet::new();
+    history
+        .iter()
+        .filter(|change| &change.target == target)
+        .filter(|change| !current_images.contains(&change.image))
+        .filter(|change| seen.insert(change.image.clone()))
+        .cloned()
+        .collect()
+}
+
+pub fn select_rollback_image(
+    candidates: &[ImageChange],
+    requested: Option<&str>,
+) -> anyhow::Result<ImageChange> {
+    let Some(requested) = requested else {
+        return candidates
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("no previously deployed image to roll back to"));
+    };
+
+    candidates
+        .iter()
+        .find(|candidate| {
+            candidate.image == requested
+                || split_image_reference(&candidate.image).1 == Some(requested)
+        })
+        .cloned()
+        .ok_or_else(|| anyhow!("{requested} was not previously deployed to this environment"))
+}
+
+pub fn update_commit_source_repo(subject: &str) -> Option<String> {
+    let (source_repo, _) = subject
+        .strip_prefix("chore(apps): update ")?
+        .rsplit_once(" image for ")?;
+    if source_repo.is_empty() {
+        None
+    } else {
+        Some(source_repo.to_string())
+    }
+}
+
+pub fn commit_change(subject: &str) -> DeploymentChange {
+    if update_commit_source_repo(subject).is_some() {
+        DeploymentChange::Update
+    } else if subject.starts_with("chore(apps): roll back ") {
+        DeploymentChange::Rollback
+    } else if subject.starts_with("feat(apps): create ")
+        || subject.starts_with("chore(apps): create ")
+    {
+        DeploymentChange::Create
+    } else if subject.starts_with("feat(apps): add components to ") {
+        DeploymentChange::Add
+    } else {
+        DeploymentChange::Other
+    }
+}
+
+fn app_target_from_path(path: &str) -> Option<AppTarget> {
+    let mut parts = path.split('/');
+    if parts.next()? != "apps" {
+        return None;
+    }
+    let tenant = parts.next()?;
+    let project = parts.next()?;
+    let environment = parts.next()?;
+    parts.next()?;
+
+    Some(AppTarget {
+        tenant: tenant.to_string(),
+        project: project.to_string(),
+        environment: environment.to_string(),
+    })
+}
+
+fn image_from_yaml_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    let line = line.strip_prefix("- ").unwrap_or(line).trim_start();
+    let image = line.strip_prefix("image:")?.trim();
+    let image = image.trim_matches(|ch| ch == '"' || ch == '\'');
+    if image.is_empty() {
+        None
+    } else {
+        Some(image.to_string())
+    }
+}
@@ -1,11 +1,15 @@
 use super::{
-    AppInventory, AppSourceTarget, AppTarget,
+    AppInventory, AppSourceTarget, AppTarget, ImageVersion,
     manifest::{
         child_dirs, is_kustomization, is_yaml_file, read_app_manifest, required_mapping,
         required_string,
     },
 };
-use std::{collections::BTreeSet, fs, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
 use yaml_serde::Value as YamlValue;
 
 const SOURCE_IMAGE_REPOSITORY: &str = "apps";
@@ -69,7 +73,39 @@ fn collect_image_references<'a>(node: &'a YamlValue, images: &mut Vec<&'a str>)
     }
 }
 
-pub(crate) fn source_repo_from_image(registry: &str, image: &str) -> Option<String> {
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourceImage {
+    pub owner: String,
+    pub repo: String,
+    pub component: Option<String>,
+}
+
+impl SourceImage {
+    pub fn new(owner: &str, repo: &str, component: Option<&str>) -> Self {
+        Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            component: component.map(ToString::to_string),
+        }
+    }
+
+    pub fn source_repo(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
+    pub fn image_owner(&self) -> String {
+        match &self.component {
+            Some(_) => format!("{SOURCE_IMAGE_REPOSITORY}/{}/{}", self.owner, self.repo),
+            None => format!("{SOURCE_IMAGE_REPOSITORY}/{}", self.owner),
+        }
+    }
+
+    pub fn image_repository(&self) -> String {
+        self.component.clone().unwrap_or_else(|| self.repo.clone())
+    }
+}
+
+pub(crate) fn source_image_from_reference(registry: &str, image: &str) -> Option<SourceImage> {
     let prefix = format!(
         "{}/{SOURCE_IMAGE_REPOSITORY}/",
         registry.trim_end_matches('/')
@@ -79,20 +115,89 @@ pub(crate) fn source_repo_from_image(registry: &str, image: &str) -> Option<Stri
     let mut parts = repository.split('/');
     let owner = parts.next().filter(|part| !part.is_empty())?;
     let repo = parts.next().filter(|part| !part.is_empty())?;
+    let component = match parts.next() {
+        Some(component) if !component.is_empty() => Some(component),
+        Some(_) => return None,
+        None => None,
+    };
     if parts.next().is_some() {
         return None;
     }
 
-    Some(format!("{owner}/{repo}"))
+    Some(SourceImage::new(owner, repo, component))
+}
+
+pub(crate) fn source_repo_from_image(registry: &str, image: &str) -> Option<String> {
+    source_image_from_reference(registry, image).map(|image| image.source_repo())
+}
+
+pub(crate) fn deployed_component_tags(
+    apps_dir: &Path,
+    registry: &str,
+    source_repo: &str,
+    environment: &str,
+) -> anyhow::Result<BTreeMap<String, BTreeSet<String>>> {
+    let mut tags = BTreeMap::<String, BTreeSet<String>>::new();
+    for (_, tenant_dir) in child_dirs(apps_dir)? {
+        for (_, project_dir) in child_dirs(&tenant_dir)? {
+            let environment_dir = project_dir.join(environment);
+            if !environment_dir.is_dir() {
+                continue;
+            }
+
+            for image in scan_environment_images(&environment_dir)? {
+                let Some(source_image) = source_image_from_reference(registry, &image)
+                    .filter(|source_image| source_image.source_repo() == source_repo)
+                else {
+                    continue;
+                };
+                let (_, Some(tag), _) = split_image_reference(&image) else {
+                    continue;
+                };
+                if let Some(component) = source_image.component {
+                    tags.entry(component).or_default().insert(tag.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(tags)
 }
 
 fn image_repository_path(image: &str) -> &str {
-    let image = image
+    split_image_reference(image).0
+}
+
+pub(crate) fn split_image_reference(image: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (image, digest) = image
         .split_once('@')
-        .map_or(image, |(repository, _digest)| repository);
-    image
-        .split_once(':')
-        .map_or(image, |(repository, _tag)| repository)
+        .map_or((image, None), |(image, digest)| (image, Some(digest)));
+    let name_start = image.rfind('/').map_or(0, |index| index + 1);
+    match image[name_start..].rfind(':') {
+        Some(index) => (
+            &image[..name_start + index],
+            Some(&image[name_start + index + 1..]),
+            digest,
+        ),
+        None => (image, None, digest),
+    }
+}
+
+pub(crate) fn scan_environment_images(environment_dir: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let mut images = BTreeSet::new();
+    for entry in fs::read_dir(environment_dir)? {
+        let path = entry?.path();
+        if !is_yaml_file(&path) || is_kustomization(&path) {
+            continue;
+        }
+
+        let manifest = read_app_manifest(&path)?;
+        let mut image_refs = Vec::new();
+        collect_image_references(&manifest, &mut image_refs);
+        images.extend(image_refs.into_iter().map(ToString::to_string));
+    }
+
+    Ok(images)
 }
 
 pub fn scan_app_inventory(apps_dir: &Path, registry: &str) -> anyhow::Result<Vec<AppInventory>> {
@@ -104,6 +209,7 @@ pub fn scan_app_inventory(apps_dir: &Path, registry: &str) -> anyhow::Result<Vec
                 let mut resources = BTreeSet::new();
                 let mut hostnames = BTreeSet::new();
                 let mut images = BTreeSet::new();
+                let mut image_versions = BTreeSet::new();
                 let mut source_repos = BTreeSet::new();
 
                 for entry in fs::read_dir(&environment_dir)? {
@@ -124,6 +230,12 @@ pub fn scan_app_inventory(apps_dir: &Path, registry: &str) -> anyhow::Result<Vec
                     collect_image_references(&manifest, &mut image_refs);
                     for image in image_refs {
                         images.insert(image.to_string());
+                        let (repository, tag, digest) = split_image_reference(image);
+                        image_versions.insert(ImageVersion {
+                            repository: repository.to_string(),
+                            tag: tag.map(ToString::to_string),
+                            digest: digest.map(ToString::to_string),
+                        });
                         if let Some(source_repo) = source_repo_from_image(registry, image) {
                             source_repos.insert(source_repo);
                         }
@@ -138,6 +250,7 @@ pub fn scan_app_inventory(apps_dir: &Path, registry: &str) -> anyhow::Result<Vec
                         resources: resources.into_iter().collect(),
                         hostnames: hostnames.into_iter().collect(),
                         images: images.into_iter().collect(),
+                        image_versions: image_versions.into_iter().collect(),
                         source_repos: source_repos.into_iter().collect(),
                     });
                 }
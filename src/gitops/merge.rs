@@ -0,0 +1,379 @@
+use super::{
+    create::{autoscaler_metrics, probe_json, resources_json, security_context_json},
+    inventory::split_image_reference,
+    manifest::{read_app_manifest, validate_app_manifest, write_yaml_manifest},
+};
+use crate::api::{
+    CreateAppRequest, CreateAutoscale, CreateDeployment, CreateHttpRoute, CreatePostgres,
+    CreateService, CreateVolume, KeyValue,
+};
+use anyhow::{Context, anyhow};
+use serde_json::{Value as JsonValue, json};
+use std::path::Path;
+use yaml_serde::{Mapping, Value as YamlValue};
+
+pub(super) fn update_manifest(
+    path: &Path,
+    update: impl FnOnce(&mut YamlValue) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut manifest = read_app_manifest(path)?;
+    let original = manifest.clone();
+    update(&mut manifest).with_context(|| format!("failed to update {}", path.display()))?;
+    if manifest == original {
+        return Ok(());
+    }
+    validate_app_manifest(path, &manifest)?;
+    write_yaml_manifest(path, &manifest)
+}
+
+pub(super) fn merge_deployment(
+    manifest: &mut YamlValue,
+    request: &CreateAppRequest,
+    deployment: Option<&CreateDeployment>,
+    image: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(replicas) = deployment.and_then(|deployment| deployment.replicas) {
+        *field(manifest, &["spec", "replicas"])? = YamlValue::from(replicas);
+    }
+    if request.autoscale.is_some() {
+        mapping(manifest, &["spec"])?.shift_remove("replicas");
+    }
+
+    let container = app_container(manifest, &request.project)?;
+
+    if let Some(image) = image {
+        let current = container.get("image").and_then(YamlValue::as_str);
+        let same_source = deployment.is_some_and(|deployment| deployment.image.is_none())
+            && current.is_some_and(|current| {
+                split_image_reference(current).0 == split_image_reference(image).0
+            });
+        if !same_source {
+            *field(container, &["image"])? = YamlValue::from(image);
+        }
+    }
+    if let Some(resources) = deployment.and_then(|deployment| deployment.resources.as_ref()) {
+        let resources = yaml_serde::to_value(resources_json(resources))?;
+        for (section, values) in resources.as_mapping().into_iter().flatten() {
+            let section = section
+                .as_str()
+                .ok_or_else(|| anyhow!("resource section must be a string"))?;
+            for (name, quantity) in values.as_mapping().into_iter().flatten() {
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| anyhow!("resource name must be a string"))?;
+                *field(container, &["resources", section, name])? = quantity.clone();
+            }
+        }
+    }
+    if let Some(deployment) = deployment {
+        if !deployment.command.is_empty() {
+            *field(container, &["command"])? = yaml_serde::to_value(&deployment.command)?;
+        }
+        if !deployment.args.is_empty() {
+            *field(container, &["args"])? = yaml_serde::to_value(&deployment.args)?;
+        }
+        for item in &deployment.env {
+            replace_named(
+                sequence(container, &["env"])?,
+                "name",
+                json!({ "name": &item.key, "value": &item.value }),
+            )?;
+        }
+        for (name, probe) in deployment.probes.iter() {
+            *field(container, &[&format!("{name}Probe")])? =
+                yaml_serde::to_value(probe_json(probe))?;
+        }
+        if let Some(security_context) = &deployment.security_context {
+            let security_context = yaml_serde::to_value(security_context_json(security_context))?;
+            for (name, value) in security_context.as_mapping().into_iter().flatten() {
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| anyhow!("security context field must be a string"))?;
+                *field(container, &["securityContext", name])? = value.clone();
+            }
+        }
+    }
+    if let Some(port) = deployment.and_then(|deployment| deployment.port) {
+        merge_named(
+            sequence(container, &["ports"])?,
+            "name",
+            json!({ "name": "http", "containerPort": port }),
+        )?;
+    }
+    if deployment.is_some_and(CreateDeployment::has_http_probe) && !has_http_port(container) {
+        return Err(anyhow!(
+            "HTTP probes need an http container port; set a deployment port"
+        ));
+    }
+    if !request.config.is_empty() {
+        push_unique(
+            sequence(container, &["envFrom"])?,
+            json!({ "configMapRef": { "name": &request.project } }),
+        )?;
+    }
+    for volume in &request.volumes {
+        merge_named(
+            sequence(container, &["volumeMounts"])?,
+            "name",
+            json!({ "name": &volume.name, "mountPath": &volume.mount_path }),
+        )?;
+    }
+
+    for volume in &request.volumes {
+        merge_named(
+            sequence(manifest, &["spec", "template", "spec", "volumes"])?,
+            "name",
+            json!({
+                "name": &volume.name,
+                "persistentVolumeClaim": { "claimName": &volume.name },
+            }),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn set_deployment_env_from(
+    manifest: &mut YamlValue,
+    project: &str,
+    source: JsonValue,
+    enabled: bool,
+) -> anyhow::Result<()> {
+    let container = app_container(manifest, project)?;
+    if enabled {
+        push_unique(sequence(container, &["envFrom"])?, source)
+    } else {
+        let source = yaml_serde::to_value(source)?;
+        retain(container, "envFrom", |item| item != &source);
+        Ok(())
+    }
+}
+
+pub(super) fn remove_deployment_volume(
+    manifest: &mut YamlValue,
+    project: &str,
+    name: &str,
+) -> anyhow::Result<()> {
+    let other_volume =
+        |item: &YamlValue| item.get("name").and_then(YamlValue::as_str) != Some(name);
+    retain(
+        app_container(manifest, project)?,
+        "volumeMounts",
+        other_volume,
+    );
+    retain(
+        field(manifest, &["spec", "template", "spec"])?,
+        "volumes",
+        other_volume,
+    );
+    Ok(())
+}
+
+pub(super) fn replace_claim_volumes(manifest: &mut YamlValue) -> anyhow::Result<()> {
+    let Some(volumes) = field(manifest, &["spec", "template", "spec"])?
+        .get_mut("volumes")
+        .and_then(YamlValue::as_sequence_mut)
+    else {
+        return Ok(());
+    };
+    for volume in volumes {
+        if let Some(volume) = volume.as_mapping_mut()
+            && volume.shift_remove("persistentVolumeClaim").is_some()
+        {
+            volume.insert(
+                YamlValue::from("emptyDir"),
+                YamlValue::Mapping(Mapping::new()),
+            );
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn merge_autoscaler(
+    manifest: &mut YamlValue,
+    autoscale: &CreateAutoscale,
+) -> anyhow::Result<()> {
+    *field(manifest, &["spec", "minReplicas"])? = YamlValue::from(autoscale.min_replicas);
+    *field(manifest, &["spec", "maxReplicas"])? = YamlValue::from(autoscale.max_replicas);
+    *field(manifest, &["spec", "metrics"])? = yaml_serde::to_value(autoscaler_metrics(autoscale))?;
+    Ok(())
+}
+
+pub(super) fn merge_service(
+    manifest: &mut YamlValue,
+    service: &CreateService,
+) -> anyhow::Result<()> {
+    merge_named(
+        sequence(manifest, &["spec", "ports"])?,
+        "name",
+        json!({ "name": "http", "port": service.port, "targetPort": "http" }),
+    )
+}
+
+pub(super) fn merge_http_route(
+    manifest: &mut YamlValue,
+    request: &CreateAppRequest,
+    route: &CreateHttpRoute,
+) -> anyhow::Result<()> {
+    push_unique(
+        sequence(manifest, &["spec", "hostnames"])?,
+        json!(&route.hostname),
+    )?;
+
+    let mut found = false;
+    for rule in sequence(manifest, &["spec", "rules"])? {
+        for backend in sequence(rule, &["backendRefs"])? {
+            if backend.get("name").and_then(YamlValue::as_str) == Some(request.project.as_str()) {
+                *field(backend, &["port"])? = YamlValue::from(route.port);
+                found = true;
+            }
+        }
+    }
+    if !found {
+        sequence(manifest, &["spec", "rules"])?.push(yaml_serde::to_value(json!({
+            "backendRefs": [{ "name": &request.project, "port": route.port }],
+            "matches": [{ "path": { "type": "PathPrefix", "value": "/" } }],
+        }))?);
+    }
+
+    Ok(())
+}
+
+pub(super) fn merge_config_map(
+    manifest: &mut YamlValue,
+    values: &[KeyValue],
+) -> anyhow::Result<()> {
+    let data = mapping(manifest, &["data"])?;
+    for value in values {
+        data.insert(
+            YamlValue::from(value.key.as_str()),
+            YamlValue::from(value.value.as_str()),
+        );
+    }
+    Ok(())
+}
+
+pub(super) fn merge_pvc(manifest: &mut YamlValue, volume: &CreateVolume) -> anyhow::Result<()> {
+    *field(manifest, &["spec", "resources", "requests", "storage"])? =
+        YamlValue::from(volume.size.as_str());
+    Ok(())
+}
+
+pub(super) fn merge_postgres(
+    manifest: &mut YamlValue,
+    postgres: &CreatePostgres,
+) -> anyhow::Result<()> {
+    *field(manifest, &["spec", "storage", "size"])? = YamlValue::from(postgres.size.as_str());
+    Ok(())
+}
+
+fn has_http_port(container: &YamlValue) -> bool {
+    container
+        .get("ports")
+        .and_then(YamlValue::as_sequence)
+        .is_some_and(|ports| {
+            ports
+                .iter()
+                .any(|port| port.get("name").and_then(YamlValue::as_str) == Some("http"))
+        })
+}
+
+fn app_container<'a>(
+    manifest: &'a mut YamlValue,
+    project: &str,
+) -> anyhow::Result<&'a mut YamlValue> {
+    let containers = sequence(manifest, &["spec", "template", "spec", "containers"])?;
+    let index = containers
+        .iter()
+        .position(|container| container.get("name").and_then(YamlValue::as_str) == Some(project))
+        .or((!containers.is_empty()).then_some(0))
+        .ok_or_else(|| anyhow!("Deployment has no containers"))?;
+    Ok(&mut containers[index])
+}
+
+fn field<'a>(value: &'a mut YamlValue, keys: &[&str]) -> anyhow::Result<&'a mut YamlValue> {
+    keys.iter().try_fold(value, |value, key| {
+        if value.is_null() {
+            *value = YamlValue::Mapping(Mapping::new());
+        }
+        let YamlValue::Mapping(mapping) = value else {
+            return Err(anyhow!("parent of {key} must be a mapping"));
+        };
+        Ok(mapping
+            .entry(YamlValue::from(*key))
+            .or_insert(YamlValue::Null))
+    })
+}
+
+fn mapping<'a>(value: &'a mut YamlValue, keys: &[&str]) -> anyhow::Result<&'a mut Mapping> {
+    let value = field(value, keys)?;
+    if value.is_null() {
+        *value = YamlValue::Mapping(Mapping::new());
+    }
+    value
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("{} must be a mapping", keys.join(".")))
+}
+
+fn sequence<'a>(value: &'a mut YamlValue, keys: &[&str]) -> anyhow::Result<&'a mut Vec<YamlValue>> {
+    let value = field(value, keys)?;
+    if value.is_null() {
+        *value = YamlValue::Sequence(Vec::new());
+    }
+    value
+        .as_sequence_mut()
+        .ok_or_else(|| anyhow!("{} must be a list", keys.join(".")))
+}
+
+fn merge_named(items: &mut Vec<YamlValue>, key: &str, item: JsonValue) -> anyhow::Result<()> {
+    let YamlValue::Mapping(item) = yaml_serde::to_value(item)? else {
+        return Err(anyhow!("list item must be a mapping"));
+    };
+    let name = item.get(key).cloned();
+    match items
+        .iter_mut()
+        .find(|existing| existing.get(key) == name.as_ref())
+        .and_then(YamlValue::as_mapping_mut)
+    {
+        Some(existing) => {
+            for (field, value) in item {
+                existing.insert(field, value);
+            }
+        }
+        None => items.push(YamlValue::Mapping(item)),
+    }
+    Ok(())
+}
+
+fn replace_named(items: &mut Vec<YamlValue>, key: &str, item: JsonValue) -> anyhow::Result<()> {
+    let item = yaml_serde::to_value(item)?;
+    match items
+        .iter_mut()
+        .find(|existing| existing.get(key) == item.get(key))
+    {
+        Some(existing) => *existing = item,
+        None => items.push(item),
+    }
+    Ok(())
+}
+
+fn push_unique(items: &mut Vec<YamlValue>, item: JsonValue) -> anyhow::Result<()> {
+    let item = yaml_serde::to_value(item)?;
+    if !items.contains(&item) {
+        items.push(item);
+    }
+    Ok(())
+}
+
+fn retain(value: &mut YamlValue, key: &str, keep: impl FnMut(&YamlValue) -> bool) {
+    let Some(mapping) = value.as_mapping_mut() else {
+        return;
+    };
+    let Some(items) = mapping.get_mut(key).and_then(YamlValue::as_sequence_mut) else {
+        return;
+    };
+    items.retain(keep);
+    if items.is_empty() {
+        mapping.shift_remove(key);
+    }
+}
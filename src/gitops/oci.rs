@@ -0,0 +1,424 @@
+use anyhow::{Context, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use flate2::{Compression, write::GzEncoder};
+use reqwest::{Certificate, Client, Method, RequestBuilder, Response, StatusCode, Url, header};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+use tokio::sync::Mutex;
+use tracing::info;
+
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const FLUX_CONFIG_MEDIA_TYPE: &str = "application/vnd.cncf.flux.config.v1+json";
+const FLUX_CONTENT_MEDIA_TYPE: &str = "application/vnd.cncf.flux.content.v1.tar+gzip";
+const SOURCE_ANNOTATION: &str = "org.opencontainers.image.source";
+const REVISION_ANNOTATION: &str = "org.opencontainers.image.revision";
+const EMPTY_CONFIG: &[u8] = b"{}";
+
+#[derive(Debug, Clone)]
+pub(crate) struct OciCredentials {
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+pub(crate) struct OciRegistryClient {
+    http: Client,
+    base_url: Url,
+    credentials: Option<OciCredentials>,
+    authorizations: Mutex<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OciManifest {
+    schema_version: u32,
+    #[serde(default)]
+    media_type: Option<String>,
+    config: OciDescriptor,
+    #[serde(default)]
+    layers: Vec<OciDescriptor>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OciDescriptor {
+    media_type: String,
+    digest: String,
+    size: usize,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    access_token: Option<String>,
+}
+
+impl OciRegistryClient {
+    pub(crate) fn new(
+        registry: &str,
+        insecure: bool,
+        credentials: Option<OciCredentials>,
+        ca_pem: Option<&[u8]>,
+    ) -> anyhow::Result<Self> {
+        let scheme = if insecure { "http" } else { "https" };
+        let base_url = Url::parse(&format!("{scheme}://{}/", registry.trim_end_matches('/')))
+            .with_context(|| format!("invalid registry {registry}"))?;
+        let mut http = Client::builder();
+        if let Some(ca_pem) = ca_pem {
+            for certificate in Certificate::from_pem_bundle(ca_pem)? {
+                http = http.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(Self {
+            http: http.build()?,
+            base_url,
+            credentials,
+            authorizations: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub(crate) async fn push_flux_artifact(
+        &self,
+        repository: &str,
+        tag: &str,
+        source: &str,
+        revision: &str,
+        dir: &Path,
+    ) -> anyhow::Result<bool> {
+        let layer = build_flux_layer(dir)?;
+        let layer_digest = sha256_digest(&layer);
+        if self
+            .manifest_layer_digest(repository, tag)
+            .await?
+            .as_deref()
+            == Some(layer_digest.as_str())
+        {
+            info!(repository = %repository, tag = %tag, digest = %layer_digest, "OCI artifact unchanged");
+            return Ok(false);
+        }
+
+        let config = OciDescriptor {
+            media_type: FLUX_CONFIG_MEDIA_TYPE.to_string(),
+            digest: sha256_digest(EMPTY_CONFIG),
+            size: EMPTY_CONFIG.len(),
+        };
+        self.upload_blob(repository, &config.digest, EMPTY_CONFIG.to_vec())
+            .await?;
+        let layer_descriptor = OciDescriptor {
+            media_type: FLUX_CONTENT_MEDIA_TYPE.to_string(),
+            digest: layer_digest,
+            size: layer.len(),
+        };
+        self.upload_blob(repository, &layer_descriptor.digest, layer)
+            .await?;
+
+        let manifest = OciManifest {
+            schema_version: 2,
+            media_type: Some(OCI_MANIFEST_MEDIA_TYPE.to_string()),
+            config,
+            layers: vec![layer_descriptor],
+            annotations: BTreeMap::from([
+                (SOURCE_ANNOTATION.to_string(), source.to_string()),
+                (REVISION_ANNOTATION.to_string(), revision.to_string()),
+            ]),
+        };
+        let body = serde_json::to_vec(&manifest)?;
+        let url = self.url(&format!("v2/{repository}/manifests/{tag}"))?;
+        let response = self
+            .send(repository, true, |http| {
+                http.put(url.clone())
+                    .header(header::CONTENT_TYPE, OCI_MANIFEST_MEDIA_TYPE)
+                    .body(body.clone())
+            })
+            .await?;
+        expect_status(
+            response,
+            &[StatusCode::CREATED, StatusCode::OK],
+            "push manifest",
+        )
+        .await?;
+
+        info!(repository = %repository, tag = %tag, "pushed OCI artifact");
+        Ok(true)
+    }
+
+    async fn manifest_layer_digest(
+        &self,
+        repository: &str,
+        tag: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let url = self.url(&format!("v2/{repository}/manifests/{tag}"))?;
+        let response = self
+            .send(repository, false, |http| {
+                http.get(url.clone())
+                    .header(header::ACCEPT, OCI_MANIFEST_MEDIA_TYPE)
+            })
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = expect_status(response, &[StatusCode::OK], "fetch manifest").await?;
+        let manifest: OciManifest = response.json().await?;
+        Ok(manifest.layers.into_iter().next().map(|layer| layer.digest))
+    }
+
+    async fn upload_blob(
+        &self,
+        repository: &str,
+        digest: &str,
+        contents: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let url = self.url(&format!("v2/{repository}/blobs/{digest}"))?;
+        let response = self
+            .send(repository, true, |http| {
+                http.request(Method::HEAD, url.clone())
+            })
+            .await?;
+        if response.status() == StatusCode::OK {
+            return Ok(());
+        }
+
+        let url = self.url(&format!("v2/{repository}/blobs/uploads/"))?;
+        let response = self
+            .send(repository, true, |http| http.post(url.clone()))
+            .await?;
+        let response =
+            expect_status(response, &[StatusCode::ACCEPTED], "start blob upload").await?;
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| anyhow!("registry did not return a blob upload location"))?;
+        let mut upload_url = self.base_url.join(location)?;
+        upload_url.query_pairs_mut().append_pair("digest", digest);
+
+        let response = self
+            .send(repository, true, |http| {
+                http.put(upload_url.clone())
+                    .header(header::CONTENT_TYPE, "application/octet-stream")
+                    .body(contents.clone())
+            })
+            .await?;
+        expect_status(response, &[StatusCode::CREATED], "upload blob").await?;
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        repository: &str,
+        push: bool,
+        request: impl Fn(&Client) -> RequestBuilder,
+    ) -> anyhow::Result<Response> {
+        let scope = format!(
+            "repository:{repository}:{}",
+            if push { "pull,push" } else { "pull" }
+        );
+        let authorization = self.authorizations.lock().await.get(&scope).cloned();
+        let response = with_authorization(request(&self.http), authorization.as_deref())
+            .send()
+            .await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let challenge = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string)
+            .ok_or_else(|| anyhow!("registry returned 401 without an authentication challenge"))?;
+        let authorization = self.authorize(&challenge, &scope).await?;
+        self.authorizations
+            .lock()
+            .await
+            .insert(scope, authorization.clone());
+        Ok(
+            with_authorization(request(&self.http), Some(&authorization))
+                .send()
+                .await?,
+        )
+    }
+
+    async fn authorize(&self, challenge: &str, scope: &str) -> anyhow::Result<String> {
+        let (scheme, params) = parse_challenge(challenge);
+        if scheme.eq_ignore_ascii_case("basic") {
+            let credentials = self
+                .credentials
+                .as_ref()
+                .ok_or_else(|| anyhow!("registry requires credentials"))?;
+            return Ok(basic_authorization(credentials));
+        }
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return Err(anyhow!(
+                "unsupported registry authentication scheme {scheme}"
+            ));
+        }
+
+        let realm = params
+            .get("realm")
+            .ok_or_else(|| anyhow!("registry bearer challenge has no realm"))?;
+        let mut token_url = Url::parse(realm)?;
+        {
+            let mut query = token_url.query_pairs_mut();
+            if let Some(service) = params.get("service") {
+                query.append_pair("service", service);
+            }
+            query.append_pair("scope", scope);
+        }
+        let mut request = self.http.get(token_url);
+        if let Some(credentials) = &self.credentials {
+            request = request.header(header::AUTHORIZATION, basic_authorization(credentials));
+        }
+        let response =
+            expect_status(request.send().await?, &[StatusCode::OK], "fetch token").await?;
+        let token: TokenResponse = response.json().await?;
+        let token = token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| anyhow!("registry token response has no token"))?;
+        Ok(format!("Bearer {token}"))
+    }
+
+    fn url(&self, path: &str) -> anyhow::Result<Url> {
+        Ok(self.base_url.join(path)?)
+    }
+}
+
+pub(crate) fn build_flux_layer(dir: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut files = Vec::new();
+    collect_files(dir, dir, &mut files)?;
+    files.sort();
+
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for relative in files {
+        let contents = fs::read(dir.join(&relative))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_entry_type(tar::EntryType::Regular);
+        archive.append_data(&mut header, &relative, contents.as_slice())?;
+    }
+
+    Ok(archive.into_inner()?.finish()?)
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if path.is_file() {
+            files.push(path.strip_prefix(root)?.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
+
+fn sha256_digest(contents: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(contents)))
+}
+
+fn basic_authorization(credentials: &OciCredentials) -> String {
+    format!(
+        "Basic {}",
+        STANDARD.encode(format!("{}:{}", credentials.username, credentials.password))
+    )
+}
+
+fn with_authorization(request: RequestBuilder, authorization: Option<&str>) -> RequestBuilder {
+    match authorization {
+        Some(authorization) => request.header(header::AUTHORIZATION, authorization),
+        None => request,
+    }
+}
+
+fn parse_challenge(challenge: &str) -> (&str, HashMap<String, String>) {
+    let (scheme, rest) = challenge
+        .trim()
+        .split_once(' ')
+        .unwrap_or((challenge.trim(), ""));
+    let mut params = HashMap::new();
+    let mut rest = rest.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_ascii_lowercase();
+        let (value, remaining) = match value.strip_prefix('"') {
+            Some(quoted) => quoted
+                .split_once('"')
+                .map_or((quoted, ""), |(value, remaining)| (value, remaining)),
+            None => value
+                .split_once(',')
+                .map_or((value, ""), |(value, remaining)| (value, remaining)),
+        };
+        params.insert(key, value.to_string());
+        rest = remaining.trim_start_matches(',').trim();
+    }
+    (scheme, params)
+}
+
+async fn expect_status(
+    response: Response,
+    expected: &[StatusCode],
+    operation: &str,
+) -> anyhow::Result<Response> {
+    let status = response.status();
+    if expected.contains(&status) {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(anyhow!("registry {operation} failed with {status}: {body}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::path::PathBuf;
+
+    #[test]
+    fn build_flux_layer_is_reproducible() {
+        let dir = PathBuf::from("/tmp/test-cloudlab-oci-layer");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("namespace.yaml"), "kind: Namespace\n").unwrap();
+        fs::write(dir.join("nested/service.yaml"), "kind: Service\n").unwrap();
+
+        let first = build_flux_layer(&dir).unwrap();
+        let second = build_flux_layer(&dir).unwrap();
+        assert_eq!(sha256_digest(&first), sha256_digest(&second));
+
+        let mut archive = tar::Archive::new(GzDecoder::new(first.as_slice()));
+        let paths = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["namespace.yaml", "nested/service.yaml"]);
+    }
+
+    #[test]
+    fn parse_challenge_reads_bearer_params() {
+        let (scheme, params) = parse_challenge(
+            r#"Bearer realm="https://auth.example.com/token",service="registry.example.com",scope="repository:apps:pull""#,
+        );
+        assert_eq!(scheme, "Bearer");
+        assert_eq!(params["realm"], "https://auth.example.com/token");
+        assert_eq!(params["service"], "registry.example.com");
+        assert_eq!(params["scope"], "repository:apps:pull");
+    }
+}
@@ -0,0 +1,133 @@
+use super::{
+    AppTarget,
+    manifest::{
+        is_namespace_manifest, is_yaml_file, read_app_manifest, required_string,
+        write_yaml_manifest,
+    },
+    merge::{replace_claim_volumes, set_deployment_env_from},
+};
+use anyhow::anyhow;
+use serde_json::json;
+use std::{fs, path::Path};
+use yaml_serde::Value as YamlValue;
+
+pub const PREVIEW_BASE_ENVIRONMENT: &str = "production";
+const PREVIEW_KINDS: [&str; 5] = [
+    "Namespace",
+    "ConfigMap",
+    "Deployment",
+    "Service",
+    "HTTPRoute",
+];
+
+pub fn preview_environment(number: u64) -> String {
+    format!("pr-{number}")
+}
+
+pub fn preview_hostname(target: &AppTarget, domain: &str) -> String {
+    format!(
+        "{}-{}-{}.{}",
+        target.environment,
+        target.project,
+        target.tenant,
+        domain.trim_start_matches('.')
+    )
+}
+
+pub(crate) fn write_preview_environment(
+    apps_dir: &Path,
+    target: &AppTarget,
+    domain: &str,
+) -> anyhow::Result<bool> {
+    let project_dir = apps_dir.join(&target.tenant).join(&target.project);
+    let source_dir = project_dir.join(PREVIEW_BASE_ENVIRONMENT);
+    let preview_dir = project_dir.join(&target.environment);
+    if preview_dir.exists() {
+        return Ok(false);
+    }
+    if !source_dir.is_dir() {
+        return Err(anyhow!(
+            "apps/{}/{}/{PREVIEW_BASE_ENVIRONMENT} does not exist",
+            target.tenant,
+            target.project
+        ));
+    }
+
+    let namespace = format!(
+        "{}-{}-{}",
+        target.tenant, target.project, target.environment
+    );
+    let hostname = preview_hostname(target, domain);
+    for entry in fs::read_dir(&source_dir)? {
+        let path = entry?.path();
+        if !is_yaml_file(&path) {
+            continue;
+        }
+
+        let mut manifest = read_app_manifest(&path)?;
+        if !manifest_kind(&manifest).is_some_and(|kind| PREVIEW_KINDS.contains(&kind)) {
+            continue;
+        }
+        if is_namespace_manifest(&manifest) {
+            set_mapping_string(&mut manifest, &["metadata", "name"], &namespace)?;
+        } else if manifest_kind(&manifest) == Some("HTTPRoute") {
+            set_mapping_value(
+                &mut manifest,
+                &["spec", "hostnames"],
+                YamlValue::Sequence(vec![YamlValue::String(hostname.clone())]),
+            )?;
+        } else if manifest_kind(&manifest) == Some("Deployment") {
+            set_deployment_env_from(
+                &mut manifest,
+                &target.project,
+                json!({ "secretRef": { "name": &target.project } }),
+                false,
+            )?;
+            replace_claim_volumes(&mut manifest)?;
+        }
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} has no file name", path.display()))?;
+        write_yaml_manifest(&preview_dir.join(file_name), &manifest)?;
+    }
+
+    Ok(true)
+}
+
+fn manifest_kind(manifest: &YamlValue) -> Option<&str> {
+    let YamlValue::Mapping(root) = manifest else {
+        return None;
+    };
+    required_string(root, "kind")
+}
+
+fn set_mapping_string(manifest: &mut YamlValue, path: &[&str], value: &str) -> anyhow::Result<()> {
+    set_mapping_value(manifest, path, YamlValue::String(value.to_string()))
+}
+
+fn set_mapping_value(
+    manifest: &mut YamlValue,
+    path: &[&str],
+    value: YamlValue,
+) -> anyhow::Result<()> {
+    let Some((last, parents)) = path.split_last() else {
+        return Ok(());
+    };
+
+    let mut current = manifest;
+    for key in parents {
+        let YamlValue::Mapping(map) = current else {
+            return Err(anyhow!("{} must be a YAML mapping", path.join(".")));
+        };
+        current = map
+            .entry(YamlValue::String(key.to_string()))
+            .or_insert_with(|| YamlValue::Mapping(Default::default()));
+    }
+
+    let YamlValue::Mapping(map) = current else {
+        return Err(anyhow!("{} must be a YAML mapping", path.join(".")));
+    };
+    map.insert(YamlValue::String(last.to_string()), value);
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use super::{
+    manifest::read_app_manifest,
+    merge::{remove_deployment_volume, set_deployment_env_from, update_manifest},
+    secrets::{sealed_secret_keys, update_sealed_secret},
+};
+use crate::api::{AppComponent, ComponentKind};
+use anyhow::anyhow;
+use serde_json::json;
+use std::{fs, path::Path};
+use yaml_serde::Value as YamlValue;
+
+pub(crate) fn remove_app_component(
+    app_dir: &Path,
+    project: &str,
+    component: &AppComponent,
+) -> anyhow::Result<()> {
+    let name = component.name.as_str();
+    match component.kind {
+        ComponentKind::Deployment => remove_manifest(app_dir, &format!("deployment-{name}.yaml")),
+        ComponentKind::Service => remove_manifest(app_dir, &format!("service-{name}.yaml")),
+        ComponentKind::HttpRoute => remove_manifest(app_dir, &format!("httproute-{name}.yaml")),
+        ComponentKind::Postgres => remove_manifest(app_dir, &format!("cluster-{name}.yaml")),
+        ComponentKind::Autoscaler => remove_autoscaler(app_dir, project, name),
+        ComponentKind::Volume => {
+            remove_manifest(app_dir, &format!("persistentvolumeclaim-{name}.yaml"))?;
+            update_deployment(app_dir, project, |manifest| {
+                remove_deployment_volume(manifest, project, name)
+            })
+        }
+        ComponentKind::Config => remove_config_key(app_dir, project, name),
+        ComponentKind::Secret => {
+            if !sealed_secret_keys(app_dir, project)?
+                .iter()
+                .any(|key| key == name)
+            {
+                return Err(anyhow!("secret {name} does not exist"));
+            }
+            update_sealed_secret(app_dir, project, &[], &[name.to_string()])
+        }
+    }
+}
+
+fn remove_manifest(app_dir: &Path, filename: &str) -> anyhow::Result<()> {
+    let path = app_dir.join(filename);
+    if !path.exists() {
+        return Err(anyhow!("{filename} does not exist"));
+    }
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+fn remove_autoscaler(app_dir: &Path, project: &str, name: &str) -> anyhow::Result<()> {
+    let filename = format!("horizontalpodautoscaler-{name}.yaml");
+    let path = app_dir.join(&filename);
+    if !path.exists() {
+        return Err(anyhow!("{filename} does not exist"));
+    }
+    let min_replicas = read_app_manifest(&path)?
+        .get("spec")
+        .and_then(|spec| spec.get("minReplicas"))
+        .cloned()
+        .unwrap_or(YamlValue::from(1));
+    fs::remove_file(&path)?;
+    update_deployment(app_dir, project, |manifest| {
+        if let Some(spec) = manifest.get_mut("spec").and_then(YamlValue::as_mapping_mut)
+            && !spec.contains_key("replicas")
+        {
+            spec.insert(YamlValue::from("replicas"), min_replicas);
+        }
+        Ok(())
+    })
+}
+
+fn remove_config_key(app_dir: &Path, project: &str, key: &str) -> anyhow::Result<()> {
+    let path = app_dir.join(format!("configmap-{project}.yaml"));
+    if !path.exists() {
+        return Err(anyhow!("config {key} does not exist"));
+    }
+
+    let mut empty = false;
+    update_manifest(&path, |manifest| {
+        let data = manifest
+            .get_mut("data")
+            .and_then(YamlValue::as_mapping_mut)
+            .ok_or_else(|| anyhow!("config {key} does not exist"))?;
+        if data.shift_remove(key).is_none() {
+            return Err(anyhow!("config {key} does not exist"));
+        }
+        empty = data.is_empty();
+        Ok(())
+    })?;
+    if !empty {
+        return Ok(());
+    }
+
+    fs::remove_file(&path)?;
+    update_deployment(app_dir, project, |manifest| {
+        set_deployment_env_from(
+            manifest,
+            project,
+            json!({ "configMapRef": { "name": project } }),
+            false,
+        )
+    })
+}
+
+fn update_deployment(
+    app_dir: &Path,
+    project: &str,
+    update: impl FnOnce(&mut YamlValue) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let path = app_dir.join(format!("deployment-{project}.yaml"));
+    if !path.exists() {
+        return Ok(());
+    }
+    update_manifest(&path, update)
+}
@@ -0,0 +1,115 @@
+use super::{
+    create::write_json_manifest,
+    manifest::read_app_manifest,
+    merge::{set_deployment_env_from, update_manifest},
+};
+use crate::api::KeyValue;
+use anyhow::anyhow;
+use serde_json::{Value as JsonValue, json};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub(super) fn sealed_secret_manifest(name: &str, values: &BTreeMap<String, String>) -> JsonValue {
+    json!({
+        "apiVersion": "bitnami.com/v1alpha1",
+        "kind": "SealedSecret",
+        "metadata": { "name": name },
+        "spec": {
+            "encryptedData": values,
+            "template": {
+                "metadata": { "name": name },
+                "type": "Opaque",
+            },
+        },
+    })
+}
+
+pub(super) fn sealed_values(values: &[KeyValue]) -> BTreeMap<String, String> {
+    values
+        .iter()
+        .map(|value| (value.key.clone(), value.value.clone()))
+        .collect()
+}
+
+pub fn sealed_secret_keys(app_dir: &Path, project: &str) -> anyhow::Result<Vec<String>> {
+    Ok(read_sealed_values(&sealed_secret_path(app_dir, project))?
+        .into_keys()
+        .collect())
+}
+
+pub(crate) fn update_sealed_secret(
+    app_dir: &Path,
+    project: &str,
+    set: &[KeyValue],
+    unset: &[String],
+) -> anyhow::Result<()> {
+    let plaintext = app_dir.join(format!("secret-{project}.yaml"));
+    if plaintext.exists() {
+        return Err(anyhow!(
+            "{} is a plaintext Secret; remove it before managing secrets with netamos",
+            plaintext.display()
+        ));
+    }
+
+    let path = sealed_secret_path(app_dir, project);
+    let mut values = read_sealed_values(&path)?;
+    for key in unset {
+        values.remove(key);
+    }
+    values.extend(sealed_values(set));
+
+    if values.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+    } else {
+        write_json_manifest(&path, sealed_secret_manifest(project, &values))?;
+    }
+    set_deployment_secret_ref(app_dir, project, !values.is_empty())
+}
+
+fn sealed_secret_path(app_dir: &Path, project: &str) -> PathBuf {
+    app_dir.join(format!("sealedsecret-{project}.yaml"))
+}
+
+fn read_sealed_values(path: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let manifest = serde_json::to_value(read_app_manifest(path)?)?;
+    match &manifest["spec"]["encryptedData"] {
+        JsonValue::Null => Ok(BTreeMap::new()),
+        JsonValue::Object(values) => values
+            .iter()
+            .map(|(key, value)| match value {
+                JsonValue::String(value) => Ok((key.clone(), value.clone())),
+                _ => Err(anyhow!(
+                    "{}: spec.encryptedData.{key} must be a string",
+                    path.display()
+                )),
+            })
+            .collect(),
+        _ => Err(anyhow!(
+            "{}: spec.encryptedData must be a mapping",
+            path.display()
+        )),
+    }
+}
+
+fn set_deployment_secret_ref(app_dir: &Path, project: &str, enabled: bool) -> anyhow::Result<()> {
+    let path = app_dir.join(format!("deployment-{project}.yaml"));
+    if !path.exists() {
+        return Ok(());
+    }
+    update_manifest(&path, |manifest| {
+        set_deployment_env_from(
+            manifest,
+            project,
+            json!({ "secretRef": { "name": project } }),
+            enabled,
+        )
+    })
+}
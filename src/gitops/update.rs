@@ -11,8 +11,14 @@ pub(crate) async fn update_app_version_inner(input: UpdateAppVersionInput) -> an
     let apps_dir = Path::new(&input.apps_dir);
     let mut changed = false;
 
-    for (_tenant, tenant_dir) in child_dirs(apps_dir)? {
-        for (_project, project_dir) in child_dirs(&tenant_dir)? {
+    for (tenant, tenant_dir) in child_dirs(apps_dir)? {
+        if input.tenant.as_ref().is_some_and(|only| only != &tenant) {
+            continue;
+        }
+        for (project, project_dir) in child_dirs(&tenant_dir)? {
+            if input.project.as_ref().is_some_and(|only| only != &project) {
+                continue;
+            }
             let app_dir = project_dir.join(&input.environment);
             if !app_dir.is_dir() {
                 continue;
@@ -77,7 +83,10 @@ fn update_image_reference(current: &str, image: &AppImageUpdate) -> Option<Strin
     let digest_prefix = format!("{}@", image.repository);
 
     if current.starts_with(&tag_prefix) || current.starts_with(&digest_prefix) {
-        let updated = format!("{}:{}", image.repository, image.tag);
+        let updated = match &image.digest {
+            Some(digest) => format!("{}:{}@{digest}", image.repository, image.tag),
+            None => format!("{}:{}", image.repository, image.tag),
+        };
         if current != updated {
             return Some(updated);
         }
@@ -1,5 +1,8 @@
+mod authz;
+mod webhook;
+
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     env,
     path::PathBuf,
     process::Stdio,
@@ -9,35 +12,57 @@ use std::{
 };
 
 use crate::{
-    activities::{ForgejoCommitStatusTarget, git_command_for_url},
+    activities::{
+        CommitStatusTarget, ForgejoCommitStatusTarget, GithubCommitStatusTarget,
+        GitlabCommitStatusTarget, ImageScanPolicy, PullRequestTarget, build_log_path,
+        git_command_for_url,
+    },
     api::{
-        AuthConfig as ApiAuthConfig, CreateAppRequest, DeleteAppRequest, DeployRequest,
-        ProjectSummary, UserInfo, WorkflowStarted, WorkflowStatus, deploy_workflow_id,
+        AddAppQuery, AppComponent, ApprovalDecision, ApprovalState, AuthConfig as ApiAuthConfig,
+        ComponentKind, CreateAppRequest, DeleteAppRequest, DeployRequest, DeploymentHistoryEntry,
+        HistoryQuery, ImageRevision, KeyValue, LogsQuery, ProjectSummary, PromoteAppRequest,
+        RemoveComponentsRequest, RollbackAppRequest, UpdateSecretsRequest, UserInfo,
+        WorkflowStarted, WorkflowStatus, deploy_workflow_id, preview_workflow_id,
+        validate_new_environment,
     },
     core::app::source::Source,
-    gitops::{AppSourceTarget, AppTarget, scan_app_inventory, scan_app_source_targets},
+    gitops::{
+        AppSourceTarget, AppTarget, ImageChange, PREVIEW_BASE_ENVIRONMENT, commit_change,
+        image_history_args, parse_image_history, preview_environment, rollback_candidates,
+        scan_app_inventory, scan_app_source_targets, scan_environment_images, sealed_secret_keys,
+        split_image_reference, update_commit_source_repo,
+    },
     temporal,
-    workflows::{self, push_to_deploy::PushToDeployInput},
+    workflows::{self, WorkflowScope, preview::PreviewInput, push_to_deploy::PushToDeployInput},
 };
 use anyhow::{Context, Result, anyhow};
+use authz::{Access, Authorizer, Role};
 use axum::{
     Json, Router,
-    body::Bytes,
-    extract::{Path as AxumPath, State},
+    body::{Body, Bytes},
+    extract::{Path as AxumPath, Query, State},
     http::{HeaderMap, StatusCode, header},
     response::IntoResponse,
-    routing::{get, patch, post},
+    routing::{delete, get, patch, post},
 };
 use openidconnect::{
-    ClientId, IssuerUrl, Nonce,
-    core::{CoreClient, CoreIdToken, CoreProviderMetadata},
+    AdditionalClaims, ClientId, IdToken, IssuerUrl, Nonce,
+    core::{
+        CoreClient, CoreGenderClaim, CoreJweContentEncryptionAlgorithm, CoreJwsSigningAlgorithm,
+        CoreProviderMetadata,
+    },
     reqwest as oidc_reqwest,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::{fs, net::TcpListener, process::Command, sync::Mutex};
+use tokio::{fs, io::AsyncWriteExt, net::TcpListener, process::Command, sync::Mutex};
 use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
+use webhook::{
+    PullRequestAction, PullRequestEvent, PushEvent, PushOrigin, WebhookMetrics, WebhookProvider,
+};
+
+const LOG_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Clone)]
 struct AppState {
@@ -45,6 +70,16 @@ struct AppState {
     config: AppConfig,
     gitops_index: Arc<GitopsIndex>,
     auth: Arc<AuthVerifier>,
+    authz: Arc<Authorizer>,
+    webhook_metrics: Arc<WebhookMetrics>,
+}
+
+impl AppState {
+    async fn authorize(&self, headers: &HeaderMap) -> Result<(UserInfo, Access), ApiError> {
+        let user = self.auth.verify(headers).await?;
+        let access = self.authz.access(&self.gitops_index, &user).await?;
+        Ok((user, access))
+    }
 }
 
 #[derive(Clone)]
@@ -62,6 +97,15 @@ struct AppConfig {
     oidc_issuer: Option<String>,
     oidc_client_id: String,
     oidc_audience: String,
+    approval_environments: BTreeSet<String>,
+    image_scan_policies: BTreeMap<String, ImageScanPolicy>,
+    webhook_secret: Option<String>,
+    github_webhook_secret: Option<String>,
+    gitlab_webhook_token: Option<String>,
+    github_api_url: String,
+    gitlab_url: String,
+    preview_domain: Option<String>,
+    sealed_secrets_cert: Option<PathBuf>,
 }
 
 impl AppConfig {
@@ -93,10 +137,57 @@ impl AppConfig {
                 .unwrap_or_else(|_| "netamos-cli".to_string()),
             oidc_audience: std::env::var("OIDC_AUDIENCE")
                 .unwrap_or_else(|_| "netamos-api".to_string()),
+            approval_environments: parse_list(
+                &env::var("NETAMOS_APPROVAL_ENVIRONMENTS").unwrap_or_default(),
+            ),
+            image_scan_policies: parse_image_scan_policies(
+                &env::var("NETAMOS_IMAGE_SCAN_THRESHOLDS").unwrap_or_default(),
+            )?,
+            webhook_secret: env::var("NETAMOS_WEBHOOK_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            github_webhook_secret: env::var("NETAMOS_GITHUB_WEBHOOK_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            gitlab_webhook_token: env::var("NETAMOS_GITLAB_WEBHOOK_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            github_api_url: env::var("GITHUB_API_URL")
+                .unwrap_or_else(|_| "https://api.github.com".to_string()),
+            gitlab_url: env::var("GITLAB_URL").unwrap_or_else(|_| "https://gitlab.com".to_string()),
+            preview_domain: env::var("NETAMOS_PREVIEW_DOMAIN")
+                .ok()
+                .filter(|domain| !domain.is_empty()),
+            sealed_secrets_cert: env::var("NETAMOS_SEALED_SECRETS_CERT")
+                .ok()
+                .filter(|cert| !cert.is_empty())
+                .map(PathBuf::from),
         })
     }
+
+    fn source_forgejo_url(&self) -> Option<String> {
+        if self.github_webhook_secret.is_some() || self.gitlab_webhook_token.is_some() {
+            return None;
+        }
+        self.forgejo_url.clone()
+    }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct GroupsClaim {
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+impl AdditionalClaims for GroupsClaim {}
+
+type GroupsIdToken = IdToken<
+    GroupsClaim,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+>;
+
 struct AuthVerifier {
     http: oidc_reqwest::Client,
     issuer: Option<String>,
@@ -128,6 +219,7 @@ impl AuthVerifier {
                 "profile".to_string(),
                 "email".to_string(),
                 "offline_access".to_string(),
+                "groups".to_string(),
                 format!("audience:server:client_id:{}", self.audience),
             ],
         })
@@ -151,7 +243,7 @@ impl AuthVerifier {
             ClientId::new(self.client_id.clone()),
             None,
         );
-        let id_token = CoreIdToken::from_str(&token)
+        let id_token = GroupsIdToken::from_str(&token)
             .map_err(|error| ApiError::unauthorized(error.to_string()))?;
         let audience = self.audience.clone();
         let verifier = client
@@ -178,6 +270,7 @@ impl AuthVerifier {
                 .preferred_username()
                 .map(|username| username.as_str().to_string()),
             email: claims.email().map(|email| email.as_str().to_string()),
+            groups: claims.additional_claims().groups.clone(),
         })
     }
 }
@@ -222,6 +315,27 @@ impl ApiError {
         }
     }
 
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+        }
+    }
+
+    fn conflict(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+
     fn bad_gateway(message: impl Into<String>) -> Self {
         Self {
             status: StatusCode::BAD_GATEWAY,
@@ -308,6 +422,18 @@ impl GitopsIndex {
             .unwrap_or_default()
     }
 
+    async fn image_history(&self, pathspec: &str) -> Result<Vec<ImageChange>> {
+        self.refresh_if_stale().await?;
+
+        let _state = self.state.lock().await;
+        let mut command = Command::new("git");
+        command
+            .args(image_history_args(pathspec))
+            .current_dir(&self.config.cache_dir);
+        let log = run_stdout_command(&mut command, "git log GitOps history").await?;
+        Ok(parse_image_history(&log))
+    }
+
     async fn refresh_if_stale(&self) -> Result<()> {
         let is_stale = {
             let state = self.state.lock().await;
@@ -334,29 +460,6 @@ impl GitopsIndex {
     }
 }
 
-#[derive(Deserialize)]
-struct Owner {
-    username: String,
-}
-
-#[derive(Deserialize)]
-struct RepoInfo {
-    name: String,
-    owner: Owner,
-    #[serde(default)]
-    default_branch: String,
-    #[serde(default)]
-    clone_url: String,
-}
-
-#[derive(Deserialize)]
-struct PushPayload {
-    after: String,
-    #[serde(rename = "ref")]
-    git_ref: String,
-    repository: RepoInfo,
-}
-
 pub async fn run() -> Result<()> {
     tracing_subscriber::fmt::fmt()
         .with_env_filter(EnvFilter::try_from_env("LOG_LEVEL").unwrap_or(EnvFilter::new("info")))
@@ -370,25 +473,57 @@ pub async fn run() -> Result<()> {
         warn!(error = %error, "failed to warm GitOps deployability cache");
     }
 
+    if config.webhook_secret.is_none() {
+        warn!("NETAMOS_WEBHOOK_SECRET is not set; webhook signatures are not verified");
+    }
+
     let state = AppState {
         client: Arc::new(client),
         auth: Arc::new(AuthVerifier::new(&config)?),
         config,
         gitops_index,
+        authz: Arc::new(Authorizer::from_env()),
+        webhook_metrics: Arc::new(WebhookMetrics::default()),
     };
 
     let app = Router::new()
         .route("/api/v1/auth/config", get(auth_config))
         .route("/api/v1/me", get(me))
         .route("/api/v1/projects", get(list_projects))
+        .route("/api/v1/history", get(deployment_history))
         .route("/api/v1/apps", post(create_app))
         .route(
             "/api/v1/apps/{tenant}/{project}/{environment}",
             patch(add_app).delete(delete_app),
         )
+        .route("/api/v1/apps/{tenant}/{project}/promote", post(promote_app))
+        .route(
+            "/api/v1/apps/{tenant}/{project}/{environment}/rollback",
+            get(rollback_revisions).post(rollback_app),
+        )
+        .route(
+            "/api/v1/apps/{tenant}/{project}/{environment}/components/{kind}/{name}",
+            delete(remove_component),
+        )
+        .route(
+            "/api/v1/apps/{tenant}/{project}/{environment}/secrets",
+            get(list_secrets).patch(update_secrets),
+        )
         .route("/api/v1/deployments", post(create_deployment))
         .route("/api/v1/workflows/{workflow_id}", get(workflow_status))
+        .route("/api/v1/workflows/{workflow_id}/logs", get(workflow_logs))
+        .route(
+            "/api/v1/workflows/{workflow_id}/approve",
+            post(approve_workflow),
+        )
+        .route(
+            "/api/v1/workflows/{workflow_id}/reject",
+            post(reject_workflow),
+        )
         .route("/webhooks/gitea", post(handle_gitea_webhook))
+        .route("/webhooks/github", post(handle_github_webhook))
+        .route("/webhooks/gitlab", post(handle_gitlab_webhook))
+        .route("/metrics", get(metrics))
         .route(
             "/healthz",
             get(|| async { StatusCode::NO_CONTENT }).post(|| async { StatusCode::NO_CONTENT }),
@@ -412,7 +547,7 @@ async fn list_projects(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> Result<Json<Vec<ProjectSummary>>, ApiError> {
-    state.auth.verify(&headers).await?;
+    let (_, access) = state.authorize(&headers).await?;
     state
         .gitops_index
         .refresh_if_stale()
@@ -425,6 +560,7 @@ async fn list_projects(
     .map_err(ApiError::internal)?;
     Ok(Json(
         apps.into_iter()
+            .filter(|app| access.can(&app.tenant, Role::Viewer))
             .map(|app| ProjectSummary {
                 tenant: app.tenant,
                 project: app.project,
@@ -432,19 +568,61 @@ async fn list_projects(
                 resources: app.resources,
                 hostnames: app.hostnames,
                 images: app.images,
+                image_versions: app.image_versions,
                 source_repos: app.source_repos,
             })
             .collect(),
     ))
 }
 
+async fn deployment_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<DeploymentHistoryEntry>>, ApiError> {
+    let (_, access) = state.authorize(&headers).await?;
+    query.validate().map_err(ApiError::bad_request)?;
+    if let Some(tenant) = &query.tenant {
+        access.require(tenant, Role::Viewer)?;
+    }
+
+    let history = state
+        .gitops_index
+        .image_history(&history_pathspec(&query))
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(Json(
+        history
+            .into_iter()
+            .filter(|change| access.can(&change.target.tenant, Role::Viewer))
+            .map(|change| DeploymentHistoryEntry {
+                change: commit_change(&change.subject),
+                source_repo: update_commit_source_repo(&change.subject),
+                source_revision: split_image_reference(&change.image)
+                    .1
+                    .map(ToString::to_string),
+                tenant: change.target.tenant,
+                project: change.target.project,
+                environment: change.target.environment,
+                commit_sha: change.commit_sha,
+                author: change.author,
+                timestamp: change.timestamp,
+                image: change.image,
+            })
+            .collect(),
+    ))
+}
+
 async fn create_app(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(request): Json<CreateAppRequest>,
+    Json(mut request): Json<CreateAppRequest>,
 ) -> Result<Json<WorkflowStarted>, ApiError> {
-    state.auth.verify(&headers).await?;
-    request.validate().map_err(ApiError::bad_request)?;
+    let (_, access) = state.authorize(&headers).await?;
+    request.validate_create().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, Role::Admin)?;
+    seal_request_secrets(&state, &mut request).await?;
     let workflow_id = format!("create-app-{}", sanitize(&request.app_path()));
     workflows::start_create_app_workflow(
         &state.client,
@@ -466,18 +644,26 @@ async fn add_app(
     State(state): State<AppState>,
     headers: HeaderMap,
     AxumPath((tenant, project, environment)): AxumPath<(String, String, String)>,
+    Query(query): Query<AddAppQuery>,
     Json(mut request): Json<CreateAppRequest>,
 ) -> Result<Json<WorkflowStarted>, ApiError> {
-    state.auth.verify(&headers).await?;
+    let (_, access) = state.authorize(&headers).await?;
     request.tenant = tenant;
     request.project = project;
     request.environment = environment;
     request.validate().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, Role::Deployer)?;
     if !request.has_components() {
         return Err(ApiError::bad_request("add needs at least one component"));
     }
+    seal_request_secrets(&state, &mut request).await?;
 
-    let workflow_id = format!("add-app-{}", sanitize(&request.app_path()));
+    let prefix = if query.dry_run {
+        "plan-add-app"
+    } else {
+        "add-app"
+    };
+    let workflow_id = format!("{prefix}-{}", sanitize(&request.app_path()));
     workflows::start_add_app_workflow(
         &state.client,
         workflow_id.clone(),
@@ -486,6 +672,7 @@ async fn add_app(
             gitops_revision: state.config.gitops_revision.clone(),
             registry: state.config.registry.clone(),
             request,
+            dry_run: query.dry_run,
         },
     )
     .await
@@ -499,13 +686,14 @@ async fn delete_app(
     headers: HeaderMap,
     AxumPath((tenant, project, environment)): AxumPath<(String, String, String)>,
 ) -> Result<Json<WorkflowStarted>, ApiError> {
-    state.auth.verify(&headers).await?;
+    let (_, access) = state.authorize(&headers).await?;
     let request = DeleteAppRequest {
         tenant,
         project,
         environment,
     };
     request.validate().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, Role::Admin)?;
     let workflow_id = format!("delete-app-{}", sanitize(&request.app_path()));
     workflows::start_delete_app_workflow(
         &state.client,
@@ -523,16 +711,354 @@ async fn delete_app(
     Ok(Json(WorkflowStarted { workflow_id }))
 }
 
+async fn remove_component(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath((tenant, project, environment, kind, name)): AxumPath<(
+        String,
+        String,
+        String,
+        String,
+        String,
+    )>,
+) -> Result<Json<WorkflowStarted>, ApiError> {
+    let (_, access) = state.authorize(&headers).await?;
+    let component = AppComponent {
+        kind: kind.parse().map_err(ApiError::bad_request)?,
+        name,
+    };
+    let role = match component.kind {
+        ComponentKind::Volume | ComponentKind::Postgres => Role::Admin,
+        _ => Role::Deployer,
+    };
+    let request = RemoveComponentsRequest {
+        tenant,
+        project,
+        environment,
+        components: vec![component],
+    };
+    request.validate().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, role)?;
+
+    let workflow_id = format!(
+        "remove-components-{}-{}",
+        sanitize(&request.app_path()),
+        sanitize(&request.components[0].to_string())
+    );
+    workflows::start_remove_components_workflow(
+        &state.client,
+        workflow_id.clone(),
+        workflows::remove_components::RemoveComponentsInput {
+            gitops_url: state.config.gitops_url.clone(),
+            gitops_revision: state.config.gitops_revision.clone(),
+            registry: state.config.registry.clone(),
+            request,
+        },
+    )
+    .await
+    .map_err(ApiError::internal)?;
+
+    Ok(Json(WorkflowStarted { workflow_id }))
+}
+
+async fn rollback_revisions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath((tenant, project, environment)): AxumPath<(String, String, String)>,
+) -> Result<Json<Vec<ImageRevision>>, ApiError> {
+    let (_, access) = state.authorize(&headers).await?;
+    let request = RollbackAppRequest {
+        tenant,
+        project,
+        environment,
+        image: None,
+    };
+    request.validate().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, Role::Viewer)?;
+
+    let app_path = request.app_path();
+    let history = state
+        .gitops_index
+        .image_history(&format!("apps/{app_path}"))
+        .await
+        .map_err(ApiError::internal)?;
+    let app_dir = state
+        .gitops_index
+        .config
+        .cache_dir
+        .join("apps")
+        .join(&app_path);
+    if !app_dir.exists() {
+        return Err(ApiError::bad_request(format!(
+            "apps/{app_path} does not exist"
+        )));
+    }
+    let current_images = scan_environment_images(&app_dir).map_err(ApiError::internal)?;
+    let target = AppTarget {
+        tenant: request.tenant,
+        project: request.project,
+        environment: request.environment,
+    };
+
+    Ok(Json(
+        rollback_candidates(&history, &target, &current_images)
+            .into_iter()
+            .map(|change| ImageRevision {
+                commit_sha: change.commit_sha,
+                author: change.author,
+                timestamp: change.timestamp,
+                image: change.image,
+            })
+            .collect(),
+    ))
+}
+
+async fn rollback_app(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath((tenant, project, environment)): AxumPath<(String, String, String)>,
+    Json(mut request): Json<RollbackAppRequest>,
+) -> Result<Json<WorkflowStarted>, ApiError> {
+    let (user, access) = state.authorize(&headers).await?;
+    request.tenant = tenant;
+    request.project = project;
+    request.environment = environment;
+    request.validate().map_err(ApiError::bad_request)?;
+    let approved_by =
+        require_publish_access(&state, &access, user, &request.tenant, &request.environment)?;
+
+    let workflow_id = format!("rollback-app-{}", sanitize(&request.app_path()));
+    workflows::start_rollback_workflow(
+        &state.client,
+        workflow_id.clone(),
+        workflows::rollback::RollbackInput {
+            gitops_url: state.config.gitops_url.clone(),
+            gitops_revision: state.config.gitops_revision.clone(),
+            registry: state.config.registry.clone(),
+            request,
+            approved_by,
+        },
+    )
+    .await
+    .map_err(ApiError::internal)?;
+
+    Ok(Json(WorkflowStarted { workflow_id }))
+}
+
+fn require_publish_access(
+    state: &AppState,
+    access: &Access,
+    user: UserInfo,
+    tenant: &str,
+    environment: &str,
+) -> Result<Option<String>, ApiError> {
+    if !state.config.approval_environments.contains(environment) {
+        access.require(tenant, Role::Deployer)?;
+        return Ok(None);
+    }
+    access.require(tenant, Role::Admin).map_err(|_| {
+        ApiError::forbidden(format!(
+            "{environment} requires approval; admin role is required for tenant {tenant}"
+        ))
+    })?;
+    Ok(Some(user_name(user)))
+}
+
+fn user_name(user: UserInfo) -> String {
+    user.username.or(user.email).unwrap_or(user.subject)
+}
+
+async fn list_secrets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath((tenant, project, environment)): AxumPath<(String, String, String)>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    let (_, access) = state.authorize(&headers).await?;
+    let request = DeleteAppRequest {
+        tenant,
+        project,
+        environment,
+    };
+    request.validate().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, Role::Viewer)?;
+
+    state
+        .gitops_index
+        .refresh_if_stale()
+        .await
+        .map_err(ApiError::internal)?;
+    let app_path = request.app_path();
+    let _state = state.gitops_index.state.lock().await;
+    let app_dir = state
+        .gitops_index
+        .config
+        .cache_dir
+        .join("apps")
+        .join(&app_path);
+    if !app_dir.exists() {
+        return Err(ApiError::not_found(format!(
+            "apps/{app_path} does not exist"
+        )));
+    }
+
+    Ok(Json(
+        sealed_secret_keys(&app_dir, &request.project).map_err(ApiError::internal)?,
+    ))
+}
+
+async fn update_secrets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath((tenant, project, environment)): AxumPath<(String, String, String)>,
+    Json(mut request): Json<UpdateSecretsRequest>,
+) -> Result<Json<WorkflowStarted>, ApiError> {
+    let (_, access) = state.authorize(&headers).await?;
+    request.tenant = tenant;
+    request.project = project;
+    request.environment = environment;
+    request.validate().map_err(ApiError::bad_request)?;
+    access.require(&request.tenant, Role::Deployer)?;
+    request.set = seal_values(
+        &state,
+        &request.app_path().replace('/', "-"),
+        &request.project,
+        &request.set,
+    )
+    .await?;
+
+    let workflow_id = format!("update-secrets-{}", sanitize(&request.app_path()));
+    workflows::start_update_secrets_workflow(
+        &state.client,
+        workflow_id.clone(),
+        workflows::update_secrets::UpdateSecretsInput {
+            gitops_url: state.config.gitops_url.clone(),
+            gitops_revision: state.config.gitops_revision.clone(),
+            registry: state.config.registry.clone(),
+            request,
+        },
+    )
+    .await
+    .map_err(ApiError::internal)?;
+
+    Ok(Json(WorkflowStarted { workflow_id }))
+}
+
+async fn seal_request_secrets(
+    state: &AppState,
+    request: &mut CreateAppRequest,
+) -> Result<(), ApiError> {
+    if request.secrets.is_empty() {
+        return Ok(());
+    }
+    let secrets = std::mem::take(&mut request.secrets);
+    let sealed = seal_values(
+        state,
+        &request.app_path().replace('/', "-"),
+        &request.project,
+        &secrets,
+    )
+    .await?;
+    request.sealed_secrets.extend(sealed);
+    Ok(())
+}
+
+async fn seal_values(
+    state: &AppState,
+    namespace: &str,
+    name: &str,
+    values: &[KeyValue],
+) -> Result<Vec<KeyValue>, ApiError> {
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(cert) = &state.config.sealed_secrets_cert else {
+        return Err(ApiError::bad_request(
+            "secrets are disabled; set NETAMOS_SEALED_SECRETS_CERT on the server",
+        ));
+    };
+
+    let mut sealed = Vec::with_capacity(values.len());
+    for value in values {
+        let mut command = Command::new("kubeseal");
+        command
+            .args(["--raw", "--scope", "strict"])
+            .arg("--cert")
+            .arg(cert)
+            .args(["--namespace", namespace, "--name", name])
+            .args(["--from-file", "/dev/stdin"]);
+        let encrypted = run_stdin_command(&mut command, value.value.as_bytes(), "kubeseal")
+            .await
+            .map_err(ApiError::internal)?;
+        sealed.push(KeyValue {
+            key: value.key.clone(),
+            value: encrypted.trim().to_string(),
+        });
+    }
+    Ok(sealed)
+}
+
+async fn promote_app(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath((tenant, project)): AxumPath<(String, String)>,
+    Json(mut request): Json<PromoteAppRequest>,
+) -> Result<Json<WorkflowStarted>, ApiError> {
+    let (user, access) = state.authorize(&headers).await?;
+    request.tenant = tenant;
+    request.project = project;
+    request.validate().map_err(ApiError::bad_request)?;
+    let approved_by = require_publish_access(&state, &access, user, &request.tenant, &request.to)?;
+
+    let workflow_id = format!(
+        "promote-app-{}-{}-to-{}",
+        sanitize(&request.app_path()),
+        request.from,
+        request.to
+    );
+    let status_url =
+        state.config.temporal_web_url.as_ref().map(|base| {
+            temporal_workflow_url(base, &state.config.temporal_namespace, &workflow_id)
+        });
+    workflows::start_promote_workflow(
+        &state.client,
+        workflow_id.clone(),
+        workflows::promote::PromoteInput {
+            gitops_url: state.config.gitops_url.clone(),
+            gitops_revision: state.config.gitops_revision.clone(),
+            registry: state.config.registry.clone(),
+            request,
+            forgejo_url: state.config.source_forgejo_url(),
+            status_url,
+            approved_by,
+        },
+    )
+    .await
+    .map_err(ApiError::internal)?;
+
+    Ok(Json(WorkflowStarted { workflow_id }))
+}
+
 async fn create_deployment(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(request): Json<DeployRequest>,
 ) -> Result<Json<WorkflowStarted>, ApiError> {
-    state.auth.verify(&headers).await?;
+    let (_, access) = state.authorize(&headers).await?;
+    validate_new_environment(&request.environment).map_err(ApiError::bad_request)?;
     let (owner, repo_name) = request
         .repo
         .split_once('/')
         .ok_or_else(|| ApiError::bad_request("repo must be in owner/name form"))?;
+    let targets = state
+        .gitops_index
+        .targets_for(&request.repo, &request.environment)
+        .await;
+    if targets.is_empty() {
+        access.require(owner, Role::Deployer)?;
+    }
+    for target in &targets {
+        access.require(&target.tenant, Role::Deployer)?;
+    }
     let source_url = forgejo_clone_url(&state.config, &request.repo);
     let workflow_id = deploy_workflow_id(repo_name, &request.revision);
     let source = git_source(owner, repo_name, source_url, &request.revision);
@@ -540,6 +1066,7 @@ async fn create_deployment(
         &state.config,
         source,
         request.environment,
+        &PushOrigin::Forgejo,
         &request.repo,
         &request.revision,
         &workflow_id,
@@ -557,7 +1084,8 @@ async fn workflow_status(
     headers: HeaderMap,
     AxumPath(workflow_id): AxumPath<String>,
 ) -> Result<Json<WorkflowStatus>, ApiError> {
-    state.auth.verify(&headers).await?;
+    let (_, access) = state.authorize(&headers).await?;
+    require_workflow_access(&state, &access, &workflow_id, Role::Viewer).await?;
     let url =
         state.config.temporal_web_url.as_ref().map(|base| {
             temporal_workflow_url(base, &state.config.temporal_namespace, &workflow_id)
@@ -568,28 +1096,287 @@ async fn workflow_status(
         .map_err(ApiError::internal)
 }
 
+async fn workflow_logs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath(workflow_id): AxumPath<String>,
+    Query(query): Query<LogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (_, access) = state.authorize(&headers).await?;
+    require_workflow_access(&state, &access, &workflow_id, Role::Viewer).await?;
+    let path = build_log_path(&workflow_id);
+    let initial = read_build_log(&path, query.offset)
+        .await
+        .map_err(ApiError::internal)?;
+    if initial.is_none() && !query.follow {
+        return Err(ApiError::not_found(format!(
+            "no build logs recorded for {workflow_id}"
+        )));
+    }
+
+    let body = if query.follow {
+        Body::from_stream(follow_build_log(state, workflow_id, path, query.offset))
+    } else {
+        Body::from(initial.unwrap_or_default())
+    };
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body))
+}
+
+async fn require_workflow_access(
+    state: &AppState,
+    access: &Access,
+    workflow_id: &str,
+    role: Role,
+) -> Result<(), ApiError> {
+    let scope = workflows::workflow_scope(&state.client, workflow_id)
+        .await
+        .map_err(ApiError::internal)?;
+    let tenants = match scope {
+        WorkflowScope::Tenant(tenant) => vec![tenant],
+        WorkflowScope::SourceRepo { repo, environment } => {
+            let targets = state.gitops_index.targets_for(&repo, &environment).await;
+            if targets.is_empty() {
+                let owner = repo
+                    .split_once('/')
+                    .map_or(repo.as_str(), |(owner, _)| owner);
+                vec![owner.to_string()]
+            } else {
+                targets
+                    .into_iter()
+                    .map(|target| target.tenant)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect()
+            }
+        }
+        WorkflowScope::Platform => return access.require_all_tenants(role),
+    };
+    for tenant in &tenants {
+        access.require(tenant, role)?;
+    }
+    Ok(())
+}
+
+async fn read_build_log(path: &std::path::Path, offset: u64) -> std::io::Result<Option<Vec<u8>>> {
+    match fs::read(path).await {
+        Ok(bytes) => {
+            let offset = usize::try_from(offset)
+                .unwrap_or(usize::MAX)
+                .min(bytes.len());
+            Ok(Some(bytes[offset..].to_vec()))
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn follow_build_log(
+    state: AppState,
+    workflow_id: String,
+    path: PathBuf,
+    offset: u64,
+) -> impl futures_util::Stream<Item = std::io::Result<Bytes>> {
+    futures_util::stream::unfold(Some(offset), move |offset| {
+        let state = state.clone();
+        let workflow_id = workflow_id.clone();
+        let path = path.clone();
+        async move {
+            let mut offset = offset?;
+            loop {
+                let running =
+                    workflows::describe_workflow(&state.client, workflow_id.clone(), None)
+                        .await
+                        .map(|status| status.status == "running")
+                        .unwrap_or(false);
+                match read_build_log(&path, offset).await {
+                    Ok(Some(bytes)) if !bytes.is_empty() => {
+                        offset += bytes.len() as u64;
+                        return Some((Ok(Bytes::from(bytes)), Some(offset)));
+                    }
+                    Ok(_) if running => tokio::time::sleep(LOG_POLL_INTERVAL).await,
+                    Ok(_) => return None,
+                    Err(error) => return Some((Err(error), None)),
+                }
+            }
+        }
+    })
+}
+
+async fn approve_workflow(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath(workflow_id): AxumPath<String>,
+) -> Result<Json<WorkflowStatus>, ApiError> {
+    decide_workflow(state, headers, workflow_id, true).await
+}
+
+async fn reject_workflow(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath(workflow_id): AxumPath<String>,
+) -> Result<Json<WorkflowStatus>, ApiError> {
+    decide_workflow(state, headers, workflow_id, false).await
+}
+
+async fn decide_workflow(
+    state: AppState,
+    headers: HeaderMap,
+    workflow_id: String,
+    approved: bool,
+) -> Result<Json<WorkflowStatus>, ApiError> {
+    let (user, access) = state.authorize(&headers).await?;
+    let url =
+        state.config.temporal_web_url.as_ref().map(|base| {
+            temporal_workflow_url(base, &state.config.temporal_namespace, &workflow_id)
+        });
+    let status = workflows::describe_workflow(&state.client, workflow_id.clone(), url)
+        .await
+        .map_err(ApiError::internal)?;
+    let mut status = status;
+    let Some(approval) = status
+        .approval
+        .as_mut()
+        .filter(|approval| approval.is_pending())
+    else {
+        return Err(ApiError::bad_request(format!(
+            "{workflow_id} is not waiting for approval"
+        )));
+    };
+    if approval.tenants.is_empty() {
+        return Err(ApiError::conflict(format!(
+            "{workflow_id} has not resolved its tenants yet; try again shortly"
+        )));
+    }
+    for tenant in &approval.tenants {
+        access.require(tenant, Role::Deployer)?;
+    }
+
+    let approver = user_name(user);
+    workflows::signal_approval(
+        &state.client,
+        &workflow_id,
+        approved,
+        ApprovalDecision {
+            approver: approver.clone(),
+        },
+    )
+    .await
+    .map_err(ApiError::internal)?;
+    approval.state = if approved {
+        ApprovalState::Approved
+    } else {
+        ApprovalState::Rejected
+    };
+    approval.approver = Some(approver);
+
+    Ok(Json(status))
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.webhook_metrics.render(),
+    )
+}
+
 async fn handle_gitea_webhook(
     State(state): State<AppState>,
     headers: HeaderMap,
     body: Bytes,
 ) -> impl IntoResponse {
-    if !is_push_event(&headers) {
+    let secret = state.config.webhook_secret.clone();
+    handle_webhook(
+        &state,
+        WebhookProvider::Gitea,
+        secret.as_deref(),
+        &headers,
+        &body,
+    )
+    .await
+}
+
+async fn handle_github_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(secret) = state.config.github_webhook_secret.clone() else {
+        return StatusCode::NOT_FOUND;
+    };
+    handle_webhook(
+        &state,
+        WebhookProvider::Github,
+        Some(&secret),
+        &headers,
+        &body,
+    )
+    .await
+}
+
+async fn handle_gitlab_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(token) = state.config.gitlab_webhook_token.clone() else {
+        return StatusCode::NOT_FOUND;
+    };
+    handle_webhook(
+        &state,
+        WebhookProvider::Gitlab,
+        Some(&token),
+        &headers,
+        &body,
+    )
+    .await
+}
+
+async fn handle_webhook(
+    state: &AppState,
+    provider: WebhookProvider,
+    secret: Option<&str>,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> StatusCode {
+    if let Some(secret) = secret
+        && let Err(rejection) = provider.verify(secret, headers, body)
+    {
+        state.webhook_metrics.record(rejection);
+        warn!(
+            provider = provider.as_str(),
+            reason = rejection.as_str(),
+            "rejected webhook delivery"
+        );
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    if provider.is_pull_request_event(headers) {
+        return match provider.parse_pull_request(body) {
+            Ok(Some(event)) => handle_pull_request(state, event).await,
+            Ok(None) => StatusCode::NO_CONTENT,
+            Err(error) => {
+                error!(provider = provider.as_str(), error = %error, "invalid JSON payload");
+                StatusCode::BAD_REQUEST
+            }
+        };
+    }
+
+    if !provider.is_push_event(headers) {
         return StatusCode::NO_CONTENT;
     }
 
-    let payload: PushPayload = match serde_json::from_slice(&body) {
-        Ok(payload) => payload,
+    match provider.parse_push(body) {
+        Ok(event) => handle_push(state, event).await,
         Err(error) => {
-            error!(error = %error, "invalid JSON payload");
-            return StatusCode::BAD_REQUEST;
+            error!(provider = provider.as_str(), error = %error, "invalid JSON payload");
+            StatusCode::BAD_REQUEST
         }
-    };
+    }
+}
 
-    let owner = payload.repository.owner.username;
-    let repo_name = payload.repository.name;
-    let revision = payload.after;
-    let environment = app_environment(&payload.git_ref, &payload.repository.default_branch);
-    let source_repo = format!("{owner}/{repo_name}");
+async fn handle_push(state: &AppState, event: PushEvent) -> StatusCode {
+    let environment = app_environment(&event.git_ref, &event.default_branch);
+    let source_repo = event.source_repo();
 
     if state.config.gitops_repo.as_deref() == Some(source_repo.as_str()) {
         if let Err(error) = state.gitops_index.refresh_now().await {
@@ -601,6 +1388,11 @@ async fn handle_gitea_webhook(
         return StatusCode::NO_CONTENT;
     }
 
+    if validate_new_environment(&environment).is_err() {
+        info!(repo = %source_repo, environment = %environment, "push targets a reserved environment");
+        return StatusCode::NO_CONTENT;
+    }
+
     if state
         .gitops_index
         .targets_for(&source_repo, &environment)
@@ -615,19 +1407,25 @@ async fn handle_gitea_webhook(
         return StatusCode::NO_CONTENT;
     }
 
-    let workflow_id = deploy_workflow_id(&repo_name, &revision);
+    let workflow_id = deploy_workflow_id(&event.repo_name, &event.revision);
     let input = push_to_deploy_input(
         &state.config,
-        git_source(&owner, &repo_name, payload.repository.clone_url, &revision),
+        git_source(
+            &event.owner,
+            &event.repo_name,
+            event.clone_url.clone(),
+            &event.revision,
+        ),
         environment,
+        &event.origin,
         &source_repo,
-        &revision,
+        &event.revision,
         &workflow_id,
     );
 
     match workflows::start_workflow(&state.client, workflow_id, input).await {
         Ok(_) => {
-            info!(repo = %repo_name, rev = %revision, "push_to_deploy triggered");
+            info!(repo = %source_repo, rev = %event.revision, "push_to_deploy triggered");
             StatusCode::ACCEPTED
         }
         Err(error) => {
@@ -637,18 +1435,122 @@ async fn handle_gitea_webhook(
     }
 }
 
-fn is_push_event(headers: &HeaderMap) -> bool {
-    headers
-        .get("X-Gitea-Event")
-        .or_else(|| headers.get("X-Forgejo-Event"))
-        .and_then(|value| value.to_str().ok())
-        .is_some_and(|event| event.eq_ignore_ascii_case("push"))
+async fn handle_pull_request(state: &AppState, event: PullRequestEvent) -> StatusCode {
+    let Some(domain) = state.config.preview_domain.clone() else {
+        return StatusCode::NO_CONTENT;
+    };
+    let source_repo = event.source_repo();
+    let environment = preview_environment(event.number);
+
+    if event.action == PullRequestAction::Close {
+        let targets = state
+            .gitops_index
+            .targets_for(&source_repo, &environment)
+            .await;
+        if targets.is_empty() {
+            return StatusCode::NO_CONTENT;
+        }
+
+        for target in targets {
+            let request = DeleteAppRequest {
+                tenant: target.tenant,
+                project: target.project,
+                environment: target.environment,
+            };
+            let workflow_id = format!("delete-app-{}", sanitize(&request.app_path()));
+            let input = workflows::delete_app::DeleteAppInput {
+                gitops_url: state.config.gitops_url.clone(),
+                gitops_revision: state.config.gitops_revision.clone(),
+                registry: state.config.registry.clone(),
+                request,
+            };
+            if let Err(error) =
+                workflows::start_delete_app_workflow(&state.client, workflow_id, input).await
+            {
+                error!(error = %error, "failed to start preview teardown workflow");
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+        }
+
+        info!(repo = %source_repo, environment = %environment, "preview teardown triggered");
+        return StatusCode::ACCEPTED;
+    }
+
+    if event.fork {
+        info!(repo = %source_repo, number = event.number, "skipping preview for forked pull request");
+        return StatusCode::NO_CONTENT;
+    }
+
+    if state
+        .gitops_index
+        .targets_for(&source_repo, PREVIEW_BASE_ENVIRONMENT)
+        .await
+        .is_empty()
+    {
+        info!(repo = %source_repo, "pull request has no production environment to preview");
+        return StatusCode::NO_CONTENT;
+    }
+
+    let workflow_id = preview_workflow_id(&event.repo_name, event.number, &event.head_sha);
+    let input = PreviewInput {
+        source: git_source(
+            &event.owner,
+            &event.repo_name,
+            event.clone_url.clone(),
+            &event.head_sha,
+        ),
+        number: event.number,
+        domain,
+        gitops_url: state.config.gitops_url.clone(),
+        gitops_revision: state.config.gitops_revision.clone(),
+        registry: state.config.registry.clone(),
+        commit_status: commit_status(
+            &state.config,
+            &event.origin,
+            &source_repo,
+            &event.head_sha,
+            &workflow_id,
+        ),
+        pull_request: pull_request_target(&state.config, &event),
+    };
+
+    match workflows::start_preview_workflow(&state.client, workflow_id, input).await {
+        Ok(_) => {
+            info!(repo = %source_repo, environment = %environment, "preview deploy triggered");
+            StatusCode::ACCEPTED
+        }
+        Err(error) => {
+            error!(error = %error, "failed to start preview workflow");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn pull_request_target(config: &AppConfig, event: &PullRequestEvent) -> Option<PullRequestTarget> {
+    Some(match event.origin {
+        PushOrigin::Forgejo => PullRequestTarget::Forgejo {
+            forgejo_url: config.forgejo_url.clone()?,
+            repo: event.source_repo(),
+            number: event.number,
+        },
+        PushOrigin::Github => PullRequestTarget::Github {
+            api_url: config.github_api_url.clone(),
+            repo: event.source_repo(),
+            number: event.number,
+        },
+        PushOrigin::Gitlab { project_id } => PullRequestTarget::Gitlab {
+            api_url: config.gitlab_url.clone(),
+            project_id,
+            number: event.number,
+        },
+    })
 }
 
 fn push_to_deploy_input(
     config: &AppConfig,
     source: Source,
     environment: String,
+    origin: &PushOrigin,
     repo: &str,
     revision: &str,
     workflow_id: &str,
@@ -657,30 +1559,44 @@ fn push_to_deploy_input(
         source,
         gitops_url: config.gitops_url.clone(),
         gitops_revision: config.gitops_revision.clone(),
-        environment,
         registry: config.registry.clone(),
-        commit_status: commit_status(config, repo, revision, workflow_id),
+        commit_status: commit_status(config, origin, repo, revision, workflow_id),
+        requires_approval: config.approval_environments.contains(&environment),
+        image_scan: config.image_scan_policies.get(&environment).copied(),
+        environment,
     }
 }
 
 fn commit_status(
     config: &AppConfig,
+    origin: &PushOrigin,
     repo: &str,
     sha: &str,
     workflow_id: &str,
-) -> Option<ForgejoCommitStatusTarget> {
-    let forgejo_url = config.forgejo_url.as_ref()?;
+) -> Option<CommitStatusTarget> {
     let temporal_web_url = config.temporal_web_url.as_ref()?;
-
-    Some(ForgejoCommitStatusTarget {
-        forgejo_url: forgejo_url.clone(),
-        repo: repo.to_string(),
-        sha: sha.to_string(),
-        target_url: temporal_workflow_url(
-            temporal_web_url,
-            &config.temporal_namespace,
-            workflow_id,
-        ),
+    let target_url =
+        temporal_workflow_url(temporal_web_url, &config.temporal_namespace, workflow_id);
+
+    Some(match origin {
+        PushOrigin::Forgejo => CommitStatusTarget::Forgejo(ForgejoCommitStatusTarget {
+            forgejo_url: config.forgejo_url.clone()?,
+            repo: repo.to_string(),
+            sha: sha.to_string(),
+            target_url,
+        }),
+        PushOrigin::Github => CommitStatusTarget::Github(GithubCommitStatusTarget {
+            api_url: config.github_api_url.clone(),
+            repo: repo.to_string(),
+            sha: sha.to_string(),
+            target_url,
+        }),
+        PushOrigin::Gitlab { project_id } => CommitStatusTarget::Gitlab(GitlabCommitStatusTarget {
+            api_url: config.gitlab_url.clone(),
+            project_id: *project_id,
+            sha: sha.to_string(),
+            target_url,
+        }),
     })
 }
 
@@ -694,6 +1610,15 @@ fn git_source(owner: &str, repo_name: &str, url: String, revision: &str) -> Sour
     }
 }
 
+fn history_pathspec(query: &HistoryQuery) -> String {
+    format!(
+        ":(glob)apps/{}/{}/{}/**",
+        query.tenant.as_deref().unwrap_or("*"),
+        query.project.as_deref().unwrap_or("*"),
+        query.environment.as_deref().unwrap_or("*"),
+    )
+}
+
 fn index_targets(targets: Vec<AppSourceTarget>) -> BTreeMap<(String, String), Vec<AppTarget>> {
     let mut index: BTreeMap<(String, String), Vec<AppTarget>> = BTreeMap::new();
     for mapping in targets {
@@ -760,6 +1685,10 @@ fn git_credentials() -> (String, String) {
 }
 
 async fn run_checked_command(command: &mut Command, operation: &str) -> Result<()> {
+    run_stdout_command(command, operation).await.map(|_| ())
+}
+
+async fn run_stdout_command(command: &mut Command, operation: &str) -> Result<String> {
     command
         .kill_on_drop(true)
         .stdout(Stdio::piped())
@@ -769,7 +1698,7 @@ async fn run_checked_command(command: &mut Command, operation: &str) -> Result<(
         .await
         .with_context(|| format!("failed to start {operation}"))?;
     if output.status.success() {
-        return Ok(());
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
     }
 
     Err(anyhow!(
@@ -779,6 +1708,28 @@ async fn run_checked_command(command: &mut Command, operation: &str) -> Result<(
     ))
 }
 
+async fn run_stdin_command(command: &mut Command, stdin: &[u8], operation: &str) -> Result<String> {
+    let mut child = command
+        .kill_on_drop(true)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start {operation}"))?;
+    if let Some(mut input) = child.stdin.take() {
+        input.write_all(stdin).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+
+    Err(anyhow!(
+        "{operation} failed\n{}",
+        String::from_utf8_lossy(&output.stderr),
+    ))
+}
+
 fn repo_slug_from_git_url(url: &str) -> Option<String> {
     let url = url.trim_end_matches(".git");
     let path = if let Some((_, rest)) = url.split_once("://") {
@@ -821,6 +1772,30 @@ fn parse_duration(value: &str) -> Option<Duration> {
         .map(|seconds| Duration::from_secs(seconds * multiplier))
 }
 
+fn parse_list(value: &str) -> BTreeSet<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn parse_image_scan_policies(value: &str) -> Result<BTreeMap<String, ImageScanPolicy>> {
+    parse_list(value)
+        .into_iter()
+        .map(|item| {
+            let (environment, policy) = item.split_once('=').ok_or_else(|| {
+                anyhow!("NETAMOS_IMAGE_SCAN_THRESHOLDS entry {item:?} must be environment=severity")
+            })?;
+            let policy = policy
+                .parse()
+                .map_err(|error| anyhow!("NETAMOS_IMAGE_SCAN_THRESHOLDS: {error}"))?;
+            Ok((environment.trim().to_string(), policy))
+        })
+        .collect()
+}
+
 fn sanitize(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     for ch in input.chars() {
@@ -870,9 +1845,11 @@ mod tests {
     use std::time::Duration;
 
     use super::{
-        AppSourceTarget, AppTarget, app_environment, index_targets, parse_duration,
-        repo_slug_from_git_url, temporal_workflow_url,
+        AppSourceTarget, AppTarget, HistoryQuery, app_environment, history_pathspec, index_targets,
+        parse_duration, parse_image_scan_policies, parse_list, repo_slug_from_git_url,
+        temporal_workflow_url,
     };
+    use crate::activities::Severity;
 
     #[test]
     fn app_environment_uses_production_for_default_branch() {
@@ -926,6 +1903,43 @@ mod tests {
         assert_eq!(parse_duration("bad"), None);
     }
 
+    #[test]
+    fn parse_list_ignores_blank_items() {
+        assert_eq!(
+            parse_list(" production, ,staging "),
+            ["production", "staging"]
+                .into_iter()
+                .map(ToString::to_string)
+                .collect()
+        );
+        assert!(parse_list("").is_empty());
+    }
+
+    #[test]
+    fn parse_image_scan_policies_by_environment() {
+        let policies = parse_image_scan_policies("production=high, staging=none").unwrap();
+        assert_eq!(policies["production"].fail_on, Some(Severity::High));
+        assert_eq!(policies["staging"].fail_on, None);
+        assert!(parse_image_scan_policies("production").is_err());
+        assert!(parse_image_scan_policies("production=severe").is_err());
+    }
+
+    #[test]
+    fn history_pathspec_defaults_to_every_app() {
+        assert_eq!(
+            history_pathspec(&HistoryQuery::default()),
+            ":(glob)apps/*/*/*/**"
+        );
+        assert_eq!(
+            history_pathspec(&HistoryQuery {
+                tenant: Some("khuedoan".to_string()),
+                project: None,
+                environment: Some("production".to_string()),
+            }),
+            ":(glob)apps/khuedoan/*/production/**"
+        );
+    }
+
     #[test]
     fn index_targets_groups_by_source_repo_and_environment() {
         let index = index_targets(vec![
@@ -0,0 +1,234 @@
+use std::{collections::BTreeMap, env, path::PathBuf};
+
+use super::{ApiError, GitopsIndex};
+use crate::api::UserInfo;
+use serde::Deserialize;
+use tokio::fs;
+
+const ALL_TENANTS: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Role {
+    Viewer,
+    Deployer,
+    Admin,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Deployer => "deployer",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AuthzPolicy {
+    #[serde(default)]
+    bindings: Vec<Binding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Binding {
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    role: Role,
+    tenants: Vec<String>,
+}
+
+impl Binding {
+    fn matches(&self, user: &UserInfo) -> bool {
+        let group_matches = self
+            .group
+            .as_ref()
+            .is_some_and(|group| user.groups.contains(group));
+        let user_matches = self.user.as_ref().is_some_and(|name| {
+            user.username.as_ref() == Some(name)
+                || user.email.as_ref() == Some(name)
+                || &user.subject == name
+        });
+
+        group_matches || user_matches
+    }
+}
+
+impl AuthzPolicy {
+    fn access(&self, user: &UserInfo) -> Access {
+        let mut access = Access::default();
+        for binding in self.bindings.iter().filter(|binding| binding.matches(user)) {
+            for tenant in &binding.tenants {
+                let role = if tenant == ALL_TENANTS {
+                    &mut access.all
+                } else {
+                    access.tenants.entry(tenant.clone()).or_default()
+                };
+                *role = (*role).max(Some(binding.role));
+            }
+        }
+
+        access
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Access {
+    all: Option<Role>,
+    tenants: BTreeMap<String, Option<Role>>,
+}
+
+impl Access {
+    fn unrestricted() -> Self {
+        Self {
+            all: Some(Role::Admin),
+            tenants: BTreeMap::new(),
+        }
+    }
+
+    pub(super) fn can(&self, tenant: &str, role: Role) -> bool {
+        self.all
+            .max(self.tenants.get(tenant).copied().flatten())
+            .is_some_and(|granted| granted >= role)
+    }
+
+    pub(super) fn require(&self, tenant: &str, role: Role) -> Result<(), ApiError> {
+        if self.can(tenant, role) {
+            return Ok(());
+        }
+
+        Err(ApiError::forbidden(format!(
+            "{} role is required for tenant {tenant}",
+            role.as_str()
+        )))
+    }
+
+    pub(super) fn require_all_tenants(&self, role: Role) -> Result<(), ApiError> {
+        if self.all.is_some_and(|granted| granted >= role) {
+            return Ok(());
+        }
+
+        Err(ApiError::forbidden(format!(
+            "{} role is required for all tenants",
+            role.as_str()
+        )))
+    }
+}
+
+pub(super) enum Authorizer {
+    Unrestricted,
+    File(PathBuf),
+    Gitops(PathBuf),
+}
+
+impl Authorizer {
+    pub(super) fn from_env() -> Self {
+        if let Ok(path) = env::var("NETAMOS_AUTHZ_FILE") {
+            Self::File(PathBuf::from(path))
+        } else if let Ok(path) = env::var("NETAMOS_AUTHZ_GITOPS_PATH") {
+            Self::Gitops(PathBuf::from(path))
+        } else {
+            Self::Unrestricted
+        }
+    }
+
+    pub(super) async fn access(
+        &self,
+        gitops_index: &GitopsIndex,
+        user: &UserInfo,
+    ) -> Result<Access, ApiError> {
+        let path = match self {
+            Self::Unrestricted => return Ok(Access::unrestricted()),
+            Self::File(path) => path.clone(),
+            Self::Gitops(path) => {
+                gitops_index
+                    .refresh_if_stale()
+                    .await
+                    .map_err(ApiError::internal)?;
+                gitops_index.config.cache_dir.join(path)
+            }
+        };
+
+        let policy = fs::read_to_string(&path).await.map_err(|error| {
+            ApiError::unavailable(format!(
+                "failed to read authorization policy {}: {error}",
+                path.display()
+            ))
+        })?;
+        let policy = parse_policy(&policy).map_err(|error| {
+            ApiError::unavailable(format!(
+                "invalid authorization policy {}: {error}",
+                path.display()
+            ))
+        })?;
+
+        Ok(policy.access(user))
+    }
+}
+
+fn parse_policy(policy: &str) -> anyhow::Result<AuthzPolicy> {
+    Ok(yaml_serde::from_str(policy)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(username: &str, groups: &[&str]) -> UserInfo {
+        UserInfo {
+            subject: format!("{username}-id"),
+            username: Some(username.to_string()),
+            email: None,
+            groups: groups.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn policy() -> AuthzPolicy {
+        parse_policy(
+            r#"bindings:
+  - group: platform-admins
+    role: admin
+    tenants: ["*"]
+  - user: alice
+    role: deployer
+    tenants: [khuedoan]
+  - group: developers
+    role: viewer
+    tenants: [khuedoan, example]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn access_uses_highest_matching_role_per_tenant() {
+        let access = policy().access(&user("alice", &["developers"]));
+
+        assert!(access.can("khuedoan", Role::Deployer));
+        assert!(!access.can("khuedoan", Role::Admin));
+        assert!(access.can("example", Role::Viewer));
+        assert!(!access.can("example", Role::Deployer));
+        assert!(!access.can("other", Role::Viewer));
+        assert!(access.require("khuedoan", Role::Deployer).is_ok());
+        assert!(access.require_all_tenants(Role::Viewer).is_err());
+    }
+
+    #[test]
+    fn access_wildcard_covers_every_tenant() {
+        let access = policy().access(&user("bob", &["platform-admins"]));
+
+        assert!(access.can("anything", Role::Admin));
+        assert!(access.require_all_tenants(Role::Admin).is_ok());
+    }
+
+    #[test]
+    fn access_without_bindings_is_denied() {
+        let access = policy().access(&user("mallory", &[]));
+
+        assert!(!access.can("khuedoan", Role::Viewer));
+        assert!(access.require_all_tenants(Role::Viewer).is_err());
+    }
+}
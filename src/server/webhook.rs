@@ -0,0 +1,754 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+const SIGNATURE_HEADERS: [&str; 2] = ["X-Forgejo-Signature", "X-Gitea-Signature"];
+const GITHUB_SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+const GITLAB_TOKEN_HEADER: &str = "X-Gitlab-Token";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum WebhookProvider {
+    Gitea,
+    Github,
+    Gitlab,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum PushOrigin {
+    Forgejo,
+    Github,
+    Gitlab { project_id: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PushEvent {
+    pub(super) origin: PushOrigin,
+    pub(super) owner: String,
+    pub(super) repo_name: String,
+    pub(super) revision: String,
+    pub(super) git_ref: String,
+    pub(super) default_branch: String,
+    pub(super) clone_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PullRequestAction {
+    Sync,
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PullRequestEvent {
+    pub(super) origin: PushOrigin,
+    pub(super) owner: String,
+    pub(super) repo_name: String,
+    pub(super) number: u64,
+    pub(super) action: PullRequestAction,
+    pub(super) head_sha: String,
+    pub(super) clone_url: String,
+    pub(super) fork: bool,
+}
+
+impl PullRequestEvent {
+    pub(super) fn source_repo(&self) -> String {
+        format!("{}/{}", self.owner, self.repo_name)
+    }
+}
+
+impl PushEvent {
+    pub(super) fn source_repo(&self) -> String {
+        format!("{}/{}", self.owner, self.repo_name)
+    }
+}
+
+#[derive(Deserialize)]
+struct GiteaOwner {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaRepository {
+    name: String,
+    owner: GiteaOwner,
+    #[serde(default)]
+    default_branch: String,
+    #[serde(default)]
+    clone_url: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaPushPayload {
+    after: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    repository: GiteaRepository,
+}
+
+#[derive(Deserialize)]
+struct PullRequestRepo {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct PullRequestBranch {
+    #[serde(default)]
+    sha: String,
+    #[serde(default)]
+    repo_id: Option<u64>,
+    #[serde(default)]
+    repo: Option<PullRequestRepo>,
+}
+
+impl PullRequestBranch {
+    fn repo_id(&self) -> Option<u64> {
+        self.repo_id.or(self.repo.as_ref().map(|repo| repo.id))
+    }
+}
+
+#[derive(Deserialize)]
+struct PullRequestInfo {
+    head: PullRequestBranch,
+    #[serde(default)]
+    base: Option<PullRequestBranch>,
+}
+
+impl PullRequestInfo {
+    fn is_fork(&self) -> bool {
+        is_fork(
+            self.head.repo_id(),
+            self.base.as_ref().and_then(PullRequestBranch::repo_id),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct GiteaPullRequestPayload {
+    action: String,
+    number: u64,
+    pull_request: PullRequestInfo,
+    repository: GiteaRepository,
+}
+
+#[derive(Deserialize)]
+struct GithubOwner {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GithubRepository {
+    name: String,
+    owner: GithubOwner,
+    #[serde(default)]
+    default_branch: String,
+    #[serde(default)]
+    clone_url: String,
+}
+
+#[derive(Deserialize)]
+struct GithubPushPayload {
+    after: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    repository: GithubRepository,
+}
+
+#[derive(Deserialize)]
+struct GithubPullRequestPayload {
+    action: String,
+    number: u64,
+    pull_request: PullRequestInfo,
+    repository: GithubRepository,
+}
+
+#[derive(Deserialize)]
+struct GitlabProject {
+    #[serde(default)]
+    id: Option<u64>,
+    path_with_namespace: String,
+    #[serde(default)]
+    default_branch: String,
+    #[serde(default)]
+    git_http_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabPushPayload {
+    after: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    project_id: u64,
+    project: GitlabProject,
+}
+
+#[derive(Deserialize)]
+struct GitlabLastCommit {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabMergeRequestAttributes {
+    iid: u64,
+    #[serde(default)]
+    action: String,
+    last_commit: GitlabLastCommit,
+    #[serde(default)]
+    source_project_id: Option<u64>,
+    #[serde(default)]
+    target_project_id: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct GitlabMergeRequestPayload {
+    object_attributes: GitlabMergeRequestAttributes,
+    project: GitlabProject,
+}
+
+impl WebhookProvider {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Gitea => "gitea",
+            Self::Github => "github",
+            Self::Gitlab => "gitlab",
+        }
+    }
+
+    pub(super) fn is_push_event(self, headers: &HeaderMap) -> bool {
+        let expected = match self {
+            Self::Gitea | Self::Github => "push",
+            Self::Gitlab => "Push Hook",
+        };
+
+        self.event_name(headers)
+            .is_some_and(|event| event.eq_ignore_ascii_case(expected))
+    }
+
+    pub(super) fn is_pull_request_event(self, headers: &HeaderMap) -> bool {
+        let expected = match self {
+            Self::Gitea | Self::Github => "pull_request",
+            Self::Gitlab => "Merge Request Hook",
+        };
+
+        self.event_name(headers)
+            .is_some_and(|event| event.eq_ignore_ascii_case(expected))
+    }
+
+    fn event_name(self, headers: &HeaderMap) -> Option<&str> {
+        let names: &[&str] = match self {
+            Self::Gitea => &["X-Gitea-Event", "X-Forgejo-Event"],
+            Self::Github => &["X-GitHub-Event"],
+            Self::Gitlab => &["X-Gitlab-Event"],
+        };
+
+        names.iter().find_map(|name| header_value(headers, name))
+    }
+
+    pub(super) fn verify(
+        self,
+        secret: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), SignatureRejection> {
+        match self {
+            Self::Gitea => verify_signature(secret, headers, body),
+            Self::Github => {
+                let signature = header_value(headers, GITHUB_SIGNATURE_HEADER)
+                    .ok_or(SignatureRejection::Missing)?;
+                let signature = signature
+                    .strip_prefix("sha256=")
+                    .ok_or(SignatureRejection::Invalid)?;
+                verify_hmac(secret, signature, body)
+            }
+            Self::Gitlab => {
+                let token = header_value(headers, GITLAB_TOKEN_HEADER)
+                    .ok_or(SignatureRejection::Missing)?;
+                if constant_time_eq(token.as_bytes(), secret.as_bytes()) {
+                    Ok(())
+                } else {
+                    Err(SignatureRejection::Invalid)
+                }
+            }
+        }
+    }
+
+    pub(super) fn parse_push(self, body: &[u8]) -> serde_json::Result<PushEvent> {
+        match self {
+            Self::Gitea => {
+                let payload: GiteaPushPayload = serde_json::from_slice(body)?;
+                Ok(PushEvent {
+                    origin: PushOrigin::Forgejo,
+                    owner: payload.repository.owner.username,
+                    repo_name: payload.repository.name,
+                    revision: payload.after,
+                    git_ref: payload.git_ref,
+                    default_branch: payload.repository.default_branch,
+                    clone_url: payload.repository.clone_url,
+                })
+            }
+            Self::Github => {
+                let payload: GithubPushPayload = serde_json::from_slice(body)?;
+                Ok(PushEvent {
+                    origin: PushOrigin::Github,
+                    owner: payload.repository.owner.login,
+                    repo_name: payload.repository.name,
+                    revision: payload.after,
+                    git_ref: payload.git_ref,
+                    default_branch: payload.repository.default_branch,
+                    clone_url: payload.repository.clone_url,
+                })
+            }
+            Self::Gitlab => {
+                let payload: GitlabPushPayload = serde_json::from_slice(body)?;
+                let (owner, repo_name) = split_gitlab_path(&payload.project.path_with_namespace)?;
+                Ok(PushEvent {
+                    origin: PushOrigin::Gitlab {
+                        project_id: payload.project_id,
+                    },
+                    owner,
+                    repo_name,
+                    revision: payload.after,
+                    git_ref: payload.git_ref,
+                    default_branch: payload.project.default_branch,
+                    clone_url: payload.project.git_http_url,
+                })
+            }
+        }
+    }
+
+    pub(super) fn parse_pull_request(
+        self,
+        body: &[u8],
+    ) -> serde_json::Result<Option<PullRequestEvent>> {
+        let event = match self {
+            Self::Gitea => {
+                let payload: GiteaPullRequestPayload = serde_json::from_slice(body)?;
+                pull_request_action(&payload.action).map(|action| PullRequestEvent {
+                    origin: PushOrigin::Forgejo,
+                    owner: payload.repository.owner.username,
+                    repo_name: payload.repository.name,
+                    number: payload.number,
+                    action,
+                    fork: payload.pull_request.is_fork(),
+                    head_sha: payload.pull_request.head.sha,
+                    clone_url: payload.repository.clone_url,
+                })
+            }
+            Self::Github => {
+                let payload: GithubPullRequestPayload = serde_json::from_slice(body)?;
+                pull_request_action(&payload.action).map(|action| PullRequestEvent {
+                    origin: PushOrigin::Github,
+                    owner: payload.repository.owner.login,
+                    repo_name: payload.repository.name,
+                    number: payload.number,
+                    action,
+                    fork: payload.pull_request.is_fork(),
+                    head_sha: payload.pull_request.head.sha,
+                    clone_url: payload.repository.clone_url,
+                })
+            }
+            Self::Gitlab => {
+                let payload: GitlabMergeRequestPayload = serde_json::from_slice(body)?;
+                let project_id = payload
+                    .project
+                    .id
+                    .ok_or_else(|| serde::de::Error::missing_field("project.id"))?;
+                let (owner, repo_name) = split_gitlab_path(&payload.project.path_with_namespace)?;
+                let fork = is_fork(
+                    payload.object_attributes.source_project_id,
+                    payload.object_attributes.target_project_id,
+                );
+                pull_request_action(&payload.object_attributes.action).map(|action| {
+                    PullRequestEvent {
+                        origin: PushOrigin::Gitlab { project_id },
+                        owner,
+                        repo_name,
+                        number: payload.object_attributes.iid,
+                        action,
+                        head_sha: payload.object_attributes.last_commit.id,
+                        clone_url: payload.project.git_http_url,
+                        fork,
+                    }
+                })
+            }
+        };
+
+        Ok(event)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SignatureRejection {
+    Missing,
+    Invalid,
+}
+
+impl SignatureRejection {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Missing => "missing_signature",
+            Self::Invalid => "invalid_signature",
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct WebhookMetrics {
+    missing_signature: AtomicU64,
+    invalid_signature: AtomicU64,
+}
+
+impl WebhookMetrics {
+    pub(super) fn record(&self, rejection: SignatureRejection) {
+        let counter = match rejection {
+            SignatureRejection::Missing => &self.missing_signature,
+            SignatureRejection::Invalid => &self.invalid_signature,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn render(&self) -> String {
+        let mut output = String::from(
+            "# HELP netamos_webhook_rejected_total Webhook deliveries rejected by signature verification.\n\
+             # TYPE netamos_webhook_rejected_total counter\n",
+        );
+        for (reason, counter) in [
+            (SignatureRejection::Missing, &self.missing_signature),
+            (SignatureRejection::Invalid, &self.invalid_signature),
+        ] {
+            output.push_str(&format!(
+                "netamos_webhook_rejected_total{{reason=\"{}\"}} {}\n",
+                reason.as_str(),
+                counter.load(Ordering::Relaxed)
+            ));
+        }
+
+        output
+    }
+}
+
+fn pull_request_action(action: &str) -> Option<PullRequestAction> {
+    match action {
+        "opened" | "reopened" | "synchronize" | "synchronized" | "open" | "reopen" | "update" => {
+            Some(PullRequestAction::Sync)
+        }
+        "closed" | "close" | "merge" => Some(PullRequestAction::Close),
+        _ => None,
+    }
+}
+
+fn is_fork(head_repo_id: Option<u64>, base_repo_id: Option<u64>) -> bool {
+    match (head_repo_id, base_repo_id) {
+        (Some(head), Some(base)) => head != base,
+        _ => true,
+    }
+}
+
+fn split_gitlab_path(path_with_namespace: &str) -> serde_json::Result<(String, String)> {
+    let (owner, name) = path_with_namespace
+        .split_once('/')
+        .ok_or_else(|| serde::de::Error::custom("path_with_namespace must contain a namespace"))?;
+    if name.contains('/') {
+        return Err(serde::de::Error::custom(
+            "nested GitLab namespaces are not supported",
+        ));
+    }
+    Ok((owner.to_string(), name.to_string()))
+}
+
+pub(super) fn verify_signature(
+    secret: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), SignatureRejection> {
+    let signature = SIGNATURE_HEADERS
+        .iter()
+        .find_map(|name| header_value(headers, name))
+        .ok_or(SignatureRejection::Missing)?;
+    verify_hmac(secret, signature, body)
+}
+
+fn verify_hmac(secret: &str, signature: &str, body: &[u8]) -> Result<(), SignatureRejection> {
+    let signature = hex::decode(signature.trim()).map_err(|_| SignatureRejection::Invalid)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|_| SignatureRejection::Invalid)?;
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| SignatureRejection::Invalid)
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0, |diff, (left, right)| diff | (left ^ right))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn verify_signature_accepts_gitea_and_forgejo_headers() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        for header in SIGNATURE_HEADERS {
+            let mut headers = HeaderMap::new();
+            headers.insert(header, sign("secret", body).parse().unwrap());
+
+            assert_eq!(verify_signature("secret", &headers, body), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verify_signature_rejects_missing_and_wrong_signatures() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        assert_eq!(
+            verify_signature("secret", &HeaderMap::new(), body),
+            Err(SignatureRejection::Missing)
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Gitea-Signature", sign("other", body).parse().unwrap());
+        assert_eq!(
+            verify_signature("secret", &headers, body),
+            Err(SignatureRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn github_verify_requires_sha256_prefixed_signature() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            GITHUB_SIGNATURE_HEADER,
+            format!("sha256={}", sign("secret", body)).parse().unwrap(),
+        );
+        assert_eq!(
+            WebhookProvider::Github.verify("secret", &headers, body),
+            Ok(())
+        );
+
+        headers.insert(
+            GITHUB_SIGNATURE_HEADER,
+            sign("secret", body).parse().unwrap(),
+        );
+        assert_eq!(
+            WebhookProvider::Github.verify("secret", &headers, body),
+            Err(SignatureRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn gitlab_verify_compares_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            WebhookProvider::Gitlab.verify("token", &headers, b"{}"),
+            Err(SignatureRejection::Missing)
+        );
+
+        headers.insert(GITLAB_TOKEN_HEADER, "wrong".parse().unwrap());
+        assert_eq!(
+            WebhookProvider::Gitlab.verify("token", &headers, b"{}"),
+            Err(SignatureRejection::Invalid)
+        );
+
+        headers.insert(GITLAB_TOKEN_HEADER, "token".parse().unwrap());
+        assert_eq!(
+            WebhookProvider::Gitlab.verify("token", &headers, b"{}"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn is_push_event_matches_provider_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-GitHub-Event", "push".parse().unwrap());
+        assert!(WebhookProvider::Github.is_push_event(&headers));
+        assert!(!WebhookProvider::Gitea.is_push_event(&headers));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Gitlab-Event", "Push Hook".parse().unwrap());
+        assert!(WebhookProvider::Gitlab.is_push_event(&headers));
+    }
+
+    #[test]
+    fn parse_push_normalizes_github_payload() {
+        let body = br#"{
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "repository": {
+                "name": "blog",
+                "owner": {"login": "khuedoan"},
+                "default_branch": "main",
+                "clone_url": "https://github.com/khuedoan/blog.git"
+            }
+        }"#;
+
+        let event = WebhookProvider::Github.parse_push(body).unwrap();
+        assert_eq!(event.origin, PushOrigin::Github);
+        assert_eq!(event.source_repo(), "khuedoan/blog");
+        assert_eq!(event.revision, "abc123");
+        assert_eq!(event.default_branch, "main");
+        assert_eq!(event.clone_url, "https://github.com/khuedoan/blog.git");
+    }
+
+    #[test]
+    fn parse_push_normalizes_gitlab_payload() {
+        let body = br#"{
+            "object_kind": "push",
+            "ref": "refs/heads/staging",
+            "after": "def456",
+            "project_id": 42,
+            "project": {
+                "path_with_namespace": "group/api",
+                "default_branch": "main",
+                "git_http_url": "https://gitlab.com/group/api.git"
+            }
+        }"#;
+
+        let event = WebhookProvider::Gitlab.parse_push(body).unwrap();
+        assert_eq!(event.origin, PushOrigin::Gitlab { project_id: 42 });
+        assert_eq!(event.owner, "group");
+        assert_eq!(event.repo_name, "api");
+        assert_eq!(event.git_ref, "refs/heads/staging");
+        assert_eq!(event.clone_url, "https://gitlab.com/group/api.git");
+    }
+
+    #[test]
+    fn parse_push_rejects_nested_gitlab_namespaces() {
+        let body = br#"{
+            "object_kind": "push",
+            "ref": "refs/heads/main",
+            "after": "def456",
+            "project_id": 42,
+            "project": {
+                "path_with_namespace": "group/subgroup/api",
+                "default_branch": "main",
+                "git_http_url": "https://gitlab.com/group/subgroup/api.git"
+            }
+        }"#;
+
+        assert!(WebhookProvider::Gitlab.parse_push(body).is_err());
+    }
+
+    #[test]
+    fn parse_pull_request_maps_actions() {
+        let body = |action: &str| {
+            format!(
+                r#"{{
+                    "action": "{action}",
+                    "number": 7,
+                    "pull_request": {{
+                        "head": {{"sha": "abc123", "ref": "feature", "repo_id": 1}},
+                        "base": {{"sha": "def456", "ref": "main", "repo_id": 1}}
+                    }},
+                    "repository": {{
+                        "name": "blog",
+                        "owner": {{"username": "khuedoan"}},
+                        "clone_url": "http://forgejo/khuedoan/blog.git"
+                    }}
+                }}"#
+            )
+        };
+
+        let event = WebhookProvider::Gitea
+            .parse_pull_request(body("synchronized").as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.action, PullRequestAction::Sync);
+        assert_eq!(event.number, 7);
+        assert_eq!(event.head_sha, "abc123");
+        assert_eq!(event.source_repo(), "khuedoan/blog");
+        assert!(!event.fork);
+
+        let event = WebhookProvider::Gitea
+            .parse_pull_request(body("closed").as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.action, PullRequestAction::Close);
+
+        assert_eq!(
+            WebhookProvider::Gitea
+                .parse_pull_request(body("labeled").as_bytes())
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_pull_request_normalizes_gitlab_merge_request() {
+        let body = br#"{
+            "object_kind": "merge_request",
+            "object_attributes": {
+                "iid": 3,
+                "action": "merge",
+                "last_commit": {"id": "def456"},
+                "source_project_id": 42,
+                "target_project_id": 42
+            },
+            "project": {
+                "id": 42,
+                "path_with_namespace": "group/api",
+                "git_http_url": "https://gitlab.com/group/api.git"
+            }
+        }"#;
+
+        let event = WebhookProvider::Gitlab
+            .parse_pull_request(body)
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.origin, PushOrigin::Gitlab { project_id: 42 });
+        assert_eq!(event.action, PullRequestAction::Close);
+        assert_eq!(event.number, 3);
+        assert_eq!(event.head_sha, "def456");
+        assert!(!event.fork);
+    }
+
+    #[test]
+    fn parse_pull_request_flags_forks() {
+        let body = br#"{
+            "action": "opened",
+            "number": 9,
+            "pull_request": {
+                "head": {"sha": "abc123", "repo": {"id": 2}},
+                "base": {"sha": "def456", "repo": {"id": 1}}
+            },
+            "repository": {
+                "name": "blog",
+                "owner": {"login": "khuedoan"},
+                "clone_url": "https://github.com/khuedoan/blog.git"
+            }
+        }"#;
+
+        let event = WebhookProvider::Github
+            .parse_pull_request(body)
+            .unwrap()
+            .unwrap();
+        assert!(event.fork);
+    }
+
+    #[test]
+    fn webhook_metrics_render_counts_by_reason() {
+        let metrics = WebhookMetrics::default();
+        metrics.record(SignatureRejection::Invalid);
+        metrics.record(SignatureRejection::Invalid);
+
+        let output = metrics.render();
+        assert!(output.contains("netamos_webhook_rejected_total{reason=\"missing_signature\"} 0"));
+        assert!(output.contains("netamos_webhook_rejected_total{reason=\"invalid_signature\"} 2"));
+    }
+}
@@ -13,6 +13,7 @@ pub async fn run() -> Result<()> {
         .without_time()
         .init();
 
+    prepare_registry_auth()?;
     let client = temporal::get_client().await?;
     let task_queue = env::var("TASK_QUEUE").unwrap_or_else(|_| "main".to_string());
     let worker_identity = worker_identity();
@@ -39,7 +40,12 @@ pub async fn run() -> Result<()> {
             .register_workflow::<workflows::add_app::AddAppWorkflow>()
             .register_workflow::<workflows::create_app::CreateAppWorkflow>()
             .register_workflow::<workflows::delete_app::DeleteAppWorkflow>()
+            .register_workflow::<workflows::preview::PreviewWorkflow>()
+            .register_workflow::<workflows::promote::PromoteWorkflow>()
             .register_workflow::<workflows::push_to_deploy::PushToDeployWorkflow>()
+            .register_workflow::<workflows::remove_components::RemoveComponentsWorkflow>()
+            .register_workflow::<workflows::rollback::RollbackWorkflow>()
+            .register_workflow::<workflows::update_secrets::UpdateSecretsWorkflow>()
             .register_workflow::<workflows::gitops_publish::GitopsPublishWorkflow>()
             .build(),
     };
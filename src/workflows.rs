@@ -1,16 +1,20 @@
 use crate::{
     activities::UpdateGitopsImageInput,
-    api::WorkflowStatus,
+    api::{ApprovalDecision, ApprovalStatus, WorkflowStatus},
     workflows::{
         add_app::AddAppInput, create_app::CreateAppInput, delete_app::DeleteAppInput,
-        forgejo_bootstrap::ForgejoBootstrapInput, push_to_deploy::PushToDeployInput,
+        forgejo_bootstrap::ForgejoBootstrapInput, preview::PreviewInput, promote::PromoteInput,
+        push_to_deploy::PushToDeployInput, remove_components::RemoveComponentsInput,
+        rollback::RollbackInput, update_secrets::UpdateSecretsInput,
     },
 };
-use anyhow::{Context, Result, ensure};
+use anyhow::{Context, Result, bail, ensure};
+use serde::de::DeserializeOwned;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use temporalio_client::{
-    Client, NamespacedClient, WorkflowStartOptions, WorkflowStartSignal,
-    errors::WorkflowStartError, grpc::WorkflowService, tonic::Code, tonic::IntoRequest,
+    Client, NamespacedClient, WorkflowQueryOptions, WorkflowSignalOptions, WorkflowStartOptions,
+    WorkflowStartSignal, errors::WorkflowStartError, grpc::WorkflowService, tonic::Code,
+    tonic::IntoRequest,
 };
 use temporalio_common::{
     data_converters::SerializationContextData,
@@ -19,12 +23,13 @@ use temporalio_common::{
         temporal::api::{
             common::v1 as common_proto,
             enums::v1::{TaskQueueKind, WorkflowIdConflictPolicy, WorkflowIdReusePolicy},
+            history::v1::history_event,
             schedule::v1 as schedule_proto,
             taskqueue::v1 as taskqueue_proto,
             workflow::v1 as workflow_proto,
             workflowservice::v1::{
                 CreateScheduleRequest, DeleteScheduleRequest, DescribeWorkflowExecutionRequest,
-                UpdateScheduleRequest,
+                GetWorkflowExecutionHistoryRequest, UpdateScheduleRequest,
             },
         },
     },
@@ -37,7 +42,12 @@ pub mod delete_app;
 pub mod forgejo_bootstrap;
 pub mod gitops_publish;
 mod options;
+pub mod preview;
+pub mod promote;
 pub mod push_to_deploy;
+pub mod remove_components;
+pub mod rollback;
+pub mod update_secrets;
 
 const FORGEJO_BOOTSTRAP_SCHEDULE_ID: &str = "forgejo-bootstrap";
 const FORGEJO_BOOTSTRAP_WORKFLOW_ID_PREFIX: &str = "forgejo-bootstrap";
@@ -100,6 +110,86 @@ pub async fn start_delete_app_workflow(
     handle_start_result(result.map(|_| ()))
 }
 
+pub async fn start_promote_workflow(
+    client: &Client,
+    id: String,
+    input: PromoteInput,
+) -> Result<()> {
+    let result = client
+        .start_workflow(
+            promote::PromoteWorkflow::run,
+            input,
+            WorkflowStartOptions::new("main", id).build(),
+        )
+        .await;
+
+    handle_start_result(result.map(|_| ()))
+}
+
+pub async fn start_rollback_workflow(
+    client: &Client,
+    id: String,
+    input: RollbackInput,
+) -> Result<()> {
+    let result = client
+        .start_workflow(
+            rollback::RollbackWorkflow::run,
+            input,
+            WorkflowStartOptions::new("main", id).build(),
+        )
+        .await;
+
+    handle_start_result(result.map(|_| ()))
+}
+
+pub async fn start_remove_components_workflow(
+    client: &Client,
+    id: String,
+    input: RemoveComponentsInput,
+) -> Result<()> {
+    let result = client
+        .start_workflow(
+            remove_components::RemoveComponentsWorkflow::run,
+            input,
+            WorkflowStartOptions::new("main", id).build(),
+        )
+        .await;
+
+    handle_start_result(result.map(|_| ()))
+}
+
+pub async fn start_update_secrets_workflow(
+    client: &Client,
+    id: String,
+    input: UpdateSecretsInput,
+) -> Result<()> {
+    let result = client
+        .start_workflow(
+            update_secrets::UpdateSecretsWorkflow::run,
+            input,
+            WorkflowStartOptions::new("main", id).build(),
+        )
+        .await;
+
+    handle_start_result(result.map(|_| ()))
+}
+
+pub async fn start_preview_workflow(
+    client: &Client,
+    id: String,
+    input: PreviewInput,
+) -> Result<()> {
+    let result = client
+        .start_workflow(
+            preview::PreviewWorkflow::run,
+            input,
+            WorkflowStartOptions::new("main", id).build(),
+        )
+        .await;
+
+    handle_start_result(result.map(|_| ()))
+}
+
 pub async fn describe_workflow(
     client: &Client,
     workflow_id: String,
@@ -124,15 +214,169 @@ pub async fn describe_workflow(
         .workflow_execution_info
         .context("Temporal response did not include workflow execution info")?;
 
+    let is_push_to_deploy = info.r#type.as_ref().is_some_and(|workflow_type| {
+        workflow_type.name == push_to_deploy::PushToDeployWorkflow::name()
+    });
+    let approval = if is_push_to_deploy && workflow_status(info.status) == "running" {
+        query_approval(client, &workflow_id)
+            .await
+            .inspect_err(|error| warn!(error = %error, "failed to query workflow approval"))
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+
     Ok(WorkflowStatus {
         workflow_id,
         status: workflow_status(info.status).to_string(),
         url,
         result: None,
         error: None,
+        approval,
     })
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowScope {
+    Tenant(String),
+    SourceRepo { repo: String, environment: String },
+    Platform,
+}
+
+pub async fn workflow_scope(client: &Client, workflow_id: &str) -> Result<WorkflowScope> {
+    let response = WorkflowService::get_workflow_execution_history(
+        &mut client.clone(),
+        GetWorkflowExecutionHistoryRequest {
+            namespace: client.namespace(),
+            execution: Some(common_proto::WorkflowExecution {
+                workflow_id: workflow_id.to_string(),
+                run_id: String::new(),
+            }),
+            maximum_page_size: 1,
+            ..Default::default()
+        }
+        .into_request(),
+    )
+    .await
+    .context("failed to read Temporal workflow history")?
+    .into_inner();
+
+    let Some(history_event::Attributes::WorkflowExecutionStartedEventAttributes(started)) =
+        response
+            .history
+            .and_then(|history| history.events.into_iter().next())
+            .and_then(|event| event.attributes)
+    else {
+        bail!("{workflow_id} has no workflow started event");
+    };
+    let workflow_type = started
+        .workflow_type
+        .map(|workflow_type| workflow_type.name)
+        .unwrap_or_default();
+    let payloads = started
+        .input
+        .map(|input| input.payloads)
+        .unwrap_or_default();
+
+    let scope = if workflow_type == push_to_deploy::PushToDeployWorkflow::name() {
+        let input: PushToDeployInput = decode_workflow_input(client, payloads).await?;
+        match push_to_deploy::git_source_repo(&input.source) {
+            Some((owner, name)) => WorkflowScope::SourceRepo {
+                repo: format!("{owner}/{name}"),
+                environment: input.environment,
+            },
+            None => WorkflowScope::Platform,
+        }
+    } else if workflow_type == preview::PreviewWorkflow::name() {
+        let input: PreviewInput = decode_workflow_input(client, payloads).await?;
+        match push_to_deploy::git_source_repo(&input.source) {
+            Some((owner, name)) => WorkflowScope::SourceRepo {
+                repo: format!("{owner}/{name}"),
+                environment: crate::gitops::PREVIEW_BASE_ENVIRONMENT.to_string(),
+            },
+            None => WorkflowScope::Platform,
+        }
+    } else if workflow_type == create_app::CreateAppWorkflow::name() {
+        let input: CreateAppInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else if workflow_type == add_app::AddAppWorkflow::name() {
+        let input: AddAppInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else if workflow_type == delete_app::DeleteAppWorkflow::name() {
+        let input: DeleteAppInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else if workflow_type == promote::PromoteWorkflow::name() {
+        let input: PromoteInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else if workflow_type == rollback::RollbackWorkflow::name() {
+        let input: RollbackInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else if workflow_type == remove_components::RemoveComponentsWorkflow::name() {
+        let input: RemoveComponentsInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else if workflow_type == update_secrets::UpdateSecretsWorkflow::name() {
+        let input: UpdateSecretsInput = decode_workflow_input(client, payloads).await?;
+        WorkflowScope::Tenant(input.request.tenant)
+    } else {
+        WorkflowScope::Platform
+    };
+
+    Ok(scope)
+}
+
+async fn decode_workflow_input<T: DeserializeOwned + 'static>(
+    client: &Client,
+    payloads: Vec<common_proto::Payload>,
+) -> Result<T> {
+    client
+        .options()
+        .data_converter
+        .from_payloads(&SerializationContextData::Workflow, payloads)
+        .await
+        .context("failed to decode workflow input")
+}
+
+pub async fn query_approval(client: &Client, workflow_id: &str) -> Result<Option<ApprovalStatus>> {
+    client
+        .get_workflow_handle::<push_to_deploy::PushToDeployWorkflow>(workflow_id)
+        .query(
+            push_to_deploy::PushToDeployWorkflow::approval,
+            (),
+            WorkflowQueryOptions::default(),
+        )
+        .await
+        .context("failed to query workflow approval")
+}
+
+pub async fn signal_approval(
+    client: &Client,
+    workflow_id: &str,
+    approved: bool,
+    decision: ApprovalDecision,
+) -> Result<()> {
+    let handle = client.get_workflow_handle::<push_to_deploy::PushToDeployWorkflow>(workflow_id);
+    let result = if approved {
+        handle
+            .signal(
+                push_to_deploy::PushToDeployWorkflow::approve,
+                decision,
+                WorkflowSignalOptions::default(),
+            )
+            .await
+    } else {
+        handle
+            .signal(
+                push_to_deploy::PushToDeployWorkflow::reject,
+                decision,
+                WorkflowSignalOptions::default(),
+            )
+            .await
+    };
+
+    result.context("failed to signal workflow approval")
+}
+
 pub async fn signal_gitops_publish(
     client: &Client,
     id: String,
@@ -16,6 +16,8 @@ pub struct AddAppInput {
     pub gitops_revision: String,
     pub registry: String,
     pub request: CreateAppRequest,
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 #[workflow]
@@ -45,6 +47,7 @@ impl AddAppWorkflow {
                     revision: input.gitops_revision,
                     registry: input.registry,
                     request: input.request,
+                    dry_run: input.dry_run,
                 },
                 command_activity_options(Duration::from_secs(900)),
             )
@@ -2,8 +2,7 @@ use std::time::Duration;
 
 use super::options::command_activity_options;
 use crate::activities::{
-    ForgejoCommitStatusTarget, ForgejoCreateCommitStatusInput, PlatformActivities,
-    UpdateGitopsImageInput,
+    CommitStatusTarget, CreateCommitStatusInput, PlatformActivities, UpdateGitopsImageInput,
 };
 use temporalio_macros::{workflow, workflow_methods};
 use temporalio_sdk::{SyncWorkflowContext, WorkflowContext, WorkflowContextView, WorkflowResult};
@@ -99,7 +98,7 @@ impl GitopsPublishWorkflow {
 
 async fn set_commit_status(
     ctx: &mut WorkflowContext<GitopsPublishWorkflow>,
-    target: Option<ForgejoCommitStatusTarget>,
+    target: Option<CommitStatusTarget>,
     state: &str,
     description: &str,
 ) {
@@ -109,8 +108,8 @@ async fn set_commit_status(
 
     let result = ctx
         .start_activity(
-            PlatformActivities::forgejo_create_commit_status,
-            ForgejoCreateCommitStatusInput {
+            PlatformActivities::create_commit_status,
+            CreateCommitStatusInput {
                 target,
                 state: state.to_string(),
                 description: description.to_string(),
@@ -121,6 +120,23 @@ async fn set_commit_status(
     if let Err(error) = result
         && !ctx.is_replaying()
     {
-        warn!(error = %error, "failed to create Forgejo commit status");
+        warn!(error = %error, "failed to create commit status");
     }
 }
+
+pub(super) fn gitops_publish_workflow_id(revision: &str) -> String {
+    format!("gitops-publisher-{}", sanitize_workflow_id(revision))
+}
+
+fn sanitize_workflow_id(input: &str) -> String {
+    input
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.' {
+                ch
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use super::{
+    gitops_publish::gitops_publish_workflow_id,
+    options::command_activity_options,
+    push_to_deploy::{SOURCE_COMPONENTS_PATCH, changed_source_images, git_source_repo},
+};
+use crate::{
+    activities::{
+        CommentPullRequestInput, CommitStatusTarget, CreateCommitStatusInput,
+        CreatePreviewEnvironmentInput, EnqueueGitopsPublishInput, PlanSourceComponentsInput,
+        PlatformActivities, PublishImageFromSourceInput, PullRequestTarget, UpdateGitopsImageInput,
+    },
+    core::app::{image::Image, source::Source},
+};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use temporalio_macros::{workflow, workflow_methods};
+use temporalio_sdk::{WorkflowContext, WorkflowContextView, WorkflowResult};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewInput {
+    pub source: Source,
+    pub number: u64,
+    pub domain: String,
+    pub gitops_url: String,
+    pub gitops_revision: String,
+    pub registry: String,
+    #[serde(default)]
+    pub commit_status: Option<CommitStatusTarget>,
+    #[serde(default)]
+    pub pull_request: Option<PullRequestTarget>,
+}
+
+#[workflow]
+pub struct PreviewWorkflow {
+    input: PreviewInput,
+}
+
+#[workflow_methods]
+impl PreviewWorkflow {
+    #[init]
+    fn new(_ctx: &WorkflowContextView, input: PreviewInput) -> Self {
+        Self { input }
+    }
+
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>) -> WorkflowResult<Vec<Image>> {
+        let input = ctx.state(|state| state.input.clone());
+        let Some((source_owner, source_repo_name)) = git_source_repo(&input.source) else {
+            return Err(anyhow!("preview environments require a Git source").into());
+        };
+        let source_repo = format!("{source_owner}/{source_repo_name}");
+        if !ctx.is_replaying() {
+            info!(repo = %source_repo, number = input.number, "deploying preview environment");
+        }
+
+        let preview = ctx
+            .start_activity(
+                PlatformActivities::create_preview_environment,
+                CreatePreviewEnvironmentInput {
+                    url: input.gitops_url.clone(),
+                    revision: input.gitops_revision.clone(),
+                    registry: input.registry.clone(),
+                    source_repo: source_repo.clone(),
+                    number: input.number,
+                    domain: input.domain.clone(),
+                },
+                command_activity_options(Duration::from_secs(900)),
+            )
+            .await?;
+        if preview.hostnames.is_empty() {
+            if !ctx.is_replaying() {
+                info!(repo = %source_repo, "no production environment to preview");
+            }
+            return Ok(Vec::new());
+        }
+
+        set_commit_status(
+            ctx,
+            input.commit_status.clone(),
+            "pending",
+            "Preview workflow started",
+        )
+        .await;
+
+        let components_result = if ctx.patched(SOURCE_COMPONENTS_PATCH) {
+            ctx.start_activity(
+                PlatformActivities::plan_source_components,
+                PlanSourceComponentsInput {
+                    source: input.source.clone(),
+                    gitops_url: input.gitops_url.clone(),
+                    gitops_revision: input.gitops_revision.clone(),
+                    registry: input.registry.clone(),
+                    environment: preview.environment.clone(),
+                },
+                command_activity_options(Duration::from_secs(600)),
+            )
+            .await
+        } else {
+            Ok(Vec::new())
+        };
+        let components = match components_result {
+            Ok(components) => components,
+            Err(error) => {
+                set_commit_status(
+                    ctx,
+                    input.commit_status.clone(),
+                    "failure",
+                    "Preview component planning failed",
+                )
+                .await;
+                return Err(error.into());
+            }
+        };
+
+        let mut images = Vec::new();
+        for source_image in changed_source_images(&source_owner, &source_repo_name, &components) {
+            let image_result = ctx
+                .start_activity(
+                    PlatformActivities::publish_image_from_source,
+                    PublishImageFromSourceInput {
+                        source: input.source.clone(),
+                        registry: input.registry.clone(),
+                        image_owner: source_image.image_owner(),
+                        image_repository: source_image.image_repository(),
+                        component: source_image.component.clone(),
+                    },
+                    command_activity_options(Duration::from_secs(1200)),
+                )
+                .await;
+            match image_result {
+                Ok(image) => images.push(image),
+                Err(error) => {
+                    set_commit_status(
+                        ctx,
+                        input.commit_status.clone(),
+                        "failure",
+                        "Preview image build failed",
+                    )
+                    .await;
+                    return Err(error.into());
+                }
+            }
+        }
+
+        for image in &images {
+            let enqueue_result = ctx
+                .start_activity(
+                    PlatformActivities::enqueue_gitops_publish,
+                    EnqueueGitopsPublishInput {
+                        workflow_id: gitops_publish_workflow_id(&input.gitops_revision),
+                        update: UpdateGitopsImageInput {
+                            url: input.gitops_url.clone(),
+                            revision: input.gitops_revision.clone(),
+                            source_repo: source_repo.clone(),
+                            environment: preview.environment.clone(),
+                            image: image.clone(),
+                            tenant: None,
+                            project: None,
+                            commit_status: input.commit_status.clone(),
+                            approved_by: None,
+                        },
+                    },
+                    command_activity_options(Duration::from_secs(300)),
+                )
+                .await;
+            if let Err(error) = enqueue_result {
+                set_commit_status(
+                    ctx,
+                    input.commit_status.clone(),
+                    "failure",
+                    "Preview GitOps publish failed to queue",
+                )
+                .await;
+                return Err(error.into());
+            }
+        }
+
+        if preview.created
+            && let Some(target) = input.pull_request.clone()
+        {
+            let result = ctx
+                .start_activity(
+                    PlatformActivities::comment_pull_request,
+                    CommentPullRequestInput {
+                        target,
+                        body: preview_comment(&preview.environment, &preview.hostnames),
+                    },
+                    command_activity_options(Duration::from_secs(30)),
+                )
+                .await;
+            if let Err(error) = result
+                && !ctx.is_replaying()
+            {
+                warn!(error = %error, "failed to comment preview URL on pull request");
+            }
+        }
+
+        Ok(images)
+    }
+}
+
+fn preview_comment(environment: &str, hostnames: &[String]) -> String {
+    let mut body = format!("Preview environment `{environment}` is deploying:\n");
+    for hostname in hostnames {
+        body.push_str(&format!("\n- https://{hostname}"));
+    }
+    body
+}
+
+async fn set_commit_status(
+    ctx: &mut WorkflowContext<PreviewWorkflow>,
+    target: Option<CommitStatusTarget>,
+    state: &str,
+    description: &str,
+) {
+    let Some(target) = target else {
+        return;
+    };
+
+    let result = ctx
+        .start_activity(
+            PlatformActivities::create_commit_status,
+            CreateCommitStatusInput {
+                target,
+                state: state.to_string(),
+                description: description.to_string(),
+            },
+            command_activity_options(Duration::from_secs(30)),
+        )
+        .await;
+    if let Err(error) = result
+        && !ctx.is_replaying()
+    {
+        warn!(error = %error, "failed to create commit status");
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use super::{gitops_publish::gitops_publish_workflow_id, options::command_activity_options};
+use crate::{
+    activities::{
+        CommitStatusTarget, EnqueueGitopsPublishInput, FindPromotionImagesInput,
+        ForgejoCommitStatusTarget, PlatformActivities, UpdateGitopsImageInput,
+    },
+    api::PromoteAppRequest,
+    core::app::image::Image,
+};
+use serde::{Deserialize, Serialize};
+use temporalio_macros::{workflow, workflow_methods};
+use temporalio_sdk::{WorkflowContext, WorkflowContextView, WorkflowResult};
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoteInput {
+    pub gitops_url: String,
+    pub gitops_revision: String,
+    pub registry: String,
+    pub request: PromoteAppRequest,
+    #[serde(default)]
+    pub forgejo_url: Option<String>,
+    #[serde(default)]
+    pub status_url: Option<String>,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+}
+
+#[workflow]
+pub struct PromoteWorkflow {
+    input: PromoteInput,
+}
+
+#[workflow_methods]
+impl PromoteWorkflow {
+    #[init]
+    fn new(_ctx: &WorkflowContextView, input: PromoteInput) -> Self {
+        Self { input }
+    }
+
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>) -> WorkflowResult<Vec<Image>> {
+        let input = ctx.state(|state| state.input.clone());
+        if !ctx.is_replaying() {
+            info!(
+                app = %input.request.app_path(),
+                from = %input.request.from,
+                to = %input.request.to,
+                "promoting app images"
+            );
+        }
+
+        let images = ctx
+            .start_activity(
+                PlatformActivities::find_promotion_images,
+                FindPromotionImagesInput {
+                    url: input.gitops_url.clone(),
+                    revision: input.gitops_revision.clone(),
+                    registry: input.registry.clone(),
+                    request: input.request.clone(),
+                },
+                command_activity_options(Duration::from_secs(300)),
+            )
+            .await?;
+
+        for promotion in &images {
+            let commit_status = input.forgejo_url.clone().zip(input.status_url.clone()).map(
+                |(forgejo_url, target_url)| {
+                    CommitStatusTarget::Forgejo(ForgejoCommitStatusTarget {
+                        forgejo_url,
+                        repo: promotion.source_repo.clone(),
+                        sha: promotion.image.tag.clone(),
+                        target_url,
+                    })
+                },
+            );
+
+            ctx.start_activity(
+                PlatformActivities::enqueue_gitops_publish,
+                EnqueueGitopsPublishInput {
+                    workflow_id: gitops_publish_workflow_id(&input.gitops_revision),
+                    update: UpdateGitopsImageInput {
+                        url: input.gitops_url.clone(),
+                        revision: input.gitops_revision.clone(),
+                        source_repo: promotion.source_repo.clone(),
+                        environment: input.request.to.clone(),
+                        image: promotion.image.clone(),
+                        tenant: Some(input.request.tenant.clone()),
+                        project: Some(input.request.project.clone()),
+                        commit_status,
+                        approved_by: input.approved_by.clone(),
+                    },
+                },
+                command_activity_options(Duration::from_secs(300)),
+            )
+            .await?;
+        }
+
+        if !ctx.is_replaying() {
+            info!(images = images.len(), "queued promotion GitOps publish");
+        }
+
+        Ok(images
+            .into_iter()
+            .map(|promotion| promotion.image)
+            .collect())
+    }
+}
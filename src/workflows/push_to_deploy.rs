@@ -1,14 +1,19 @@
-use std::time::Duration;
+use std::{collections::BTreeSet, time::Duration};
 
-use super::options::command_activity_options;
+use super::{gitops_publish::gitops_publish_workflow_id, options::command_activity_options};
 use crate::activities::*;
+use crate::api::{ApprovalDecision, ApprovalState, ApprovalStatus};
 use crate::core::app::{image::Image, source::Source};
+use crate::gitops::SourceImage;
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use temporalio_macros::{workflow, workflow_methods};
-use temporalio_sdk::{WorkflowContext, WorkflowContextView, WorkflowResult};
+use temporalio_sdk::{SyncWorkflowContext, WorkflowContext, WorkflowContextView, WorkflowResult};
 use tracing::{info, warn};
 
+pub(super) const SOURCE_COMPONENTS_PATCH: &str = "source-components";
+const IMAGE_SCAN_PATCH: &str = "image-scan";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PushToDeployInput {
     pub source: Source,
@@ -17,23 +22,31 @@ pub struct PushToDeployInput {
     pub environment: String,
     pub registry: String,
     #[serde(default)]
-    pub commit_status: Option<ForgejoCommitStatusTarget>,
+    pub commit_status: Option<CommitStatusTarget>,
+    #[serde(default)]
+    pub requires_approval: bool,
+    #[serde(default)]
+    pub image_scan: Option<ImageScanPolicy>,
 }
 
 #[workflow]
 pub struct PushToDeployWorkflow {
     input: PushToDeployInput,
+    approval: Option<ApprovalStatus>,
 }
 
 #[workflow_methods]
 impl PushToDeployWorkflow {
     #[init]
     fn new(_ctx: &WorkflowContextView, input: PushToDeployInput) -> Self {
-        Self { input }
+        Self {
+            input,
+            approval: None,
+        }
     }
 
     #[run]
-    pub async fn run(ctx: &mut WorkflowContext<Self>) -> WorkflowResult<Option<Image>> {
+    pub async fn run(ctx: &mut WorkflowContext<Self>) -> WorkflowResult<Vec<Image>> {
         let input = ctx.state(|state| state.input.clone());
         if !ctx.is_replaying() {
             info!("starting push to deploy: {input:?}");
@@ -68,7 +81,7 @@ impl PushToDeployWorkflow {
             if !ctx.is_replaying() {
                 info!(source_repo = %source_repo, environment = %input.environment, "no matching app environment");
             }
-            return Ok(None);
+            return Ok(Vec::new());
         }
 
         set_commit_status(
@@ -79,40 +92,183 @@ impl PushToDeployWorkflow {
         )
         .await;
 
-        let image_result = ctx
-            .start_activity(
-                PlatformActivities::publish_image_from_source,
-                PublishImageFromSourceInput {
+        let components_result = if ctx.patched(SOURCE_COMPONENTS_PATCH) {
+            ctx.start_activity(
+                PlatformActivities::plan_source_components,
+                PlanSourceComponentsInput {
                     source: input.source.clone(),
+                    gitops_url: input.gitops_url.clone(),
+                    gitops_revision: input.gitops_revision.clone(),
                     registry: input.registry.clone(),
-                    image_owner: format!("apps/{source_owner}"),
-                    image_repository: source_repo_name.clone(),
+                    environment: input.environment.clone(),
                 },
-                command_activity_options(Duration::from_secs(1200)),
+                command_activity_options(Duration::from_secs(600)),
             )
-            .await;
-        let image = match image_result {
-            Ok(image) => image,
+            .await
+        } else {
+            Ok(Vec::new())
+        };
+        let components = match components_result {
+            Ok(components) => components,
             Err(error) => {
                 set_commit_status(
                     ctx,
                     input.commit_status.clone(),
                     "failure",
-                    "Image build failed",
+                    "Component planning failed",
                 )
                 .await;
                 return Err(error.into());
             }
         };
+        let source_images = changed_source_images(&source_owner, &source_repo_name, &components);
+        if source_images.is_empty() {
+            if !ctx.is_replaying() {
+                info!(source_repo = %source_repo, "no components changed");
+            }
+            set_commit_status(
+                ctx,
+                input.commit_status.clone(),
+                "success",
+                "No components changed",
+            )
+            .await;
+            return Ok(Vec::new());
+        }
 
-        let update = UpdateGitopsImageInput {
-            url: input.gitops_url.clone(),
-            revision: input.gitops_revision.clone(),
-            source_repo,
-            environment: input.environment.clone(),
-            image: image.clone(),
-            commit_status: input.commit_status.clone(),
-        };
+        let mut images = Vec::new();
+        for source_image in source_images {
+            let image_result = ctx
+                .start_activity(
+                    PlatformActivities::publish_image_from_source,
+                    PublishImageFromSourceInput {
+                        source: input.source.clone(),
+                        registry: input.registry.clone(),
+                        image_owner: source_image.image_owner(),
+                        image_repository: source_image.image_repository(),
+                        component: source_image.component.clone(),
+                    },
+                    command_activity_options(Duration::from_secs(1200)),
+                )
+                .await;
+            match image_result {
+                Ok(image) => images.push(image),
+                Err(error) => {
+                    set_commit_status(
+                        ctx,
+                        input.commit_status.clone(),
+                        "failure",
+                        "Image build failed",
+                    )
+                    .await;
+                    return Err(error.into());
+                }
+            }
+        }
+
+        if let Some(policy) = input.image_scan
+            && ctx.patched(IMAGE_SCAN_PATCH)
+        {
+            for image in &images {
+                let scan_result = ctx
+                    .start_activity(
+                        PlatformActivities::scan_image,
+                        ScanImageInput {
+                            image: image.clone(),
+                        },
+                        command_activity_options(Duration::from_secs(900)),
+                    )
+                    .await;
+                let report = match scan_result {
+                    Ok(report) => report,
+                    Err(error) => {
+                        set_commit_status(
+                            ctx,
+                            input.commit_status.clone(),
+                            "failure",
+                            "Image scan failed",
+                        )
+                        .await;
+                        return Err(error.into());
+                    }
+                };
+                if report.blocks(&policy) {
+                    set_commit_status(
+                        ctx,
+                        input.commit_status.clone(),
+                        "failure",
+                        &format!("Blocked by image scan: {}", report.vulnerabilities),
+                    )
+                    .await;
+                    return Err(anyhow!(
+                        "{} has vulnerabilities at or above the {} threshold: {}",
+                        report.image,
+                        input.environment,
+                        report.vulnerabilities
+                    )
+                    .into());
+                }
+                set_commit_status(
+                    ctx,
+                    input.commit_status.clone(),
+                    "pending",
+                    &format!("Image scan: {}", report.vulnerabilities),
+                )
+                .await;
+            }
+        }
+
+        let mut approved_by = None;
+        if input.requires_approval {
+            set_commit_status(
+                ctx,
+                input.commit_status.clone(),
+                "pending",
+                "Image built; waiting for approval",
+            )
+            .await;
+            if !ctx.is_replaying() {
+                info!(environment = %input.environment, "waiting for deployment approval");
+            }
+
+            let tenants = targets
+                .iter()
+                .map(|target| target.tenant.clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            ctx.state_mut(|state| {
+                state.approval = Some(ApprovalStatus {
+                    state: ApprovalState::Pending,
+                    environment: state.input.environment.clone(),
+                    approver: None,
+                    tenants,
+                });
+            });
+            ctx.wait_condition(|state| {
+                !state
+                    .approval
+                    .as_ref()
+                    .is_some_and(ApprovalStatus::is_pending)
+            })
+            .await;
+            let approval = ctx.state(|state| state.approval.clone());
+            let approver = approval
+                .as_ref()
+                .and_then(|approval| approval.approver.clone())
+                .unwrap_or_default();
+            if approval.is_none_or(|approval| approval.state != ApprovalState::Approved) {
+                set_commit_status(
+                    ctx,
+                    input.commit_status.clone(),
+                    "failure",
+                    "Deployment rejected",
+                )
+                .await;
+                return Err(anyhow!("deployment rejected by {approver}").into());
+            }
+            approved_by = Some(approver);
+        }
 
         set_commit_status(
             ctx,
@@ -122,36 +278,91 @@ impl PushToDeployWorkflow {
         )
         .await;
 
-        let enqueue_result = ctx
-            .start_activity(
-                PlatformActivities::enqueue_gitops_publish,
-                EnqueueGitopsPublishInput {
-                    workflow_id: gitops_publish_workflow_id(&input.gitops_revision),
-                    update,
-                },
-                command_activity_options(Duration::from_secs(300)),
-            )
-            .await;
-        if let Err(error) = enqueue_result {
-            set_commit_status(
-                ctx,
-                input.commit_status.clone(),
-                "failure",
-                "GitOps publish failed to queue",
-            )
-            .await;
-            return Err(error.into());
+        for image in &images {
+            let update = UpdateGitopsImageInput {
+                url: input.gitops_url.clone(),
+                revision: input.gitops_revision.clone(),
+                source_repo: source_repo.clone(),
+                environment: input.environment.clone(),
+                image: image.clone(),
+                tenant: None,
+                project: None,
+                commit_status: input.commit_status.clone(),
+                approved_by: approved_by.clone(),
+            };
+            let enqueue_result = ctx
+                .start_activity(
+                    PlatformActivities::enqueue_gitops_publish,
+                    EnqueueGitopsPublishInput {
+                        workflow_id: gitops_publish_workflow_id(&input.gitops_revision),
+                        update,
+                    },
+                    command_activity_options(Duration::from_secs(300)),
+                )
+                .await;
+            if let Err(error) = enqueue_result {
+                set_commit_status(
+                    ctx,
+                    input.commit_status.clone(),
+                    "failure",
+                    "GitOps publish failed to queue",
+                )
+                .await;
+                return Err(error.into());
+            }
         }
 
         if !ctx.is_replaying() {
-            info!("queued GitOps and apps OCI publish");
+            info!(images = images.len(), "queued GitOps and apps OCI publish");
         }
 
-        Ok(Some(image))
+        Ok(images)
+    }
+
+    #[signal(name = "approve")]
+    pub fn approve(&mut self, _ctx: &mut SyncWorkflowContext<Self>, input: ApprovalDecision) {
+        self.decide(ApprovalState::Approved, input);
+    }
+
+    #[signal(name = "reject")]
+    pub fn reject(&mut self, _ctx: &mut SyncWorkflowContext<Self>, input: ApprovalDecision) {
+        self.decide(ApprovalState::Rejected, input);
+    }
+
+    #[query(name = "approval")]
+    pub fn approval(&self, _ctx: &WorkflowContextView) -> Option<ApprovalStatus> {
+        self.approval.clone()
+    }
+}
+
+impl PushToDeployWorkflow {
+    fn decide(&mut self, state: ApprovalState, input: ApprovalDecision) {
+        if let Some(approval) = &mut self.approval
+            && approval.is_pending()
+        {
+            approval.state = state;
+            approval.approver = Some(input.approver);
+        }
     }
 }
 
-fn git_source_repo(source: &Source) -> Option<(String, String)> {
+pub(super) fn changed_source_images(
+    owner: &str,
+    repo: &str,
+    components: &[SourceComponent],
+) -> Vec<SourceImage> {
+    if components.is_empty() {
+        return vec![SourceImage::new(owner, repo, None)];
+    }
+
+    components
+        .iter()
+        .filter(|component| component.changed)
+        .map(|component| SourceImage::new(owner, repo, Some(&component.name)))
+        .collect()
+}
+
+pub(super) fn git_source_repo(source: &Source) -> Option<(String, String)> {
     match source {
         Source::Git { owner, name, .. } => Some((owner.clone(), name.clone())),
         Source::Docker(_) => None,
@@ -160,7 +371,7 @@ fn git_source_repo(source: &Source) -> Option<(String, String)> {
 
 async fn set_commit_status(
     ctx: &mut WorkflowContext<PushToDeployWorkflow>,
-    target: Option<ForgejoCommitStatusTarget>,
+    target: Option<CommitStatusTarget>,
     state: &str,
     description: &str,
 ) {
@@ -170,8 +381,8 @@ async fn set_commit_status(
 
     let result = ctx
         .start_activity(
-            PlatformActivities::forgejo_create_commit_status,
-            ForgejoCreateCommitStatusInput {
+            PlatformActivities::create_commit_status,
+            CreateCommitStatusInput {
                 target,
                 state: state.to_string(),
                 description: description.to_string(),
@@ -182,23 +393,6 @@ async fn set_commit_status(
     if let Err(error) = result
         && !ctx.is_replaying()
     {
-        warn!(error = %error, "failed to create Forgejo commit status");
+        warn!(error = %error, "failed to create commit status");
     }
 }
-
-fn gitops_publish_workflow_id(revision: &str) -> String {
-    format!("gitops-publisher-{}", sanitize_workflow_id(revision))
-}
-
-fn sanitize_workflow_id(input: &str) -> String {
-    input
-        .chars()
-        .map(|ch| {
-            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.' {
-                ch
-            } else {
-                '-'
-            }
-        })
-        .collect()
-}
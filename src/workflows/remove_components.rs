@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use super::options::command_activity_options;
+use crate::{
+    activities::{PlatformActivities, RemoveGitopsComponentsInput, RemoveGitopsComponentsResult},
+    api::RemoveComponentsRequest,
+};
+use serde::{Deserialize, Serialize};
+use temporalio_macros::{workflow, workflow_methods};
+use temporalio_sdk::{WorkflowContext, WorkflowContextView, WorkflowResult};
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveComponentsInput {
+    pub gitops_url: String,
+    pub gitops_revision: String,
+    pub registry: String,
+    pub request: RemoveComponentsRequest,
+}
+
+#[workflow]
+pub struct RemoveComponentsWorkflow {
+    input: RemoveComponentsInput,
+}
+
+#[workflow_methods]
+impl RemoveComponentsWorkflow {
+    #[init]
+    fn new(_ctx: &WorkflowContextView, input: RemoveComponentsInput) -> Self {
+        Self { input }
+    }
+
+    #[run]
+    pub async fn run(
+        ctx: &mut WorkflowContext<Self>,
+    ) -> WorkflowResult<RemoveGitopsComponentsResult> {
+        let input = ctx.state(|state| state.input.clone());
+        if !ctx.is_replaying() {
+            info!(app = %input.request.app_path(), "removing app components");
+        }
+
+        let result = ctx
+            .start_activity(
+                PlatformActivities::remove_gitops_components,
+                RemoveGitopsComponentsInput {
+                    url: input.gitops_url,
+                    revision: input.gitops_revision,
+                    registry: input.registry,
+                    request: input.request,
+                },
+                command_activity_options(Duration::from_secs(900)),
+            )
+            .await?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use super::options::command_activity_options;
+use crate::{
+    activities::{PlatformActivities, RollbackGitopsAppInput, RollbackGitopsAppResult},
+    api::RollbackAppRequest,
+};
+use serde::{Deserialize, Serialize};
+use temporalio_macros::{workflow, workflow_methods};
+use temporalio_sdk::{WorkflowContext, WorkflowContextView, WorkflowResult};
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackInput {
+    pub gitops_url: String,
+    pub gitops_revision: String,
+    pub registry: String,
+    pub request: RollbackAppRequest,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+}
+
+#[workflow]
+pub struct RollbackWorkflow {
+    input: RollbackInput,
+}
+
+#[workflow_methods]
+impl RollbackWorkflow {
+    #[init]
+    fn new(_ctx: &WorkflowContextView, input: RollbackInput) -> Self {
+        Self { input }
+    }
+
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>) -> WorkflowResult<RollbackGitopsAppResult> {
+        let input = ctx.state(|state| state.input.clone());
+        if !ctx.is_replaying() {
+            info!(app = %input.request.app_path(), "rolling back app environment");
+        }
+
+        let result = ctx
+            .start_activity(
+                PlatformActivities::rollback_gitops_app,
+                RollbackGitopsAppInput {
+                    url: input.gitops_url,
+                    revision: input.gitops_revision,
+                    registry: input.registry,
+                    request: input.request,
+                    approved_by: input.approved_by,
+                },
+                command_activity_options(Duration::from_secs(900)),
+            )
+            .await?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use super::options::command_activity_options;
+use crate::{
+    activities::{PlatformActivities, UpdateGitopsSecretsInput, UpdateGitopsSecretsResult},
+    api::UpdateSecretsRequest,
+};
+use serde::{Deserialize, Serialize};
+use temporalio_macros::{workflow, workflow_methods};
+use temporalio_sdk::{WorkflowContext, WorkflowContextView, WorkflowResult};
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSecretsInput {
+    pub gitops_url: String,
+    pub gitops_revision: String,
+    pub registry: String,
+    pub request: UpdateSecretsRequest,
+}
+
+#[workflow]
+pub struct UpdateSecretsWorkflow {
+    input: UpdateSecretsInput,
+}
+
+#[workflow_methods]
+impl UpdateSecretsWorkflow {
+    #[init]
+    fn new(_ctx: &WorkflowContextView, input: UpdateSecretsInput) -> Self {
+        Self { input }
+    }
+
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>) -> WorkflowResult<UpdateGitopsSecretsResult> {
+        let input = ctx.state(|state| state.input.clone());
+        if !ctx.is_replaying() {
+            info!(app = %input.request.app_path(), "updating app secrets");
+        }
+
+        let result = ctx
+            .start_activity(
+                PlatformActivities::update_gitops_secrets,
+                UpdateGitopsSecretsInput {
+                    url: input.gitops_url,
+                    revision: input.gitops_revision,
+                    registry: input.registry,
+                    request: input.request,
+                },
+                command_activity_options(Duration::from_secs(900)),
+            )
+            .await?;
+
+        Ok(result)
+    }
+}